@@ -0,0 +1,74 @@
+/*
+:project: telegram-onedrive
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use super::{utils::validate_root_path, OneDriveClient};
+use anyhow::{anyhow, Result};
+use onedrive_api::{DriveLocation, OneDrive as Client};
+
+impl OneDriveClient {
+    // a client with the token of the account, the current account if none,
+    // so that a task keeps its account after the current one is changed
+    pub(super) async fn get_client(&self, account: Option<&str>) -> Result<Client> {
+        let current_username = self.session.read().await.username.clone();
+
+        match account {
+            Some(account) if account != current_username => {
+                let _account_lock = self.account_lock.lock().await;
+
+                let mut session = self.session.read().await.load_user(account).await?;
+
+                if session.is_expired() {
+                    let token_response = self
+                        .get_token_using_refresh_token(&session.refresh_token)
+                        .await?;
+
+                    session.access_token = token_response.access_token;
+                    session.refresh_token = token_response.refresh_token.ok_or_else(|| {
+                        anyhow!("failed to receive onedrive refresh token of {}", account)
+                    })?;
+                    session.set_expiration_timestamp(token_response.expires_in_secs);
+
+                    session.save().await?;
+                }
+
+                Ok(Client::new(session.access_token, DriveLocation::me()))
+            }
+            _ => {
+                self.refresh_access_token().await?;
+
+                let access_token = self.session.read().await.access_token.clone();
+
+                Ok(Client::new(access_token, DriveLocation::me()))
+            }
+        }
+    }
+
+    // temp root path only applies to the current account
+    pub async fn get_account_root_path(
+        &self,
+        account: &str,
+        should_consume_temp: bool,
+    ) -> Result<String> {
+        let current_username = self.session.read().await.username.clone();
+
+        if account == current_username {
+            return self.get_root_path(should_consume_temp).await;
+        }
+
+        let root_path = self
+            .session
+            .read()
+            .await
+            .load_user(account)
+            .await?
+            .root_path;
+
+        validate_root_path(&root_path)?;
+
+        Ok(root_path)
+    }
+}
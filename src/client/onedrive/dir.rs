@@ -6,7 +6,11 @@
 */
 
 use super::{utils::validate_root_path, OneDriveClient};
-use anyhow::Result;
+use anyhow::{anyhow, Context, Result};
+use onedrive_api::{FileName, ItemLocation};
+use path_slash::PathBufExt;
+use reqwest::StatusCode;
+use std::path::Path;
 
 impl OneDriveClient {
     pub async fn get_root_path(&self, should_consume_temp: bool) -> Result<String> {
@@ -92,4 +96,87 @@ impl OneDriveClient {
 
         self.set_temp_root_path("").await
     }
+
+    // names of the sub directories sorted, and the number of files
+    pub async fn list_dir(&self, path: &str) -> Result<(Vec<String>, usize)> {
+        let item_location = ItemLocation::from_path(path)
+            .ok_or_else(|| anyhow!("directory path should start with /"))?;
+
+        let children = self
+            .get_client(None)
+            .await?
+            .list_children(item_location)
+            .await
+            .context(format!("failed to list directory {}", path))?;
+
+        let (dirs, files): (Vec<_>, Vec<_>) = children
+            .into_iter()
+            .partition(|drive_item| drive_item.folder.is_some());
+
+        let mut dir_names = dirs
+            .into_iter()
+            .filter_map(|drive_item| drive_item.name)
+            .collect::<Vec<_>>();
+        dir_names.sort_by_key(|name| name.to_lowercase());
+
+        Ok((dir_names, files.len()))
+    }
+
+    pub async fn create_dir(&self, parent_path: &str, name: &str) -> Result<()> {
+        let item_location = ItemLocation::from_path(parent_path)
+            .ok_or_else(|| anyhow!("directory path should start with /"))?;
+
+        let file_name =
+            FileName::new(name).ok_or_else(|| anyhow!("invalid directory name: {}", name))?;
+
+        self.get_client(None)
+            .await?
+            .create_folder(item_location, file_name)
+            .await
+            .context(format!("failed to create directory {}", name))?;
+
+        tracing::info!("created onedrive directory {} in {}", name, parent_path);
+
+        Ok(())
+    }
+
+    // missing parent directories are created as well
+    pub async fn create_dir_all(&self, path: &str, account: Option<&str>) -> Result<()> {
+        let client = self.get_client(account).await?;
+
+        let mut parent_path = "/".to_string();
+
+        for name in path.split('/').filter(|name| !name.is_empty()) {
+            let dir_path = Path::new(&parent_path)
+                .join(name)
+                .to_slash_lossy()
+                .to_string();
+
+            let item_location = ItemLocation::from_path(&dir_path)
+                .ok_or_else(|| anyhow!("directory path should start with /"))?;
+
+            match client.get_item(item_location).await {
+                Ok(_) => {}
+                Err(e) if e.status_code() == Some(StatusCode::NOT_FOUND) => {
+                    let parent_location = ItemLocation::from_path(&parent_path)
+                        .ok_or_else(|| anyhow!("directory path should start with /"))?;
+
+                    let file_name = FileName::new(name)
+                        .ok_or_else(|| anyhow!("invalid directory name: {}", name))?;
+
+                    client
+                        .create_folder(parent_location, file_name)
+                        .await
+                        .context(format!("failed to create directory {}", dir_path))?;
+
+                    tracing::info!("created onedrive directory {}", dir_path);
+                }
+                Err(e) => return Err(e).context(format!("failed to get directory {}", dir_path)),
+            }
+
+            parent_path = dir_path;
+        }
+
+        Ok(())
+    }
 }
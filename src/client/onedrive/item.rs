@@ -0,0 +1,91 @@
+/*
+:project: telegram-onedrive
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use super::OneDriveClient;
+use anyhow::{anyhow, Context, Result};
+use onedrive_api::{resource::DriveItem, ItemId, ItemLocation};
+use path_slash::PathBufExt;
+use reqwest::StatusCode;
+use std::path::Path;
+
+impl OneDriveClient {
+    // none if there is no item at the path
+    pub async fn get_item_from_path(
+        &self,
+        root_path: &str,
+        filename: &str,
+        account: Option<&str>,
+    ) -> Result<Option<DriveItem>> {
+        let file_path_obj = Path::new(root_path).join(filename);
+        let file_path = file_path_obj.to_slash_lossy();
+
+        let item_location = ItemLocation::from_path(&file_path)
+            .ok_or_else(|| anyhow!("file path does not start with /"))?;
+
+        self.get_item(item_location, account).await
+    }
+
+    // none if the item has been deleted
+    pub async fn get_item_from_id(
+        &self,
+        item_id: &str,
+        account: Option<&str>,
+    ) -> Result<Option<DriveItem>> {
+        let item_id = ItemId(item_id.to_string());
+
+        self.get_item(ItemLocation::from_id(&item_id), account)
+            .await
+    }
+
+    // the url is short-lived and needs no authorization
+    pub async fn get_download_url_from_path(
+        &self,
+        root_path: &str,
+        filename: &str,
+        account: Option<&str>,
+    ) -> Result<String> {
+        let file_path_obj = Path::new(root_path).join(filename);
+        let file_path = file_path_obj.to_slash_lossy();
+
+        let item_location = ItemLocation::from_path(&file_path)
+            .ok_or_else(|| anyhow!("file path does not start with /"))?;
+
+        self.get_client(account)
+            .await?
+            .get_item_download_url(item_location)
+            .await
+            .context(format!("failed to get download url of {}", file_path))
+    }
+
+    async fn get_item(
+        &self,
+        item_location: ItemLocation<'_>,
+        account: Option<&str>,
+    ) -> Result<Option<DriveItem>> {
+        match self
+            .get_client(account)
+            .await?
+            .get_item(item_location)
+            .await
+        {
+            Ok(drive_item) => Ok(Some(drive_item)),
+            Err(e) if e.status_code() == Some(StatusCode::NOT_FOUND) => Ok(None),
+            Err(e) => Err(e).context("failed to get drive item"),
+        }
+    }
+}
+
+// quickXorHash is provided by both personal and business drives
+pub fn get_quick_xor_hash(drive_item: &DriveItem) -> Option<String> {
+    drive_item
+        .file
+        .as_ref()?
+        .get("hashes")?
+        .get("quickXorHash")?
+        .as_str()
+        .map(|hash| hash.to_string())
+}
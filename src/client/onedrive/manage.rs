@@ -0,0 +1,198 @@
+/*
+:project: telegram-onedrive
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use super::OneDriveClient;
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, SecondsFormat, Utc};
+use onedrive_api::{resource::DriveItem, FileName, ItemLocation};
+use path_slash::PathBufExt;
+use reqwest::{header, StatusCode};
+use serde_json::{json, Value};
+use std::path::Path;
+
+pub struct ShareLinkOptions {
+    // view or edit
+    pub link_type: String,
+    // anonymous or organization
+    pub scope: String,
+    pub expiration: Option<DateTime<Utc>>,
+    pub password: Option<String>,
+}
+
+impl OneDriveClient {
+    pub async fn list_items(&self, path: &str) -> Result<Vec<DriveItem>> {
+        self.get_client(None)
+            .await?
+            .list_children(to_item_location(path)?)
+            .await
+            .context(format!("failed to list {}", path))
+    }
+
+    // into the destination if it's a directory, otherwise to the destination path
+    pub async fn move_item(&self, source_path: &str, destination_path: &str) -> Result<()> {
+        let client = self.get_client(None).await?;
+
+        let is_dir = match client.get_item(to_item_location(destination_path)?).await {
+            Ok(drive_item) => drive_item.folder.is_some(),
+            Err(e) if e.status_code() == Some(StatusCode::NOT_FOUND) => false,
+            Err(e) => return Err(e).context(format!("failed to get {}", destination_path)),
+        };
+
+        let (parent_path, name) = if is_dir {
+            (destination_path.to_string(), None)
+        } else {
+            let (parent_path, name) = split_path(destination_path)?;
+
+            (parent_path, Some(name))
+        };
+
+        let file_name = match &name {
+            Some(name) => {
+                Some(FileName::new(name).ok_or_else(|| anyhow!("invalid name: {}", name))?)
+            }
+            None => None,
+        };
+
+        client
+            .move_(
+                to_item_location(source_path)?,
+                to_item_location(&parent_path)?,
+                file_name,
+            )
+            .await
+            .context(format!(
+                "failed to move {} to {}",
+                source_path, destination_path
+            ))?;
+
+        tracing::info!("moved {} to {}", source_path, destination_path);
+
+        Ok(())
+    }
+
+    pub async fn delete_item_from_path(&self, path: &str) -> Result<()> {
+        self.get_client(None)
+            .await?
+            .delete(to_item_location(path)?)
+            .await
+            .context(format!("failed to delete {}", path))?;
+
+        tracing::info!("deleted {}", path);
+
+        Ok(())
+    }
+
+    pub async fn create_share_link(
+        &self,
+        path: &str,
+        options: &ShareLinkOptions,
+    ) -> Result<String> {
+        let item_id = self
+            .get_client(None)
+            .await?
+            .get_item(to_item_location(path)?)
+            .await
+            .context(format!("failed to get {}", path))?
+            .id
+            .ok_or_else(|| anyhow!("drive item id not found"))?;
+
+        self.create_share_link_from_id(item_id.as_str(), None, options)
+            .await
+    }
+
+    // expiration is only supported by business accounts, and password by personal accounts
+    pub async fn create_share_link_from_id(
+        &self,
+        item_id: &str,
+        account: Option<&str>,
+        options: &ShareLinkOptions,
+    ) -> Result<String> {
+        let client = self.get_client(account).await?;
+
+        let url = format!(
+            "https://graph.microsoft.com/v1.0/me/drive/items/{}/createLink",
+            item_id
+        );
+
+        let mut body = json!({
+            "type": options.link_type,
+            "scope": options.scope,
+        });
+        if let Some(expiration) = options.expiration {
+            body["expirationDateTime"] =
+                Value::String(expiration.to_rfc3339_opts(SecondsFormat::Secs, true));
+        }
+        if let Some(password) = &options.password {
+            body["password"] = Value::String(password.clone());
+        }
+
+        let response = client
+            .client()
+            .post(url)
+            .header(
+                header::AUTHORIZATION,
+                format!("Bearer {}", client.access_token()),
+            )
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(body.to_string())
+            .send()
+            .await
+            .context("failed to send request for share link")?;
+
+        let status = response.status();
+
+        let content = response
+            .text()
+            .await
+            .context("failed to get response text for share link")?;
+
+        if !status.is_success() {
+            return Err(anyhow!(
+                "failed to create share link: {} {}",
+                status,
+                content
+            ));
+        }
+
+        let share_link = serde_json::from_str::<Value>(&content)
+            .context("failed to deserialize share link into Value")?;
+
+        let web_url = share_link
+            .get("link")
+            .and_then(|link| link.get("webUrl"))
+            .and_then(Value::as_str)
+            .ok_or_else(|| anyhow!("field link.webUrl not found in share link"))?
+            .to_string();
+
+        tracing::info!("created {} link of {}", options.link_type, item_id);
+
+        Ok(web_url)
+    }
+}
+
+fn to_item_location(path: &str) -> Result<ItemLocation<'_>> {
+    ItemLocation::from_path(path).ok_or_else(|| anyhow!("path should start with /: {}", path))
+}
+
+// parent directory and name
+pub fn split_path(path: &str) -> Result<(String, String)> {
+    let path = Path::new(path);
+
+    let parent_path = path
+        .parent()
+        .ok_or_else(|| anyhow!("path has no parent directory"))?
+        .to_slash_lossy()
+        .to_string();
+
+    let name = path
+        .file_name()
+        .ok_or_else(|| anyhow!("path has no name"))?
+        .to_string_lossy()
+        .to_string();
+
+    Ok((parent_path, name))
+}
@@ -5,9 +5,13 @@
 :license: MIT, see LICENSE for more details.
 */
 
+mod account;
 mod dir;
 mod drive;
 pub mod invalid_name;
+pub mod item;
+pub mod manage;
+pub mod quota;
 mod session;
 mod upload;
 mod utils;
@@ -23,7 +27,7 @@ use onedrive_api::{
 use path_slash::PathBufExt;
 use session::OneDriveSession;
 use std::{collections::HashMap, path::Path};
-use tokio::sync::{mpsc::Receiver, RwLock};
+use tokio::sync::{mpsc::Receiver, Mutex, RwLock};
 
 pub struct OneDriveClient {
     client: RwLock<Client>,
@@ -33,6 +37,8 @@ pub struct OneDriveClient {
     session_path: String,
     pub default_root_path: String,
     temp_root_path: RwLock<String>,
+    // refreshing tokens of accounts other than the current one
+    account_lock: Mutex<()>,
 }
 
 impl OneDriveClient {
@@ -74,6 +80,7 @@ impl OneDriveClient {
             session_path: session_path.clone(),
             default_root_path: root_path.to_string(),
             temp_root_path: RwLock::new(String::new()),
+            account_lock: Mutex::new(()),
         };
 
         let _ = onedrive_client.auto_login().await;
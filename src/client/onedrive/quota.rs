@@ -0,0 +1,130 @@
+/*
+:project: telegram-onedrive
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use super::OneDriveClient;
+use anyhow::{anyhow, Context, Result};
+use onedrive_api::ItemLocation;
+use reqwest::StatusCode;
+use serde_json::Value;
+use std::collections::HashMap;
+
+// in bytes
+#[derive(Debug, Clone, Copy)]
+pub struct Quota {
+    pub total: u64,
+    pub used: u64,
+    // in the recycle bin, counted as used
+    pub deleted: u64,
+    pub remaining: u64,
+}
+
+impl Quota {
+    fn from_json(quota: &Value) -> Option<Self> {
+        Some(Self {
+            total: quota.get("total")?.as_u64()?,
+            used: quota.get("used")?.as_u64()?,
+            deleted: quota
+                .get("deleted")
+                .and_then(Value::as_u64)
+                .unwrap_or_default(),
+            remaining: quota.get("remaining")?.as_u64()?,
+        })
+    }
+}
+
+impl OneDriveClient {
+    // none if the drive doesn't report its quota
+    pub async fn get_quota(&self, account: Option<&str>) -> Result<Option<Quota>> {
+        let drive = self
+            .get_client(account)
+            .await?
+            .get_drive()
+            .await
+            .context("failed to get drive")?;
+
+        Ok(drive.quota.as_ref().and_then(Quota::from_json))
+    }
+
+    // total size of the files in the directory, none if it doesn't exist
+    pub async fn get_dir_size(&self, path: &str, account: Option<&str>) -> Result<Option<u64>> {
+        let item_location =
+            ItemLocation::from_path(path).ok_or_else(|| anyhow!("path does not start with /"))?;
+
+        match self
+            .get_client(account)
+            .await?
+            .get_item(item_location)
+            .await
+        {
+            Ok(drive_item) => Ok(drive_item.size.map(|size| size as u64)),
+            Err(e) if e.status_code() == Some(StatusCode::NOT_FOUND) => Ok(None),
+            Err(e) => Err(e).context("failed to get directory"),
+        }
+    }
+
+    // the account itself if the file fits, otherwise the next account in /drive that has room,
+    // bytes that queued tasks of an account will take are not counted as room
+    pub async fn find_account_with_room(
+        &self,
+        account: &str,
+        size: u64,
+        queued_lengths: &HashMap<String, u64>,
+    ) -> Result<String> {
+        let get_room = |username: &str, remaining: u64| {
+            remaining.saturating_sub(queued_lengths.get(username).copied().unwrap_or_default())
+        };
+
+        let remaining = match self.get_quota(Some(account)).await {
+            Ok(Some(quota)) => get_room(account, quota.remaining),
+            Ok(None) => return Ok(account.to_string()),
+            Err(e) => {
+                // quota is only a hint, the upload itself tells if the drive is full
+                tracing::warn!("failed to get quota of {}: {:#}", account, e);
+
+                return Ok(account.to_string());
+            }
+        };
+
+        if remaining >= size {
+            return Ok(account.to_string());
+        }
+
+        let usernames = self.get_usernames().await?;
+        let index = usernames
+            .iter()
+            .position(|username| username == account)
+            .unwrap_or_default();
+
+        for username in usernames
+            .iter()
+            .cycle()
+            .skip(index + 1)
+            .take(usernames.len())
+            .filter(|username| *username != account)
+        {
+            if let Ok(Some(quota)) = self.get_quota(Some(username)).await {
+                if get_room(username, quota.remaining) >= size {
+                    tracing::info!(
+                        "{} has {} bytes left, upload to {} instead",
+                        account,
+                        remaining,
+                        username
+                    );
+
+                    return Ok(username.clone());
+                }
+            }
+        }
+
+        Err(anyhow!(
+            "no OneDrive account has room for {:.2}MB, {} has {:.2}MB left",
+            size as f64 / 1024.0 / 1024.0,
+            account,
+            remaining as f64 / 1024.0 / 1024.0
+        ))
+    }
+}
@@ -435,6 +435,22 @@ impl OneDriveSession {
         Ok(())
     }
 
+    // the session of another saved account, sharing the connection
+    pub async fn load_user(&self, username: &str) -> Result<Self> {
+        let session = session::Entity::find()
+            .filter(session::Column::Username.eq(username))
+            .one(&self.connection)
+            .await
+            .context("failed to query onedrive session")?
+            .ok_or_else(|| anyhow!("onedrive session of {} not found", username))?;
+
+        let mut session = Self::from(session);
+
+        session.connection = self.connection.clone();
+
+        Ok(session)
+    }
+
     pub fn is_expired(&self) -> bool {
         let is_expired = self.expiration_timestamp < get_current_timestamp() + 60;
 
@@ -6,10 +6,9 @@
 */
 
 use super::OneDriveClient;
+use crate::settings::ConflictPolicy;
 use anyhow::{anyhow, Context, Result};
-use onedrive_api::{
-    option::DriveItemPutOption, ConflictBehavior, ItemLocation, UploadSession, UploadSessionMeta,
-};
+use onedrive_api::{option::DriveItemPutOption, ItemLocation, UploadSession, UploadSessionMeta};
 use path_slash::PathBufExt;
 use std::path::Path;
 
@@ -18,6 +17,8 @@ impl OneDriveClient {
         &self,
         root_path: &str,
         filename: &str,
+        conflict_policy: ConflictPolicy,
+        account: Option<&str>,
     ) -> Result<(UploadSession, UploadSessionMeta)> {
         let file_path_obj = Path::new(root_path).join(filename);
         let file_path = file_path_obj.to_slash_lossy();
@@ -25,15 +26,12 @@ impl OneDriveClient {
         let item_location = ItemLocation::from_path(&file_path)
             .ok_or_else(|| anyhow!("file path does not start with /"))?;
 
-        self.refresh_access_token().await?;
-
         let session = self
-            .client
-            .read()
-            .await
+            .get_client(account)
+            .await?
             .new_upload_session_with_option(
                 item_location,
-                DriveItemPutOption::new().conflict_behavior(ConflictBehavior::Rename),
+                DriveItemPutOption::new().conflict_behavior(conflict_policy.to_conflict_behavior()),
             )
             .await
             .context("failed to create upload session")?;
@@ -45,6 +45,28 @@ impl TelegramClient {
         Ok(message)
     }
 
+    // messages that are not found are left out
+    pub async fn get_messages<C>(
+        &self,
+        chat: C,
+        message_ids: &[i32],
+    ) -> Result<Vec<TelegramMessage>>
+    where
+        C: Into<PackedChat>,
+    {
+        let messages = self
+            .raw()
+            .get_messages_by_id(chat, message_ids)
+            .await
+            .context("failed to get messages by id")?
+            .into_iter()
+            .flatten()
+            .map(|message_raw| TelegramMessage::new(self.clone(), message_raw))
+            .collect();
+
+        Ok(messages)
+    }
+
     pub async fn get_chat(&self, chat_entity: &ChatEntity) -> Result<Chat> {
         let mut dialogs = self.raw().iter_dialogs();
 
@@ -17,8 +17,7 @@ use std::{fs, sync::OnceLock};
 pub use telegram_bot::TelegramBotEnv;
 pub use telegram_user::TelegramUserEnv;
 use utils::{get_env_value, get_env_value_option, get_env_value_option_legacy};
-pub use var::LOGS_PATH;
-use var::SESSION_DIR;
+pub use var::{LOGS_PATH, SESSION_DIR};
 
 use crate::error::ResultExt;
 
@@ -34,7 +33,29 @@ pub struct Env {
     pub use_reverse_proxy: bool,
     pub should_auto_delete: bool,
     pub tasker_session_path: String,
+    pub settings_session_path: String,
+    pub history_session_path: String,
     pub task_handler_num: u8,
+    pub url_prefetch_num: usize,
+    pub tg_download_workers: usize,
+    pub part_prefetch_num: usize,
+    // KB/s, 0 means unlimited
+    pub download_limit: u64,
+    // KB/s, 0 means unlimited
+    pub upload_limit: u64,
+    pub max_retries: u32,
+    // seconds
+    pub retry_base_delay: u64,
+    // seconds
+    pub retry_max_delay: u64,
+    // skip media that has been uploaded before
+    pub dedup: bool,
+    // windows in which waiting tasks are started, empty means always
+    pub schedule: String,
+    // MB, chats subscribed by /quota alert are warned if a drive has less space left
+    pub quota_alert_threshold: u64,
+    // MB, /get --zip writes the archive to disk first, so larger files are refused, 0 means unlimited
+    pub zip_max_size: u64,
 }
 
 impl Env {
@@ -51,7 +72,21 @@ impl Env {
         let should_auto_delete =
             get_env_value_option_legacy(&["auto_delete", "delete_flag"], false);
         let tasker_session_path = var::TASKER_SESSION_PATH.to_string();
+        let settings_session_path = var::SETTINGS_SESSION_PATH.to_string();
+        let history_session_path = var::HISTORY_SESSION_PATH.to_string();
         let task_handler_num = get_env_value_option("worker_num", 5);
+        let url_prefetch_num = get_env_value_option("url_prefetch_num", 0);
+        let tg_download_workers = get_env_value_option("tg_download_workers", 4);
+        let part_prefetch_num = get_env_value_option("part_prefetch_num", 1);
+        let download_limit = get_env_value_option("download_limit", 0);
+        let upload_limit = get_env_value_option("upload_limit", 0);
+        let max_retries = get_env_value_option("max_retries", 5);
+        let retry_base_delay = get_env_value_option("retry_base_delay", 2);
+        let retry_max_delay = get_env_value_option("retry_max_delay", 60);
+        let dedup = get_env_value_option("dedup", false);
+        let schedule = get_env_value_option("schedule", String::new());
+        let quota_alert_threshold = get_env_value_option("quota_alert_threshold", 1024);
+        let zip_max_size = get_env_value_option("zip_max_size", 4096);
 
         Self {
             telegram_bot,
@@ -63,7 +98,21 @@ impl Env {
             use_reverse_proxy,
             should_auto_delete,
             tasker_session_path,
+            settings_session_path,
+            history_session_path,
             task_handler_num,
+            url_prefetch_num,
+            tg_download_workers,
+            part_prefetch_num,
+            download_limit,
+            upload_limit,
+            max_retries,
+            retry_base_delay,
+            retry_max_delay,
+            dedup,
+            schedule,
+            quota_alert_threshold,
+            zip_max_size,
         }
     }
 
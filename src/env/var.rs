@@ -15,6 +15,8 @@ pub const TG_BOT_SESSION_PATH: &str = "./session/tg-bot.session";
 pub const TG_USER_SESSION_PATH: &str = "./session/tg-user.session";
 pub const OD_SESSION_PATH: &str = "./session/od.session";
 pub const TASKER_SESSION_PATH: &str = "./session/tasker.session";
+pub const SETTINGS_SESSION_PATH: &str = "./session/settings.session";
+pub const HISTORY_SESSION_PATH: &str = "./session/history.session";
 
 pub const RECONNECTION_POLICY: FixedReconnect = FixedReconnect {
     attempts: 5,
@@ -125,3 +125,15 @@ impl Display for TaskAbortError {
         write!(f, "Task was aborted")
     }
 }
+
+// the file exists and the conflict policy of the task is skip
+#[derive(Debug)]
+pub struct TaskSkipError;
+
+impl std::error::Error for TaskSkipError {}
+
+impl Display for TaskSkipError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Task was skipped because the file already exists")
+    }
+}
@@ -0,0 +1,251 @@
+/*
+:project: telegram-onedrive
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use super::{
+    docs::{format_help, format_unknown_command_help},
+    utils::{text::cmd_parser, validate_root_path},
+};
+use crate::{message::TelegramMessage, state::AppState};
+use anyhow::{anyhow, Context, Result};
+use grammers_client::{button, reply_markup, InputMessage};
+use path_slash::PathBufExt;
+use proc_macros::{check_in_group, check_od_login, check_senders};
+use std::path::Path;
+
+pub const PATTERN: &str = "/browse";
+// first word of the data of the buttons
+pub const CALLBACK_PATTERN: &str = "browse";
+
+const PAGE_SIZE: usize = 10;
+// the browsed directory is kept in the first line of the message, so that buttons still work after restart
+const PATH_PREFIX: &str = "Directory: ";
+
+#[check_od_login]
+#[check_senders]
+#[check_in_group]
+pub async fn handler(message: TelegramMessage, state: AppState) -> Result<()> {
+    let onedrive = &state.onedrive;
+
+    let cmd = cmd_parser(message.text());
+
+    if cmd.len() == 1 {
+        // /browse
+        let path = onedrive.get_root_path(false).await?;
+
+        let response = format_browser(&path, 0, None, &state).await?;
+        message.respond(response).await.context("browser")?;
+    } else if cmd.len() == 2 && cmd[1] == "help" {
+        // /browse help
+        message
+            .respond(InputMessage::html(format_help(PATTERN)))
+            .await
+            .context("help")?;
+    } else if cmd.len() >= 3 && cmd[1] == "mkdir" {
+        // /browse mkdir $name, in the directory of the replied browser or the current directory
+        let parent_path = match message.reply_to_message_id() {
+            Some(message_id) => {
+                let browser = state
+                    .telegram_bot
+                    .get_message(message.chat(), message_id)
+                    .await?;
+
+                get_browser_path(&browser)?
+            }
+            None => onedrive.get_root_path(false).await?,
+        };
+
+        let name = cmd[2..].join(" ");
+
+        onedrive.create_dir(&parent_path, &name).await?;
+
+        let path = join_path(&parent_path, &name);
+        let note = format!("Directory {} created.", path);
+
+        let response = format_browser(&path, 0, Some(&note), &state).await?;
+        message.respond(response).await.context("browser")?;
+    } else if cmd.len() == 2 {
+        // /browse $path
+        validate_root_path(&cmd[1]).await?;
+
+        let response = format_browser(&cmd[1], 0, None, &state).await?;
+        message.respond(response).await.context("browser")?;
+    } else {
+        return Err(anyhow!("command error")).context(format_unknown_command_help(PATTERN));
+    }
+
+    Ok(())
+}
+
+// the message is the browser, its text is the data of the pressed button
+#[check_od_login]
+#[check_senders]
+#[check_in_group]
+pub async fn callback_handler(message: TelegramMessage, state: AppState) -> Result<()> {
+    let onedrive = &state.onedrive;
+
+    let cmd = cmd_parser(message.text());
+
+    let path = get_browser_path(&message)?;
+
+    let response = match cmd.get(1).map(String::as_str) {
+        Some("open") => {
+            // browse open $index
+            let index = parse_number(cmd.get(2))?;
+
+            let (dir_names, _) = onedrive.list_dir(&path).await?;
+            let dir_name = dir_names
+                .get(index)
+                .ok_or_else(|| anyhow!("directory not found, it may have been changed"))?;
+
+            format_browser(&join_path(&path, dir_name), 0, None, &state).await?
+        }
+        Some("page") => {
+            // browse page $page
+            let page = parse_number(cmd.get(2))?;
+
+            format_browser(&path, page, None, &state).await?
+        }
+        Some("up") => {
+            // browse up
+            let parent_path = Path::new(&path).parent().map_or_else(
+                || "/".to_string(),
+                |parent| parent.to_slash_lossy().to_string(),
+            );
+
+            format_browser(&parent_path, 0, None, &state).await?
+        }
+        Some("mkdir") => {
+            // browse mkdir
+            let note = "Reply to this message with /browse mkdir $name to create a directory here.";
+
+            format_browser(&path, 0, Some(note), &state).await?
+        }
+        Some("root") => {
+            // browse root
+            onedrive.set_root_path(&path).await?;
+
+            let note = format!("Directory set to {}", path);
+
+            format_browser(&path, 0, Some(&note), &state).await?
+        }
+        Some("temp") => {
+            // browse temp
+            onedrive.set_temp_root_path(&path).await?;
+
+            let note = format!("Temporary directory set to {}", path);
+
+            format_browser(&path, 0, Some(&note), &state).await?
+        }
+        _ => return Err(anyhow!("unknown button: {}", message.text())),
+    };
+
+    message
+        .edit(message.id(), response)
+        .await
+        .context("browser")?;
+
+    Ok(())
+}
+
+// the browsed directory with buttons of its sub directories
+async fn format_browser(
+    path: &str,
+    page: usize,
+    note: Option<&str>,
+    state: &AppState,
+) -> Result<InputMessage> {
+    let (dir_names, file_count) = state.onedrive.list_dir(path).await?;
+
+    let page_count = dir_names.len().div_ceil(PAGE_SIZE).max(1);
+    let page = page.min(page_count - 1);
+
+    let mut text = format!(
+        "{}{}\n{} directories, {} files",
+        PATH_PREFIX,
+        path,
+        dir_names.len(),
+        file_count
+    );
+
+    if page_count > 1 {
+        text.push_str(&format!(", page {}/{}", page + 1, page_count));
+    }
+
+    if let Some(note) = note {
+        text.push_str(&format!("\n\n{}", note));
+    }
+
+    let mut rows = dir_names
+        .iter()
+        .enumerate()
+        .skip(page * PAGE_SIZE)
+        .take(PAGE_SIZE)
+        .map(|(index, dir_name)| {
+            vec![button::inline(
+                format!("{}/", dir_name),
+                format!("{} open {}", CALLBACK_PATTERN, index),
+            )]
+        })
+        .collect::<Vec<_>>();
+
+    let mut page_row = Vec::new();
+    if page > 0 {
+        page_row.push(button::inline(
+            "< Prev",
+            format!("{} page {}", CALLBACK_PATTERN, page - 1),
+        ));
+    }
+    if page + 1 < page_count {
+        page_row.push(button::inline(
+            "Next >",
+            format!("{} page {}", CALLBACK_PATTERN, page + 1),
+        ));
+    }
+    if !page_row.is_empty() {
+        rows.push(page_row);
+    }
+
+    let mut dir_row = Vec::new();
+    if path != "/" {
+        dir_row.push(button::inline("Up", format!("{} up", CALLBACK_PATTERN)));
+    }
+    dir_row.push(button::inline(
+        "New directory",
+        format!("{} mkdir", CALLBACK_PATTERN),
+    ));
+    rows.push(dir_row);
+
+    rows.push(vec![
+        button::inline("Set as root", format!("{} root", CALLBACK_PATTERN)),
+        button::inline("Set as temp root", format!("{} temp", CALLBACK_PATTERN)),
+    ]);
+
+    Ok(InputMessage::text(text).reply_markup(&reply_markup::inline(rows)))
+}
+
+fn get_browser_path(browser: &TelegramMessage) -> Result<String> {
+    browser
+        .raw
+        .text()
+        .lines()
+        .next()
+        .and_then(|line| line.strip_prefix(PATH_PREFIX))
+        .map(|path| path.to_string())
+        .ok_or_else(|| anyhow!("message is not a browser"))
+}
+
+fn join_path(parent_path: &str, name: &str) -> String {
+    Path::new(parent_path)
+        .join(name)
+        .to_slash_lossy()
+        .to_string()
+}
+
+fn parse_number(arg: Option<&String>) -> Result<usize> {
+    arg.and_then(|arg| arg.parse::<usize>().ok())
+        .ok_or_else(|| anyhow!("button data should end with a number"))
+}
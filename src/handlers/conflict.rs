@@ -0,0 +1,117 @@
+/*
+:project: telegram-onedrive
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use super::{
+    docs::{format_help, format_unknown_command_help},
+    utils::{text::cmd_parser, validate_root_path},
+};
+use crate::{message::TelegramMessage, settings::ConflictPolicy, state::AppState};
+use anyhow::{anyhow, Context, Result};
+use grammers_client::InputMessage;
+use proc_macros::{check_in_group, check_od_login, check_senders};
+
+pub const PATTERN: &str = "/conflict";
+
+#[check_od_login]
+#[check_senders]
+#[check_in_group]
+pub async fn handler(message: TelegramMessage, state: AppState) -> Result<()> {
+    let cmd = cmd_parser(message.text());
+
+    let settings = &state.settings;
+    let chat_id = message.chat().id();
+
+    if cmd.len() == 1 {
+        // /conflict
+        show_conflict_policies(message, &state).await?;
+    } else if cmd.len() == 2 {
+        if cmd[1] == "help" {
+            // /conflict help
+            message
+                .respond(InputMessage::html(format_help(PATTERN)))
+                .await
+                .context("help")?;
+        } else if cmd[1] == "reset" {
+            // /conflict reset
+            settings.delete_chat_conflict_policy(chat_id).await?;
+
+            let response = format!(
+                "Conflict policy of this chat reset to default {}.",
+                ConflictPolicy::default()
+            );
+            message.respond(response.as_str()).await.context(response)?;
+        } else {
+            // /conflict $policy
+            let policy = parse_policy(&cmd[1])?;
+
+            settings.set_chat_conflict_policy(chat_id, policy).await?;
+
+            let response = format!("Conflict policy of this chat set to {}.", policy);
+            message.respond(response.as_str()).await.context(response)?;
+        }
+    } else if (cmd.len() == 3 || cmd.len() == 4) && cmd[1] == "dir" {
+        // /conflict dir $policy applies to the current directory
+        let path = if cmd.len() == 4 {
+            validate_root_path(&cmd[2]).await?;
+
+            cmd[2].clone()
+        } else {
+            state.onedrive.get_root_path(false).await?
+        };
+
+        let policy = &cmd[cmd.len() - 1];
+
+        if policy == "reset" {
+            // /conflict dir [$path] reset
+            settings.delete_dir_conflict_policy(&path).await?;
+
+            let response = format!("Conflict policy of {} removed.", path);
+            message.respond(response.as_str()).await.context(response)?;
+        } else {
+            // /conflict dir [$path] $policy
+            let policy = parse_policy(policy)?;
+
+            settings.set_dir_conflict_policy(&path, policy).await?;
+
+            let response = format!("Conflict policy of {} set to {}.", path, policy);
+            message.respond(response.as_str()).await.context(response)?;
+        }
+    } else {
+        return Err(anyhow!("command error")).context(format_unknown_command_help(PATTERN));
+    }
+
+    Ok(())
+}
+
+async fn show_conflict_policies(message: TelegramMessage, state: &AppState) -> Result<()> {
+    let settings = &state.settings;
+
+    let chat_policy = settings
+        .get_chat_conflict_policy(message.chat().id())
+        .await?
+        .unwrap_or_default();
+
+    let mut response = format!("Conflict policy of this chat: {}", chat_policy);
+
+    let dir_policies = settings.get_dir_conflict_policies().await?;
+
+    if !dir_policies.is_empty() {
+        response.push_str("\n\nConflict policies of directories:");
+
+        for (path, policy) in dir_policies {
+            response.push_str(&format!("\n{}: {}", path, policy));
+        }
+    }
+
+    message.respond(response.as_str()).await.context(response)?;
+
+    Ok(())
+}
+
+fn parse_policy(policy: &str) -> Result<ConflictPolicy> {
+    ConflictPolicy::parse(policy).context(format_unknown_command_help(PATTERN))
+}
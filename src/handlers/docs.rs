@@ -27,17 +27,193 @@ To show the version.
 const HELP_LINKS: &str = "\
 <pre><code>/links $message_link $num</code></pre>
 To transfer sequential restricted content.
+<pre><code>/links $message_link $num --replace</code></pre>
+To transfer with a conflict policy, one of --rename, --replace and --skip.
+<pre><code>/links $message_link $num --now</code></pre>
+To start even outside the schedule windows.
 <pre><code>/links help</code></pre>
 To show command help.
 ";
 
+const HELP_MIRROR: &str = "\
+<pre><code>/mirror $chat_link</code></pre>
+To transfer every file of a chat into a directory named after the chat, with a sub directory for each month, like https://t.me/xxxx or https://t.me/c/xxxxxxx. It runs in the background, delete the command to stop it.
+<pre><code>/mirror $chat_link $from $to</code></pre>
+To transfer files posted between two dates like 2024-01-31, $to can be omitted.
+<pre><code>/mirror $chat_link type:video,photo min:10 max:500 caption:$regex</code></pre>
+To filter by media type, one of photo, video, audio, image, document and sticker, size in MB and caption.
+<pre><code>/mirror reset $chat_link</code></pre>
+To mirror the chat from the beginning again, otherwise only files newer than the last mirror are transferred. The last mirror is kept for each group regardless of filters, so files skipped by the filters of one mirror are not transferred by later ones until reset.
+<pre><code>/mirror help</code></pre>
+To show command help.
+";
+
+const HELP_WATCH: &str = "\
+<pre><code>/watch</code></pre>
+To show chats watched by this chat.
+<pre><code>/watch $chat_link</code></pre>
+To transfer new files of a chat that the user has joined as soon as they are posted, like https://t.me/xxxx or https://t.me/c/xxxxxxx.
+<pre><code>/watch $chat_link type:video,photo min:10 max:500 caption:$regex $path</code></pre>
+To filter by media type, size in MB and caption, and transfer to a OneDrive directory instead of current directory.
+<pre><code>/watch remove $chat_link</code></pre>
+To stop watching a chat.
+<pre><code>/watch help</code></pre>
+To show command help.
+";
+
 const HELP_URL: &str = "\
 <pre><code>/url $url</code></pre>
 To upload file through url.
+<pre><code>/url $url --replace</code></pre>
+To upload with a conflict policy, one of --rename, --replace and --skip.
+<pre><code>/url $url --now</code></pre>
+To start even outside the schedule windows.
 <pre><code>/url help</code></pre>
 To show command help.
 ";
 
+const HELP_RETRY: &str = "\
+<pre><code>/retry</code></pre>
+To retry all failed tasks of this chat.
+<pre><code>/retry</code></pre>
+Reply to the message of a failed task to retry it.
+<pre><code>/retry help</code></pre>
+To show command help.
+";
+
+const HELP_QUEUE: &str = "\
+<pre><code>/queue</code></pre>
+To list queued tasks of this chat in the order they will start.
+<pre><code>/queue bump $position</code></pre>
+To move a queued task ahead of all others.
+<pre><code>/queue pause $position</code></pre>
+To hold a queued task back.
+<pre><code>/queue resume $position</code></pre>
+To put a paused task back to the queue.
+<pre><code>/queue cancel $position</code></pre>
+To cancel a queued task.
+<pre><code>/queue help</code></pre>
+To show command help.
+";
+
+const HELP_PAUSE: &str = "\
+<pre><code>/pause</code></pre>
+To pause running and waiting tasks of this chat.
+<pre><code>/pause</code></pre>
+Reply to the message of a task to pause only that one.
+<pre><code>/pause all</code></pre>
+To pause running and waiting tasks of all chats, new tasks wait until /resume all.
+<pre><code>/pause help</code></pre>
+To show command help.
+";
+
+const HELP_RESUME: &str = "\
+<pre><code>/resume</code></pre>
+To resume paused tasks of this chat.
+<pre><code>/resume</code></pre>
+Reply to the message of a paused task to resume only that one.
+<pre><code>/resume all</code></pre>
+To resume paused tasks of all chats and new tasks.
+<pre><code>/resume help</code></pre>
+To show command help.
+";
+
+const HELP_SCHEDULE: &str = "\
+<pre><code>/schedule</code></pre>
+To show the windows in which waiting tasks start.
+<pre><code>/schedule $rules</code></pre>
+To set windows separated by semicolons, like mon-fri 22:00-06:00; sat,sun 00:00-24:00. It's kept after restart.
+<pre><code>/schedule always</code></pre>
+To start tasks at any time.
+<pre><code>/schedule reset</code></pre>
+To reset schedule to the one in env.
+<pre><code>/schedule help</code></pre>
+To show command help.
+";
+
+const HELP_LIMIT: &str = "\
+<pre><code>/limit</code></pre>
+To show bandwidth limits.
+<pre><code>/limit $direction $rate</code></pre>
+To set global download or upload limit in KB/s, 0 for unlimited. It's kept after restart.
+<pre><code>/limit chat $direction $rate</code></pre>
+To set download or upload limit of this chat in KB/s, 0 for unlimited. It's kept after restart.
+<pre><code>/limit help</code></pre>
+To show command help.
+";
+
+const HELP_CONFLICT: &str = "\
+<pre><code>/conflict</code></pre>
+To show conflict policies.
+<pre><code>/conflict $policy</code></pre>
+To set what to do if the file exists for this chat, one of rename, replace and skip.
+<pre><code>/conflict reset</code></pre>
+To reset conflict policy of this chat to rename.
+<pre><code>/conflict dir $policy</code></pre>
+To set conflict policy of current OneDrive directory and its sub directories.
+<pre><code>/conflict dir $path $policy</code></pre>
+To set conflict policy of a OneDrive directory and its sub directories.
+<pre><code>/conflict dir $path reset</code></pre>
+To remove conflict policy of a OneDrive directory.
+<pre><code>/conflict help</code></pre>
+To show command help.
+";
+
+const HELP_TEMPLATE: &str = "\
+<pre><code>/template</code></pre>
+To show name templates.
+<pre><code>/template $template</code></pre>
+To set how files of this chat are named, like {chat_title}/{date:%Y/%m}/{sender}_{filename}, / creates directories. Placeholders are filename, name, ext, caption, caption_slug, sender, chat_title, message_id, media_type, duration, resolution and date, which takes a format like {date:%Y-%m-%d}.
+<pre><code>/template reset</code></pre>
+To remove name template of this chat.
+<pre><code>/template dir $template</code></pre>
+To set name template of current OneDrive directory and its sub directories.
+<pre><code>/template dir $path $template</code></pre>
+To set name template of a OneDrive directory and its sub directories.
+<pre><code>/template dir $path reset</code></pre>
+To remove name template of a OneDrive directory.
+<pre><code>/template help</code></pre>
+To show command help.
+";
+
+const HELP_FILTER: &str = "\
+<pre><code>/filter</code></pre>
+To show upload filter of this chat.
+<pre><code>/filter allow type video/*,image/png</code></pre>
+To only upload files of these MIME types, none to remove the rule.
+<pre><code>/filter deny type application/zip</code></pre>
+To reject files of these MIME types, none to remove the rule.
+<pre><code>/filter allow ext mp4,jpg</code></pre>
+To only upload files with these extensions, none to remove the rule.
+<pre><code>/filter deny ext exe</code></pre>
+To reject files with these extensions, none to remove the rule.
+<pre><code>/filter min $mb</code></pre>
+To reject files smaller than the size in MB, none to remove the rule.
+<pre><code>/filter max $mb</code></pre>
+To reject files larger than the size in MB, none to remove the rule.
+<pre><code>/filter sticker ignore</code></pre>
+To ignore stickers, accept to upload them again.
+<pre><code>/filter voice ignore</code></pre>
+To ignore voice notes, accept to upload them again.
+<pre><code>/filter reset</code></pre>
+To remove upload filter of this chat.
+<pre><code>/filter help</code></pre>
+To show command help.
+";
+
+const HELP_HISTORY: &str = "\
+<pre><code>/history</code></pre>
+To show the latest transfer history of this chat.
+<pre><code>/history $filters</code></pre>
+To filter history by name:$keyword, status:$status, date:YYYY-MM-DD and page:$page.
+<pre><code>/history export $filters</code></pre>
+To export history as CSV, filters are optional.
+<pre><code>/history clear</code></pre>
+To clear history of this chat.
+<pre><code>/history help</code></pre>
+To show command help.
+";
+
 const HELP_LOGS: &str = "\
 <pre><code>/logs</code></pre>
 To send logs zip.
@@ -49,11 +225,11 @@ To show command help.
 
 const HELP_DRIVE: &str = "\
 <pre><code>/drive</code></pre>
-To list all OneDrive accounts.
+To list all OneDrive accounts with their used and remaining quota.
 <pre><code>/drive add</code></pre>
 To add a OneDrive account.
 <pre><code>/drive $index</code></pre>
-To change the OneDrive account.
+To change the OneDrive account, queued tasks keep the account they were added with.
 <pre><code>/drive logout</code></pre>
 To logout current OneDrive account.
 <pre><code>/drive logout $index</code></pre>
@@ -62,6 +238,77 @@ To logout specified OneDrive account.
 To show command help.
 ";
 
+const HELP_QUOTA: &str = "\
+<pre><code>/quota</code></pre>
+To show total, used, deleted and remaining storage of all OneDrive accounts, and the size of their directories.
+<pre><code>/quota alert on</code></pre>
+To warn this chat if a drive is running out of space or queued tasks don't fit.
+<pre><code>/quota alert off</code></pre>
+To stop warning this chat.
+<pre><code>/quota help</code></pre>
+To show command help.
+";
+
+const HELP_ROUTE: &str = "\
+<pre><code>/route</code></pre>
+To show routes of new tasks to OneDrive accounts.
+<pre><code>/route chat $index</code></pre>
+To upload files of this chat to the OneDrive account of the index in /drive.
+<pre><code>/route sender $user_id $index</code></pre>
+To upload files from a sender to a OneDrive account.
+<pre><code>/route type $type $index</code></pre>
+To upload files of a type to a OneDrive account, one of image, video, audio and document.
+<pre><code>/route $kind [$value] reset</code></pre>
+To remove a route, like /route chat reset.
+<pre><code>/route help</code></pre>
+To show command help.
+";
+
+const HELP_BROWSE: &str = "\
+<pre><code>/browse</code></pre>
+To browse current OneDrive directory with buttons to open, go up, create a directory, or set it as directory or temporary directory.
+<pre><code>/browse $path</code></pre>
+To browse a OneDrive directory.
+<pre><code>/browse mkdir $name</code></pre>
+To create a directory in the browsed directory if replied to a browser, otherwise in current directory.
+<pre><code>/browse help</code></pre>
+To show command help.
+";
+
+const HELP_FILES: &str = "\
+<pre><code>/ls</code></pre>
+To list current OneDrive directory.
+<pre><code>/ls $path</code></pre>
+To list a OneDrive directory, relative paths are in current directory.
+<pre><code>/mkdir $path</code></pre>
+To create a OneDrive directory.
+<pre><code>/mv $source $destination</code></pre>
+To move into a directory if the destination is one, otherwise to move or rename to the destination.
+<pre><code>/rm $path</code></pre>
+To delete a OneDrive file or directory after confirmation.
+<pre><code>/share $path</code></pre>
+To create a link that anyone can view.
+<pre><code>/share $path edit $expiration</code></pre>
+To create a link that anyone can edit, the expiration is optional, like 12h or 7d, and only works for business accounts.
+<pre><code>/share auto on $options</code></pre>
+To add a share link to the message of files uploaded from this chat. Options are optional, anonymous or organization, view or edit, an expiration like 7d, and password:$password for personal accounts.
+<pre><code>/share auto off</code></pre>
+To stop adding share links for this chat.
+<pre><code>/share auto</code></pre>
+To show share link setting of this chat.
+";
+
+const HELP_GET: &str = "\
+<pre><code>/get $path</code></pre>
+To send a OneDrive file to this chat, files larger than 2GB are sent in numbered parts like name.001, relative paths are in current directory.
+<pre><code>/get $path --zip</code></pre>
+To send as a zip split into parts like name.zip.001, which can be joined by cat or opened by 7-Zip, files larger than zip_max_size in env are refused since the zip is written to disk first.
+<pre><code>/get $path --now</code></pre>
+To start even outside the schedule windows.
+<pre><code>/get help</code></pre>
+To show command help.
+";
+
 const HELP_DIR: &str = "\
 <pre><code>/dir</code></pre>
 To show current OneDrive directory.
@@ -79,9 +326,14 @@ To show command help.
 
 const INSTRUCTION: &str = "\
 - To transfer files, forward or upload to me.
+- Albums are uploaded into a directory named after the caption, or the chat and date.
 - To transfer restricted content, right click the content, copy the message link, and send to me.
 - Tap the file name on the Progress message to locate the job.
-- To upload files through url, the headers of the file response must includes Content-Length.
+- Files from url without Content-Length are downloaded before they are uploaded, and the Progress message shows the speed instead.
+- A message link can be followed by a conflict policy, like --replace.
+- New tasks are uploaded to the account routed by sender, then chat, then file type, otherwise the current account.
+- Uploaded files are checked with quickXorHash, and broken ones fail so that they can be retried.
+- If dedup is enabled, files already on OneDrive are skipped and linked in the responded message.
 - To cancel a job, delete the responded message.
 - To cancel batch or links tasks, delete the message you sent.
 - Support files with extension .t2o as scripts.
@@ -101,16 +353,56 @@ pub fn format_help(name: &str) -> String {
     match name {
         "/help" => {
             format!(
-                "{}{}{}{}{}{}\n{}",
-                HELP_BASE, HELP_LINKS, HELP_URL, HELP_LOGS, HELP_DRIVE, HELP_DIR, INSTRUCTION
+                "{}{}{}{}{}{}{}{}{}{}{}{}{}{}{}{}{}{}{}{}{}{}{}\n{}",
+                HELP_BASE,
+                HELP_LINKS,
+                HELP_MIRROR,
+                HELP_WATCH,
+                HELP_URL,
+                HELP_RETRY,
+                HELP_QUEUE,
+                HELP_PAUSE,
+                HELP_RESUME,
+                HELP_SCHEDULE,
+                HELP_LIMIT,
+                HELP_CONFLICT,
+                HELP_TEMPLATE,
+                HELP_FILTER,
+                HELP_HISTORY,
+                HELP_LOGS,
+                HELP_DRIVE,
+                HELP_QUOTA,
+                HELP_ROUTE,
+                HELP_DIR,
+                HELP_BROWSE,
+                HELP_FILES,
+                HELP_GET,
+                INSTRUCTION
             )
         }
         "/start" => GREETING.to_string(),
         "/links" => HELP_LINKS.to_string(),
+        "/mirror" => HELP_MIRROR.to_string(),
+        "/watch" => HELP_WATCH.to_string(),
         "/url" => HELP_URL.to_string(),
+        "/retry" => HELP_RETRY.to_string(),
+        "/queue" => HELP_QUEUE.to_string(),
+        "/pause" => HELP_PAUSE.to_string(),
+        "/resume" => HELP_RESUME.to_string(),
+        "/schedule" => HELP_SCHEDULE.to_string(),
+        "/limit" => HELP_LIMIT.to_string(),
+        "/conflict" => HELP_CONFLICT.to_string(),
+        "/template" => HELP_TEMPLATE.to_string(),
+        "/filter" => HELP_FILTER.to_string(),
+        "/history" => HELP_HISTORY.to_string(),
         "/logs" => HELP_LOGS.to_string(),
         "/drive" => HELP_DRIVE.to_string(),
+        "/quota" => HELP_QUOTA.to_string(),
+        "/route" => HELP_ROUTE.to_string(),
         "/dir" => HELP_DIR.to_string(),
+        "/browse" => HELP_BROWSE.to_string(),
+        "/ls" | "/mkdir" | "/mv" | "/rm" | "/share" => HELP_FILES.to_string(),
+        "/get" => HELP_GET.to_string(),
         _ => String::new(),
     }
 }
@@ -74,18 +74,19 @@ async fn show_drive(onedrive: &OneDriveClient, message: TelegramMessage) -> Resu
     let usernames = onedrive.get_usernames().await?;
     if let Some(current_username) = onedrive.get_current_username().await? {
         if !usernames.is_empty() {
-            let response = {
-                let mut response = format!("Current account is {}", current_username);
-
-                if usernames.len() > 1 {
-                    response.insert(0, '\n');
-                    for i in (1..=usernames.len()).rev() {
-                        response.insert_str(0, &format!("{}. {}\n", i, usernames[i - 1]));
-                    }
-                }
-
-                response
-            };
+            let mut response = String::new();
+
+            for (i, username) in usernames.iter().enumerate() {
+                response.push_str(&format!(
+                    "{}. {} ({})\n",
+                    i + 1,
+                    username,
+                    format_quota(onedrive, username).await
+                ));
+            }
+
+            response.push_str(&format!("\nCurrent account is {}", current_username));
+
             message.respond(response.as_str()).await.context(response)?;
 
             return Ok(());
@@ -98,6 +99,23 @@ async fn show_drive(onedrive: &OneDriveClient, message: TelegramMessage) -> Resu
     Ok(())
 }
 
+async fn format_quota(onedrive: &OneDriveClient, username: &str) -> String {
+    match onedrive.get_quota(Some(username)).await {
+        Ok(Some(quota)) => format!(
+            "used {:.2}GB of {:.2}GB, {:.2}GB left",
+            quota.used as f64 / 1024.0 / 1024.0 / 1024.0,
+            quota.total as f64 / 1024.0 / 1024.0 / 1024.0,
+            quota.remaining as f64 / 1024.0 / 1024.0 / 1024.0
+        ),
+        Ok(None) => "quota unknown".to_string(),
+        Err(e) => {
+            tracing::warn!("failed to get quota of {}: {:#}", username, e);
+
+            "quota unavailable".to_string()
+        }
+    }
+}
+
 async fn add_drive(message: TelegramMessage, state: AppState) -> Result<()> {
     let (_, rx, _server_abort_handle) = auth_server::spawn().await?;
     authorize_onedrive(message, state, true, rx).await?;
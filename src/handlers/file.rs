@@ -5,24 +5,59 @@
 :license: MIT, see LICENSE for more details.
 */
 
-use std::sync::atomic::Ordering;
+use std::{path::Path, sync::atomic::Ordering, time::Duration};
 
 use super::utils::upload::upload_thumb;
 use crate::{
-    handlers::utils::{get_tg_file_size, message::format_message_link, preprocess_tg_file_name},
+    error::{ErrorExt, ResultUnwrapExt},
+    handlers::utils::{
+        dedup::{find_existing_item, get_tg_media_key, report_skipped},
+        filter::reject_filtered_media,
+        get_tg_file_size,
+        message::format_message_link,
+        preprocess_dir_name, preprocess_tg_file_name,
+        route::get_account,
+        schedule::report_scheduled,
+        template::{apply_name_template, TemplateContext},
+    },
     message::{ChatEntity, TelegramMessage},
     state::AppState,
     tasker::{CmdType, InsertTask},
+    utils::get_current_timestamp,
 };
 use anyhow::{anyhow, Context, Result};
+use chrono::Local;
 use grammers_client::{types::Media, InputMessage};
+use path_slash::PathBufExt;
 use proc_macros::{check_in_group, check_od_login, check_senders, check_tg_login};
 
+// items of an album come one by one, they are handled together once no more item comes for this long
+const ALBUM_WAIT_DURATION: Duration = Duration::from_secs(2);
+
+struct AlbumItem {
+    message_id: i32,
+    filename: String,
+    total_length: u64,
+    media_key: Option<String>,
+    caption: String,
+    context: TemplateContext,
+}
+
 #[check_od_login]
 #[check_tg_login]
 #[check_senders]
 #[check_in_group]
 pub async fn handler(message: TelegramMessage, state: AppState) -> Result<()> {
+    if let Some(media) = message.media() {
+        if reject_filtered_media(&message, &media, &state).await? {
+            return Ok(());
+        }
+    }
+
+    if let Some(grouped_id) = message.grouped_id() {
+        return collect_album_item(message, grouped_id, state).await;
+    }
+
     let telegram_user = &state.telegram_user;
     let onedrive = &state.onedrive;
     let task_session = &state.task_session;
@@ -41,6 +76,8 @@ pub async fn handler(message: TelegramMessage, state: AppState) -> Result<()> {
 
     let total_length = get_tg_file_size(&media);
 
+    let media_key = get_tg_media_key(&media);
+
     let message_id = message.id();
 
     let cmd_type = match media {
@@ -68,20 +105,52 @@ pub async fn handler(message: TelegramMessage, state: AppState) -> Result<()> {
             .respond(InputMessage::html(&response).photo(uploaded))
             .await
             .context("message with thumb")
-            .context(response)?
+            .context(response.clone())?
             .id(),
         None => message
             .respond(InputMessage::html(&response))
             .await
             .context("message without thumn")
-            .context(response)?
+            .context(response.clone())?
             .id(),
     };
 
-    let root_path = onedrive.get_root_path(true).await?;
+    let account = get_account(&message, &filename, Some(total_length), &state).await?;
+
+    let root_path = onedrive.get_account_root_path(&account, true).await?;
+
+    let (root_path, filename) = apply_name_template(
+        &state,
+        chat_user.id(),
+        &root_path,
+        &account,
+        &TemplateContext::from_message(&message_user, &filename),
+    )
+    .await?;
+
+    let conflict_policy = state
+        .settings
+        .get_conflict_policy(chat_user.id(), &root_path)
+        .await?;
+
+    if let Some(drive_item) = find_existing_item(
+        &state,
+        media_key.as_deref(),
+        &root_path,
+        &filename,
+        Some(total_length),
+        conflict_policy,
+        &account,
+    )
+    .await?
+    {
+        report_skipped(&message, message_indicator_id, &response, &drive_item).await?;
+
+        return Ok(());
+    }
 
     let (upload_session, upload_session_meta) = onedrive
-        .multipart_upload_session_builder(&root_path, &filename)
+        .multipart_upload_session_builder(&root_path, &filename, conflict_policy, Some(&account))
         .await?;
 
     // all task should be new, so this should always be 0
@@ -103,7 +172,7 @@ pub async fn handler(message: TelegramMessage, state: AppState) -> Result<()> {
             url: None,
             upload_url: upload_session.upload_url().to_string(),
             current_length,
-            total_length,
+            total_length: Some(total_length),
             chat_id: chat_user.id(),
             chat_bot_hex,
             chat_user_hex,
@@ -112,10 +181,260 @@ pub async fn handler(message: TelegramMessage, state: AppState) -> Result<()> {
             message_indicator_id,
             message_origin_id: None,
             auto_delete,
+            media_key,
+            conflict_policy,
+            account: Some(account),
+            ignore_schedule: false,
+            zip: false,
         })
         .await?;
 
+    report_scheduled(&message, message_indicator_id, &response, false, &state).await?;
+
     tracing::info!("inserted file task: {} size: {}", filename, total_length);
 
     Ok(())
 }
+
+async fn collect_album_item(
+    message: TelegramMessage,
+    grouped_id: i64,
+    state: AppState,
+) -> Result<()> {
+    let key = (message.chat().id(), grouped_id);
+
+    let mut album_messages = state.task_session.album_messages.lock().await;
+
+    if let Some(messages) = album_messages.get_mut(&key) {
+        messages.push(message);
+
+        return Ok(());
+    }
+
+    album_messages.insert(key, vec![message.clone()]);
+    drop(album_messages);
+
+    // the listener waits for handlers, so the album is handled in the background
+    tokio::spawn(async move {
+        let mut items_num = 1;
+
+        let messages = loop {
+            tokio::time::sleep(ALBUM_WAIT_DURATION).await;
+
+            let mut album_messages = state.task_session.album_messages.lock().await;
+
+            let new_items_num = album_messages.get(&key).map_or(0, Vec::len);
+            if new_items_num == items_num {
+                break album_messages.remove(&key).unwrap_or_default();
+            }
+
+            items_num = new_items_num;
+        };
+
+        if let Err(e) = handle_album(messages, state).await {
+            e.send(message).await.unwrap_both().trace();
+        }
+    });
+
+    Ok(())
+}
+
+// the album is uploaded into a directory named after its caption, or its chat and date,
+// with a single indicator, and its items are uploaded one by one in order,
+// name templates are applied under that directory
+async fn handle_album(mut messages: Vec<TelegramMessage>, state: AppState) -> Result<()> {
+    let telegram_user = &state.telegram_user;
+    let onedrive = &state.onedrive;
+    let task_session = &state.task_session;
+
+    messages.sort_by_key(TelegramMessage::id);
+
+    let first_message = messages
+        .first()
+        .ok_or_else(|| anyhow!("album is empty"))?
+        .clone();
+
+    let chat_user = telegram_user
+        .get_chat(&ChatEntity::from(first_message.chat()))
+        .await?;
+
+    let mut items = Vec::new();
+
+    for message in &messages {
+        let message_user = telegram_user.get_message(&chat_user, message.id()).await?;
+
+        let media = message_user
+            .media()
+            .ok_or_else(|| anyhow!("message does not contain any media"))?;
+
+        if !matches!(
+            media,
+            Media::Photo(_) | Media::Document(_) | Media::Sticker(_)
+        ) {
+            return Err(anyhow!(
+                "media type is not one of photo, document and sticker",
+            ));
+        }
+
+        let filename = preprocess_tg_file_name(&media);
+
+        items.push(AlbumItem {
+            message_id: message.id(),
+            context: TemplateContext::from_message(&message_user, &filename),
+            filename,
+            total_length: get_tg_file_size(&media),
+            media_key: get_tg_media_key(&media),
+            caption: message_user.raw.text().to_string(),
+        });
+    }
+
+    let dir_name = items
+        .iter()
+        .find_map(|item| item.caption.lines().next().and_then(preprocess_dir_name))
+        .or_else(|| {
+            preprocess_dir_name(&format!(
+                "{} {}",
+                first_message.chat().name(),
+                first_message
+                    .raw
+                    .date()
+                    .with_timezone(&Local)
+                    .format("%Y-%m-%d %H-%M-%S")
+            ))
+        })
+        .unwrap_or_else(|| get_current_timestamp().to_string());
+
+    let album_length = items.iter().map(|item| item.total_length).sum();
+
+    let account = get_account(
+        &first_message,
+        &items[0].filename,
+        Some(album_length),
+        &state,
+    )
+    .await?;
+
+    let root_path = Path::new(&onedrive.get_account_root_path(&account, true).await?)
+        .join(&dir_name)
+        .to_slash_lossy()
+        .to_string();
+
+    let mut response = format!("Album: {}\n", root_path);
+    let mut new_items = Vec::new();
+
+    for mut item in items {
+        // the template is applied under the directory of the album
+        let (item_root_path, filename) =
+            apply_name_template(&state, chat_user.id(), &root_path, &account, &item.context)
+                .await?;
+        item.filename = filename;
+
+        let conflict_policy = state
+            .settings
+            .get_conflict_policy(chat_user.id(), &item_root_path)
+            .await?;
+
+        let link = format_message_link(chat_user.id(), item.message_id, &item.filename);
+
+        if find_existing_item(
+            &state,
+            item.media_key.as_deref(),
+            &item_root_path,
+            &item.filename,
+            Some(item.total_length),
+            conflict_policy,
+            &account,
+        )
+        .await?
+        .is_some()
+        {
+            response.push_str(&format!("\n{} skipped, already exists.", link));
+        } else {
+            response.push_str(&format!("\n{}", link));
+
+            new_items.push((item, item_root_path, conflict_policy));
+        }
+    }
+
+    if new_items.is_empty() {
+        response.push_str("\n\nSkipped.\nAll files already exist.");
+    }
+
+    // in case if cancellation happens before inserting the tasks
+    let _aborters = state.task_session.task_aborters.lock().await;
+
+    let message_indicator_id = first_message
+        .respond(InputMessage::html(&response))
+        .await
+        .context(response.clone())?
+        .id();
+
+    let chat_bot_hex = first_message.chat().pack().to_hex();
+    let chat_user_hex = chat_user.pack().to_hex();
+
+    let auto_delete = state.should_auto_delete.load(Ordering::Acquire);
+
+    for (item, item_root_path, conflict_policy) in &new_items {
+        let conflict_policy = *conflict_policy;
+
+        let (upload_session, upload_session_meta) = onedrive
+            .multipart_upload_session_builder(
+                item_root_path,
+                &item.filename,
+                conflict_policy,
+                Some(&account),
+            )
+            .await?;
+
+        let current_length = upload_session_meta
+            .next_expected_ranges
+            .first()
+            .map_or(0, |range| range.start);
+
+        task_session
+            .insert_task(InsertTask {
+                cmd_type: CmdType::File,
+                filename: item.filename.clone(),
+                root_path: item_root_path.clone(),
+                url: None,
+                upload_url: upload_session.upload_url().to_string(),
+                current_length,
+                total_length: Some(item.total_length),
+                chat_id: chat_user.id(),
+                chat_bot_hex: chat_bot_hex.clone(),
+                chat_user_hex: chat_user_hex.clone(),
+                chat_origin_hex: None,
+                // the album is cancelled as a whole by deleting its first item
+                message_id: first_message.id(),
+                message_indicator_id,
+                message_origin_id: Some(item.message_id),
+                auto_delete,
+                media_key: item.media_key.clone(),
+                conflict_policy,
+                account: Some(account.clone()),
+                ignore_schedule: false,
+                zip: false,
+            })
+            .await?;
+    }
+
+    if !new_items.is_empty() {
+        report_scheduled(
+            &first_message,
+            message_indicator_id,
+            &response,
+            false,
+            &state,
+        )
+        .await?;
+    }
+
+    tracing::info!(
+        "inserted album tasks: {} items: {} size: {}",
+        root_path,
+        new_items.len(),
+        album_length
+    );
+
+    Ok(())
+}
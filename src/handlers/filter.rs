@@ -0,0 +1,175 @@
+/*
+:project: telegram-onedrive
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use super::{
+    docs::{format_help, format_unknown_command_help},
+    utils::{
+        filter::{format_size, parse_size, split_list},
+        text::cmd_parser,
+    },
+};
+use crate::{message::TelegramMessage, settings::ChatUploadFilter, state::AppState};
+use anyhow::{anyhow, Context, Result};
+use grammers_client::InputMessage;
+use proc_macros::{check_in_group, check_senders};
+
+pub const PATTERN: &str = "/filter";
+
+// clears a list or a size
+const NONE: &str = "none";
+
+#[check_senders]
+#[check_in_group]
+pub async fn handler(message: TelegramMessage, state: AppState) -> Result<()> {
+    let cmd = cmd_parser(message.text());
+
+    let settings = &state.settings;
+    let chat_id = message.chat().id();
+
+    if cmd.len() == 1 {
+        // /filter
+        let upload_filter = settings
+            .get_chat_upload_filter(chat_id)
+            .await?
+            .unwrap_or_default();
+
+        let response = format!(
+            "Upload filter of this chat:\n{}",
+            format_upload_filter(&upload_filter)
+        );
+        message.respond(response.as_str()).await.context(response)?;
+
+        return Ok(());
+    }
+
+    if cmd.len() == 2 && cmd[1] == "help" {
+        // /filter help
+        message
+            .respond(InputMessage::html(format_help(PATTERN)))
+            .await
+            .context("help")?;
+
+        return Ok(());
+    }
+
+    if cmd.len() == 2 && cmd[1] == "reset" {
+        // /filter reset
+        settings.delete_chat_upload_filter(chat_id).await?;
+
+        let response = "Upload filter of this chat removed.";
+        message.respond(response).await.context(response)?;
+
+        return Ok(());
+    }
+
+    let mut upload_filter = settings
+        .get_chat_upload_filter(chat_id)
+        .await?
+        .unwrap_or_else(|| ChatUploadFilter {
+            chat_id,
+            ..Default::default()
+        });
+
+    let args = cmd[1..].iter().map(String::as_str).collect::<Vec<&str>>();
+
+    match args[..] {
+        // /filter allow|deny type|ext $list
+        [action @ ("allow" | "deny"), kind @ ("type" | "ext"), list] => {
+            let list = parse_list(list, kind == "ext");
+
+            match (action, kind) {
+                ("allow", "type") => upload_filter.allowed_types = list,
+                ("deny", "type") => upload_filter.denied_types = list,
+                ("allow", _) => upload_filter.allowed_exts = list,
+                _ => upload_filter.denied_exts = list,
+            }
+        }
+        // /filter min|max $mb
+        [limit @ ("min" | "max"), size] => {
+            let size = if size == NONE {
+                None
+            } else {
+                Some(parse_size(size).context(format_unknown_command_help(PATTERN))? as i64)
+            };
+
+            if limit == "min" {
+                upload_filter.min_size = size;
+            } else {
+                upload_filter.max_size = size;
+            }
+        }
+        // /filter sticker|voice ignore|accept
+        [kind @ ("sticker" | "voice"), mode @ ("ignore" | "accept")] => {
+            let ignore = mode == "ignore";
+
+            if kind == "sticker" {
+                upload_filter.ignore_stickers = ignore;
+            } else {
+                upload_filter.ignore_voices = ignore;
+            }
+        }
+        _ => return Err(anyhow!("command error")).context(format_unknown_command_help(PATTERN)),
+    }
+
+    let response = format!(
+        "Upload filter of this chat set to:\n{}",
+        format_upload_filter(&upload_filter)
+    );
+
+    settings.set_chat_upload_filter(upload_filter).await?;
+
+    message.respond(response.as_str()).await.context(response)?;
+
+    Ok(())
+}
+
+// lowercase and comma separated, extensions without dot
+fn parse_list(list: &str, is_ext: bool) -> String {
+    if list == NONE {
+        return String::new();
+    }
+
+    split_list(list)
+        .map(|item| {
+            let item = item.to_lowercase();
+
+            if is_ext {
+                item.trim_start_matches('.').to_string()
+            } else {
+                item
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+fn format_upload_filter(upload_filter: &ChatUploadFilter) -> String {
+    let format_list = |list: &str| {
+        if list.is_empty() {
+            NONE.to_string()
+        } else {
+            list.to_string()
+        }
+    };
+
+    let format_limit =
+        |size: Option<i64>| size.map_or_else(|| NONE.to_string(), |size| format_size(size as u64));
+
+    let format_mode = |ignore: bool| if ignore { "ignore" } else { "accept" };
+
+    format!(
+        "allowed types: {}\ndenied types: {}\nallowed extensions: {}\ndenied extensions: {}\nmin size: {}\nmax size: {}\nstickers: {}\nvoice notes: {}",
+        format_list(&upload_filter.allowed_types),
+        format_list(&upload_filter.denied_types),
+        format_list(&upload_filter.allowed_exts),
+        format_list(&upload_filter.denied_exts),
+        format_limit(upload_filter.min_size),
+        format_limit(upload_filter.max_size),
+        format_mode(upload_filter.ignore_stickers),
+        format_mode(upload_filter.ignore_voices),
+    )
+}
@@ -0,0 +1,153 @@
+/*
+:project: telegram-onedrive
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use super::{
+    docs::{format_help, format_unknown_command_help},
+    utils::{
+        message::format_message_link,
+        resolve_remote_path,
+        schedule::report_scheduled,
+        text::{cmd_parser, flags_parser},
+    },
+};
+use crate::{
+    client::onedrive::manage::split_path,
+    env::ENV,
+    message::{ChatEntity, TelegramMessage},
+    settings::ConflictPolicy,
+    state::AppState,
+    tasker::{CmdType, InsertTask},
+};
+use anyhow::{anyhow, Context, Result};
+use grammers_client::InputMessage;
+use proc_macros::{check_in_group, check_od_login, check_senders, check_tg_login};
+use std::sync::atomic::Ordering;
+
+pub const PATTERN: &str = "/get";
+
+#[check_od_login]
+#[check_tg_login]
+#[check_senders]
+#[check_in_group]
+pub async fn handler(message: TelegramMessage, state: AppState) -> Result<()> {
+    let (cmd, flags) =
+        flags_parser(cmd_parser(message.text())).context(format_unknown_command_help(PATTERN))?;
+
+    if cmd.len() != 2 {
+        return Err(anyhow!("command error")).context(format_unknown_command_help(PATTERN));
+    }
+
+    if cmd[1] == "help" {
+        // /get help
+        message
+            .respond(InputMessage::html(format_help(PATTERN)))
+            .await
+            .context("help")?;
+
+        return Ok(());
+    }
+
+    // /get $path [--zip] [--now]
+    let telegram_user = &state.telegram_user;
+    let onedrive = &state.onedrive;
+
+    let path = resolve_remote_path(onedrive, &cmd[1]).await?;
+    let (root_path, filename) = split_path(&path)?;
+
+    let account = onedrive
+        .get_current_username()
+        .await?
+        .ok_or_else(|| anyhow!("no onedrive account is logged in"))?;
+
+    let drive_item = onedrive
+        .get_item_from_path(&root_path, &filename, Some(&account))
+        .await?
+        .ok_or_else(|| anyhow!("{} not found", path))?;
+
+    if drive_item.folder.is_some() {
+        return Err(anyhow!("{} is a directory, only files can be sent", path));
+    }
+
+    let total_length = drive_item.size.unwrap_or_default() as u64;
+
+    if total_length == 0 {
+        return Err(anyhow!("{} is empty", path));
+    }
+
+    // the zip is written to disk before it's sent
+    let zip_max_size = ENV.get().unwrap().zip_max_size;
+    if flags.zip && zip_max_size > 0 && total_length > zip_max_size.saturating_mul(1024 * 1024) {
+        return Err(anyhow!(
+            "{} is larger than {}MB, which is the limit of --zip, send it without --zip",
+            path,
+            zip_max_size
+        ));
+    }
+
+    let chat_user = telegram_user
+        .get_chat(&ChatEntity::from(message.chat()))
+        .await?;
+
+    let response = format!(
+        "{}\n\n{}",
+        path,
+        format_message_link(chat_user.id(), message.id(), &filename)
+    );
+    let message_indicator_id = message
+        .respond(InputMessage::html(&response))
+        .await
+        .context(response.clone())?
+        .id();
+
+    let chat_bot_hex = message.chat().pack().to_hex();
+    let chat_user_hex = chat_user.pack().to_hex();
+
+    let auto_delete = state.should_auto_delete.load(Ordering::Acquire);
+
+    // in case if cancellation happens before inserting the task
+    let _aborters = state.task_session.task_aborters.lock().await;
+
+    state
+        .task_session
+        .insert_task(InsertTask {
+            cmd_type: CmdType::Get,
+            filename: filename.clone(),
+            root_path,
+            url: None,
+            // nothing is uploaded to onedrive
+            upload_url: String::new(),
+            current_length: 0,
+            total_length: Some(total_length),
+            chat_id: message.chat().id(),
+            chat_bot_hex,
+            chat_user_hex,
+            chat_origin_hex: None,
+            message_id: message.id(),
+            message_indicator_id,
+            message_origin_id: None,
+            auto_delete,
+            media_key: None,
+            conflict_policy: ConflictPolicy::default(),
+            account: Some(account),
+            ignore_schedule: flags.ignore_schedule,
+            zip: flags.zip,
+        })
+        .await?;
+
+    report_scheduled(
+        &message,
+        message_indicator_id,
+        &response,
+        flags.ignore_schedule,
+        &state,
+    )
+    .await?;
+
+    tracing::info!("inserted get task: {} size: {}", filename, total_length);
+
+    Ok(())
+}
@@ -0,0 +1,248 @@
+/*
+:project: telegram-onedrive
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use super::{
+    docs::{format_help, format_unknown_command_help},
+    utils::text::cmd_parser,
+};
+use crate::{
+    history::{HistoryFilter, Outcome, Record},
+    message::TelegramMessage,
+    state::AppState,
+};
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Local, NaiveDate};
+use grammers_client::InputMessage;
+use proc_macros::{check_in_group, check_senders};
+use std::io::Cursor;
+
+pub const PATTERN: &str = "/history";
+
+// telegram rejects longer messages
+const MESSAGE_LENGTH_LIMIT: usize = 4096;
+// errors may contain whole responses from onedrive
+const ERROR_LENGTH_LIMIT: usize = 200;
+
+#[check_senders]
+#[check_in_group]
+pub async fn handler(message: TelegramMessage, state: AppState) -> Result<()> {
+    let cmd = cmd_parser(message.text());
+
+    if cmd.len() == 2 && cmd[1] == "help" {
+        // /history help
+        message
+            .respond(InputMessage::html(format_help(PATTERN)))
+            .await
+            .context("help")?;
+    } else if cmd.len() == 2 && cmd[1] == "clear" {
+        // /history clear
+        state.history.clear(message.chat().id()).await?;
+
+        let response = "History of this chat cleared.";
+        message.respond(response).await.context(response)?;
+    } else if cmd.len() >= 2 && cmd[1] == "export" {
+        // /history export [filters]
+        let (mut filter, _) = parse_filters(&cmd[2..])?;
+        filter.chat_id = Some(message.chat().id());
+
+        send_csv(filter, message, state).await?;
+    } else {
+        // /history [filters]
+        let (mut filter, page) = parse_filters(&cmd[1..])?;
+        filter.chat_id = Some(message.chat().id());
+
+        show_page(filter, page, message, state).await?;
+    }
+
+    Ok(())
+}
+
+// name:$keyword status:$status date:YYYY-MM-DD page:$page
+fn parse_filters(args: &[String]) -> Result<(HistoryFilter, u64)> {
+    let mut filter = HistoryFilter::default();
+    let mut page = 1;
+
+    for arg in args {
+        let (key, value) = arg
+            .split_once(':')
+            .ok_or_else(|| anyhow!("filter should be like key:value"))
+            .context(format_unknown_command_help(PATTERN))?;
+
+        match key {
+            "name" => filter.filename = Some(value.to_string()),
+            "status" => {
+                filter.outcome = Some(
+                    Outcome::parse(value)
+                        .ok_or_else(|| {
+                            anyhow!("status should be completed, failed, skipped or cancelled")
+                        })
+                        .context(format_unknown_command_help(PATTERN))?,
+                );
+            }
+            "date" => {
+                filter.date = Some(
+                    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                        .context("date should be like 2024-01-31")
+                        .context(format_unknown_command_help(PATTERN))?,
+                );
+            }
+            "page" => {
+                page = value
+                    .parse::<u64>()
+                    .ok()
+                    .filter(|page| *page > 0)
+                    .ok_or_else(|| anyhow!("page should be a positive number"))
+                    .context(format_unknown_command_help(PATTERN))?;
+            }
+            _ => {
+                return Err(anyhow!("unknown filter: {}", key))
+                    .context(format_unknown_command_help(PATTERN))
+            }
+        }
+    }
+
+    Ok((filter, page))
+}
+
+async fn show_page(
+    filter: HistoryFilter,
+    page: u64,
+    message: TelegramMessage,
+    state: AppState,
+) -> Result<()> {
+    let (records, pages) = state.history.get_records(&filter, page).await?;
+
+    if records.is_empty() {
+        let response = "No history found.";
+        message.respond(response).await.context(response)?;
+
+        return Ok(());
+    }
+
+    let mut response = format!("History page {}/{}:", page, pages);
+
+    for record in &records {
+        let mut entry = format!(
+            "\n\n{} {}\n{}\n{:.2}MB in {}s, {:.2}MB/s",
+            format_time(record.finished_at),
+            record.outcome,
+            record.path,
+            record.size as f64 / 1024.0 / 1024.0,
+            record.duration / 1000,
+            record.average_speed as f64 / 1024.0 / 1024.0
+        );
+
+        if let Some(error) = &record.error {
+            entry.push_str(&format!("\nError: {}", truncate(error, ERROR_LENGTH_LIMIT)));
+        }
+
+        // the rest of the page goes to another message
+        if response.chars().count() + entry.chars().count() > MESSAGE_LENGTH_LIMIT {
+            message.respond(response.as_str()).await.context(response)?;
+
+            response = entry.trim_start().to_string();
+        } else {
+            response.push_str(&entry);
+        }
+    }
+
+    message.respond(response.as_str()).await.context(response)?;
+
+    Ok(())
+}
+
+async fn send_csv(filter: HistoryFilter, message: TelegramMessage, state: AppState) -> Result<()> {
+    let records = state.history.get_all_records(&filter).await?;
+
+    if records.is_empty() {
+        let response = "No history found.";
+        message.respond(response).await.context(response)?;
+
+        return Ok(());
+    }
+
+    let csv = build_csv(&records);
+    let size = csv.len();
+    let mut stream = Cursor::new(csv.into_bytes());
+
+    let file = state
+        .telegram_bot
+        .upload_stream(&mut stream, size, "history.csv".to_string())
+        .await
+        .context("history csv")?;
+
+    message.respond(InputMessage::default().file(file)).await?;
+
+    Ok(())
+}
+
+fn build_csv(records: &[Record]) -> String {
+    let mut csv = "finished_at,outcome,source,filename,path,item_id,url,chat_id,message_id,origin_chat_id,origin_message_id,account,size,duration_ms,average_speed,error\n".to_string();
+
+    for record in records {
+        let fields = [
+            format_time(record.finished_at),
+            record.outcome.to_string(),
+            record.cmd_type.to_string(),
+            record.filename.clone(),
+            record.path.clone(),
+            record.item_id.clone().unwrap_or_default(),
+            record.url.clone().unwrap_or_default(),
+            record.chat_id.to_string(),
+            record.message_id.to_string(),
+            record
+                .origin_chat_id
+                .map(|id| id.to_string())
+                .unwrap_or_default(),
+            record
+                .origin_message_id
+                .map(|id| id.to_string())
+                .unwrap_or_default(),
+            record.account.clone().unwrap_or_default(),
+            record.size.to_string(),
+            record.duration.to_string(),
+            record.average_speed.to_string(),
+            record.error.clone().unwrap_or_default(),
+        ];
+
+        let line = fields
+            .iter()
+            .map(|field| escape_csv_field(field))
+            .collect::<Vec<_>>()
+            .join(",");
+
+        csv.push_str(&line);
+        csv.push('\n');
+    }
+
+    csv
+}
+
+fn escape_csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn truncate(text: &str, limit: usize) -> String {
+    if text.chars().count() > limit {
+        format!("{}...", text.chars().take(limit).collect::<String>())
+    } else {
+        text.to_string()
+    }
+}
+
+fn format_time(timestamp: i64) -> String {
+    DateTime::from_timestamp(timestamp, 0).map_or_else(String::new, |datetime| {
+        datetime
+            .with_timezone(&Local)
+            .format("%Y-%m-%d %H:%M:%S")
+            .to_string()
+    })
+}
@@ -0,0 +1,116 @@
+/*
+:project: telegram-onedrive
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use super::{
+    docs::{format_help, format_unknown_command_help},
+    utils::text::cmd_parser,
+};
+use crate::{
+    message::TelegramMessage,
+    settings::ChatBandwidthLimit,
+    state::AppState,
+    tasker::{format_rate, Direction},
+};
+use anyhow::{anyhow, Context, Result};
+use grammers_client::InputMessage;
+use proc_macros::{check_in_group, check_senders};
+
+pub const PATTERN: &str = "/limit";
+
+#[check_senders]
+#[check_in_group]
+pub async fn handler(message: TelegramMessage, state: AppState) -> Result<()> {
+    let cmd = cmd_parser(message.text());
+
+    let bandwidth_limiter = &state.bandwidth_limiter;
+    let chat_id = message.chat().id();
+
+    if cmd.len() == 1 {
+        // /limit
+        let global_limits = bandwidth_limiter.get_global_limits().await;
+        let chat_limits = bandwidth_limiter.get_chat_limits(chat_id).await;
+
+        let response = format!(
+            "Global limit:\ndownload {}, upload {}\n\nChat limit:\ndownload {}, upload {}",
+            format_rate(global_limits.download),
+            format_rate(global_limits.upload),
+            format_rate(chat_limits.download),
+            format_rate(chat_limits.upload)
+        );
+        message.respond(response.as_str()).await.context(response)?;
+    } else if cmd.len() == 2 && cmd[1] == "help" {
+        // /limit help
+        message
+            .respond(InputMessage::html(format_help(PATTERN)))
+            .await
+            .context("help")?;
+    } else if cmd.len() == 3 {
+        // /limit $direction $rate
+        let direction = parse_direction(&cmd[1])?;
+        let rate = parse_rate(&cmd[2])?;
+
+        bandwidth_limiter.set_global_limit(direction, rate).await;
+
+        match direction {
+            Direction::Download => state.settings.set_download_limit(rate).await?,
+            Direction::Upload => state.settings.set_upload_limit(rate).await?,
+        }
+
+        let response = format!("Global {} limit set to {}.", direction, format_rate(rate));
+        message.respond(response.as_str()).await.context(response)?;
+    } else if cmd.len() == 4 && cmd[1] == "chat" {
+        // /limit chat $direction $rate
+        let direction = parse_direction(&cmd[2])?;
+        let rate = parse_rate(&cmd[3])?;
+
+        bandwidth_limiter
+            .set_chat_limit(chat_id, direction, rate)
+            .await;
+
+        let chat_limits = bandwidth_limiter.get_chat_limits(chat_id).await;
+        if chat_limits.is_unlimited() {
+            state.settings.delete_chat_bandwidth_limit(chat_id).await?;
+        } else {
+            state
+                .settings
+                .set_chat_bandwidth_limit(ChatBandwidthLimit {
+                    chat_id,
+                    download: chat_limits.download as i64,
+                    upload: chat_limits.upload as i64,
+                })
+                .await?;
+        }
+
+        let response = format!("Chat {} limit set to {}.", direction, format_rate(rate));
+        message.respond(response.as_str()).await.context(response)?;
+    } else {
+        return Err(anyhow!("command error")).context(format_unknown_command_help(PATTERN));
+    }
+
+    Ok(())
+}
+
+fn parse_direction(direction: &str) -> Result<Direction> {
+    match direction {
+        "download" => Ok(Direction::Download),
+        "upload" => Ok(Direction::Upload),
+        _ => Err(anyhow!("direction should be download or upload"))
+            .context(format_unknown_command_help(PATTERN)),
+    }
+}
+
+// KB/s to bytes per second
+fn parse_rate(rate: &str) -> Result<u64> {
+    let rate = rate
+        .parse::<u64>()
+        .context("rate should be a number in KB/s")
+        .context(format_unknown_command_help(PATTERN))?;
+
+    rate.checked_mul(1024)
+        .ok_or_else(|| anyhow!("rate is too large"))
+        .context(format_unknown_command_help(PATTERN))
+}
@@ -5,17 +5,31 @@
 :license: MIT, see LICENSE for more details.
 */
 
-use std::sync::atomic::Ordering;
+use std::{path::Path, sync::atomic::Ordering};
 
-use super::utils::{message::get_message_from_link, upload::upload_thumb};
+use super::utils::{
+    message::get_message_from_link,
+    text::{cmd_parser, flags_parser, Flags},
+    upload::upload_thumb,
+};
 use crate::{
-    handlers::utils::{get_tg_file_size, message::format_message_link, preprocess_tg_file_name},
+    handlers::utils::{
+        dedup::{find_existing_item, get_tg_media_key, report_skipped},
+        filter::reject_filtered_media,
+        get_tg_file_size,
+        message::format_message_link,
+        preprocess_tg_file_name,
+        route::get_account,
+        schedule::report_scheduled,
+        template::{apply_name_template, TemplateContext},
+    },
     message::{ChatEntity, TelegramMessage},
     state::AppState,
     tasker::{CmdType, InsertTask},
 };
 use anyhow::{anyhow, Context, Result};
 use grammers_client::{types::Media, InputMessage};
+use path_slash::PathBufExt;
 use proc_macros::{check_in_group, check_od_login, check_senders, check_tg_login};
 
 #[check_od_login]
@@ -23,14 +37,32 @@ use proc_macros::{check_in_group, check_od_login, check_senders, check_tg_login}
 #[check_senders]
 #[check_in_group]
 pub async fn handler(message: TelegramMessage, state: AppState) -> Result<()> {
+    // the link may be followed by flags like --replace
+    let (cmd, flags) = flags_parser(cmd_parser(message.text()))?;
+
+    let link = cmd
+        .first()
+        .cloned()
+        .ok_or_else(|| anyhow!("message link not found"))?;
+
+    let message_origin = get_message_from_link(&state.telegram_user, &link).await?;
+
+    insert_link_task(&message, &message_origin, &link, &flags, None, &state).await
+}
+
+// dir is created under the root path of the account, an absolute dir replaces the root path
+pub async fn insert_link_task(
+    message: &TelegramMessage,
+    message_origin: &TelegramMessage,
+    link: &str,
+    flags: &Flags,
+    dir: Option<&str>,
+    state: &AppState,
+) -> Result<()> {
     let telegram_user = &state.telegram_user;
     let onedrive = &state.onedrive;
     let task_session = &state.task_session;
 
-    let link = message.text();
-
-    let message_origin = get_message_from_link(telegram_user, &link).await?;
-
     let chat_user = telegram_user
         .get_chat(&ChatEntity::from(message.chat()))
         .await?;
@@ -39,10 +71,17 @@ pub async fn handler(message: TelegramMessage, state: AppState) -> Result<()> {
         .media()
         .ok_or_else(|| anyhow!("message does not contain any media"))?;
 
+    // the upload filter of the chat the command is sent to
+    if reject_filtered_media(message, &media, state).await? {
+        return Ok(());
+    }
+
     let filename = preprocess_tg_file_name(&media);
 
     let total_length = get_tg_file_size(&media);
 
+    let media_key = get_tg_media_key(&media);
+
     let cmd_type = match media {
         Media::Photo(_) | Media::Document(_) | Media::Sticker(_) => CmdType::Link,
         _ => Err(anyhow!(
@@ -73,20 +112,68 @@ pub async fn handler(message: TelegramMessage, state: AppState) -> Result<()> {
             .respond(InputMessage::html(&response).photo(uploaded))
             .await
             .context("linked message with thumb")
-            .context(response)?
+            .context(response.clone())?
             .id(),
         None => message
             .respond(InputMessage::html(&response))
             .await
             .context("linked message without thumn")
-            .context(response)?
+            .context(response.clone())?
             .id(),
     };
 
-    let root_path = onedrive.get_root_path(true).await?;
+    let account = get_account(message, &filename, Some(total_length), state).await?;
+
+    let root_path = onedrive.get_account_root_path(&account, true).await?;
+
+    let root_path = match dir {
+        Some(dir) => {
+            let path = Path::new(&root_path).join(dir).to_slash_lossy().to_string();
+
+            onedrive.create_dir_all(&path, Some(&account)).await?;
+
+            path
+        }
+        None => root_path,
+    };
+
+    let (root_path, filename) = apply_name_template(
+        state,
+        chat_user.id(),
+        &root_path,
+        &account,
+        &TemplateContext::from_message(message_origin, &filename),
+    )
+    .await?;
+
+    let conflict_policy = match flags.conflict_policy {
+        Some(conflict_policy) => conflict_policy,
+        None => {
+            state
+                .settings
+                .get_conflict_policy(chat_user.id(), &root_path)
+                .await?
+        }
+    };
+
+    if let Some(drive_item) = find_existing_item(
+        state,
+        media_key.as_deref(),
+        &root_path,
+        &filename,
+        Some(total_length),
+        conflict_policy,
+        &account,
+    )
+    .await?
+    {
+        report_skipped(message, message_indicator_id, &response, &drive_item).await?;
+
+        return Ok(());
+    }
 
     let (upload_session, upload_session_meta) = onedrive
-        .multipart_upload_session_builder(&root_path, &filename)
+        .multipart_upload_session_builder(&root_path, &filename, conflict_policy, Some(&account))
         .await?;
 
     // all task should be new, so this should always be 0
@@ -109,7 +196,7 @@ pub async fn handler(message: TelegramMessage, state: AppState) -> Result<()> {
             url: None,
             upload_url: upload_session.upload_url().to_string(),
             current_length,
-            total_length,
+            total_length: Some(total_length),
             chat_id: chat_user.id(),
             chat_bot_hex,
             chat_user_hex,
@@ -118,9 +205,23 @@ pub async fn handler(message: TelegramMessage, state: AppState) -> Result<()> {
             message_indicator_id,
             message_origin_id: Some(message_origin.id()),
             auto_delete,
+            media_key,
+            conflict_policy,
+            account: Some(account),
+            ignore_schedule: flags.ignore_schedule,
+            zip: false,
         })
         .await?;
 
+    report_scheduled(
+        message,
+        message_indicator_id,
+        &response,
+        flags.ignore_schedule,
+        state,
+    )
+    .await?;
+
     tracing::info!("inserted link task: {} size: {}", filename, total_length);
 
     Ok(())
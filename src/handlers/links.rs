@@ -10,7 +10,7 @@ use super::{
     link,
     utils::{
         message::{get_message_info, get_message_link},
-        text::cmd_parser,
+        text::{cmd_parser, flags_parser},
     },
 };
 use crate::{
@@ -30,7 +30,8 @@ pub const PATTERN: &str = "/links";
 #[check_senders]
 #[check_in_group]
 pub async fn handler(message: TelegramMessage, state: AppState) -> Result<()> {
-    let cmd = cmd_parser(message.text());
+    let (cmd, flags) =
+        flags_parser(cmd_parser(message.text())).context(format_unknown_command_help(PATTERN))?;
 
     if cmd.len() == 2 && cmd[1] == "help" {
         // /links help
@@ -39,7 +40,7 @@ pub async fn handler(message: TelegramMessage, state: AppState) -> Result<()> {
             .await
             .context("help")?;
     } else if cmd.len() == 3 {
-        // /links $message_link $num
+        // /links $message_link $num [--rename|--replace|--skip]
         let link_head = &cmd[1];
         let link_num = cmd[2]
             .parse::<usize>()
@@ -77,8 +78,13 @@ pub async fn handler(message: TelegramMessage, state: AppState) -> Result<()> {
                 let message_origin_id = head_message_id + offset as i32;
                 let message_link = get_message_link(&chat_entity, message_origin_id);
 
+                // pass the flags to each link
+                let text = [vec![message_link.clone()], flags.to_args()]
+                    .concat()
+                    .join(" ");
+
                 let mut message_clone = message.clone();
-                message_clone.override_text(message_link.clone());
+                message_clone.override_text(text);
 
                 if link::handler(message_clone, state.clone()).await.is_err() {
                     message
@@ -0,0 +1,85 @@
+/*
+:project: telegram-onedrive
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use super::{
+    docs::{format_help, format_unknown_command_help},
+    utils::{resolve_remote_path, text::cmd_parser},
+};
+use crate::{message::TelegramMessage, state::AppState};
+use anyhow::{anyhow, Context, Result};
+use grammers_client::InputMessage;
+use proc_macros::{check_in_group, check_od_login, check_senders};
+
+pub const PATTERN: &str = "/ls";
+
+// keep the response within the message length limit
+const MAX_ITEMS: usize = 50;
+
+#[check_od_login]
+#[check_senders]
+#[check_in_group]
+pub async fn handler(message: TelegramMessage, state: AppState) -> Result<()> {
+    let onedrive = &state.onedrive;
+
+    let cmd = cmd_parser(message.text());
+
+    let path = if cmd.len() == 1 {
+        // /ls
+        onedrive.get_root_path(false).await?
+    } else if cmd.len() == 2 && cmd[1] == "help" {
+        // /ls help
+        message
+            .respond(InputMessage::html(format_help(PATTERN)))
+            .await
+            .context("help")?;
+
+        return Ok(());
+    } else if cmd.len() == 2 {
+        // /ls $path
+        resolve_remote_path(onedrive, &cmd[1]).await?
+    } else {
+        return Err(anyhow!("command error")).context(format_unknown_command_help(PATTERN));
+    };
+
+    let mut drive_items = onedrive.list_items(&path).await?;
+
+    // directories first
+    drive_items.sort_by_key(|drive_item| {
+        (
+            drive_item.folder.is_none(),
+            drive_item.name.clone().unwrap_or_default().to_lowercase(),
+        )
+    });
+
+    let mut response = format!("{}\n", path);
+
+    if drive_items.is_empty() {
+        response.push_str("\nEmpty directory.");
+    }
+
+    for drive_item in drive_items.iter().take(MAX_ITEMS) {
+        let name = drive_item.name.as_deref().unwrap_or_default();
+
+        if drive_item.folder.is_some() {
+            response.push_str(&format!("\n{}/", name));
+        } else {
+            response.push_str(&format!(
+                "\n{} {:.2}MB",
+                name,
+                drive_item.size.unwrap_or_default() as f64 / 1024.0 / 1024.0
+            ));
+        }
+    }
+
+    if drive_items.len() > MAX_ITEMS {
+        response.push_str(&format!("\n\nand {} more.", drive_items.len() - MAX_ITEMS));
+    }
+
+    message.respond(response.as_str()).await.context(response)?;
+
+    Ok(())
+}
@@ -0,0 +1,265 @@
+/*
+:project: telegram-onedrive
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use super::{
+    docs::{format_help, format_unknown_command_help},
+    link::insert_link_task,
+    utils::{
+        filter::MediaFilter,
+        message::{get_chat_entity, get_message_link},
+        preprocess_dir_name,
+        text::{cmd_parser, flags_parser, Flags},
+    },
+};
+use crate::{
+    error::{ErrorExt, ResultExt, ResultUnwrapExt},
+    message::{ChatEntity, TelegramMessage},
+    state::AppState,
+    tasker::BatchAborter,
+};
+use anyhow::{anyhow, Context, Error, Result};
+use chrono::{Local, NaiveDate};
+use grammers_client::{types::Chat, InputMessage};
+use proc_macros::{check_in_group, check_od_login, check_senders, check_tg_login};
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+pub const PATTERN: &str = "/mirror";
+
+// messages fetched at once, the most that telegram returns by id
+const BATCH_SIZE: usize = 100;
+// the bot sends a progress message for each task, keep pace with the message loop to avoid FLOOD_WAIT
+const INSERT_INTERVAL: Duration = Duration::from_secs(3);
+
+// which messages of the chat are mirrored
+struct MirrorFilter {
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    media: MediaFilter,
+}
+
+#[check_od_login]
+#[check_tg_login]
+#[check_senders]
+#[check_in_group]
+pub async fn handler(message: TelegramMessage, state: AppState) -> Result<()> {
+    let (cmd, flags) =
+        flags_parser(cmd_parser(message.text())).context(format_unknown_command_help(PATTERN))?;
+
+    if cmd.len() == 2 && cmd[1] == "help" {
+        // /mirror help
+        message
+            .respond(InputMessage::html(format_help(PATTERN)))
+            .await
+            .context("help")?;
+    } else if cmd.len() == 3 && cmd[1] == "reset" {
+        // /mirror reset $chat_link
+        let chat = get_source_chat(&cmd[2], &state).await?;
+
+        state
+            .settings
+            .delete_mirror_cursor(chat.id(), message.chat().id())
+            .await?;
+
+        let response = format!("Mirror cursor of {} removed.", chat.name());
+        message.respond(response.as_str()).await.context(response)?;
+    } else if cmd.len() >= 2 {
+        // /mirror $chat_link [$from] [$to] [$filters] [--rename|--replace|--skip] [--now]
+        let chat = get_source_chat(&cmd[1], &state).await?;
+        let filter = parse_filter(&cmd[2..])?;
+
+        let chat_user = state
+            .telegram_user
+            .get_chat(&ChatEntity::from(message.chat()))
+            .await?;
+
+        let mut batch_aborters = state.task_session.batch_aborters.lock().await;
+        // /mirror may be in a batch
+        #[allow(clippy::option_if_let_else)]
+        let (cancellation_token, wrapped_in_batch) =
+            if let Some(batch_aborter) = batch_aborters.get(&(chat_user.id(), message.id())) {
+                (batch_aborter.token.clone(), true)
+            } else {
+                let batch_aborter = BatchAborter::new();
+                let cancellation_token = batch_aborter.token.clone();
+                batch_aborters.insert((chat_user.id(), message.id()), batch_aborter);
+
+                (cancellation_token, false)
+            };
+        // allow cancellation
+        drop(batch_aborters);
+
+        if wrapped_in_batch {
+            // the batch goes on after the mirror, and keeps its message until then
+            mirror(&message, chat, &filter, &flags, cancellation_token, &state).await?;
+        } else {
+            let response = format!("Mirroring {}, delete this command to stop it.", chat.name());
+            message.respond(response.as_str()).await.context(response)?;
+
+            // walking the history of a large chat takes hours, other commands are handled meanwhile
+            tokio::spawn(async move {
+                if let Err(e) =
+                    mirror(&message, chat, &filter, &flags, cancellation_token, &state).await
+                {
+                    e.send(message.clone()).await.unwrap_both().trace();
+                }
+
+                let mut batch_aborters = state.task_session.batch_aborters.lock().await;
+                if let Some(batch_aborter) = batch_aborters.get_mut(&(chat_user.id(), message.id()))
+                {
+                    batch_aborter.processing = false;
+                }
+            });
+        }
+    } else {
+        return Err(anyhow!("command error")).context(format_unknown_command_help(PATTERN));
+    }
+
+    Ok(())
+}
+
+async fn get_source_chat(link: &str, state: &AppState) -> Result<Chat> {
+    let chat_entity = get_chat_entity(link).context(format_unknown_command_help(PATTERN))?;
+
+    state.telegram_user.get_chat(&chat_entity).await
+}
+
+// [$from] [$to] type:$types min:$mb max:$mb caption:$regex
+fn parse_filter(args: &[String]) -> Result<MirrorFilter> {
+    let mut media = MediaFilter::default();
+    let mut dates = Vec::new();
+
+    for arg in args {
+        if let Some((key, value)) = arg.split_once(':') {
+            media
+                .set(key, value)
+                .context(format_unknown_command_help(PATTERN))?;
+        } else {
+            dates.push(
+                NaiveDate::parse_from_str(arg, "%Y-%m-%d")
+                    .context("date should be like 2024-01-31")
+                    .context(format_unknown_command_help(PATTERN))?,
+            );
+        }
+    }
+
+    let (from, to) = match dates.as_slice() {
+        [] => (None, None),
+        [from] => (Some(*from), None),
+        [from, to] => (Some(*from), Some(*to)),
+        _ => return Err(anyhow!("too many dates")).context(format_unknown_command_help(PATTERN)),
+    };
+
+    Ok(MirrorFilter { from, to, media })
+}
+
+async fn mirror(
+    message: &TelegramMessage,
+    chat: Chat,
+    filter: &MirrorFilter,
+    flags: &Flags,
+    cancellation_token: CancellationToken,
+    state: &AppState,
+) -> Result<()> {
+    let telegram_user = &state.telegram_user;
+    let settings = &state.settings;
+
+    let group_id = message.chat().id();
+    // shared by all filters, messages skipped by the filters of one run are not mirrored by later runs
+    let cursor = settings.get_mirror_cursor(chat.id(), group_id).await?;
+
+    let chat_dir = preprocess_dir_name(chat.name()).unwrap_or_else(|| chat.id().to_string());
+    let chat_entity = ChatEntity::from(chat.clone());
+
+    let fut = async {
+        // messages come from the latest one, they are queued from the earliest one
+        let mut messages = telegram_user.iter_messages(&chat);
+        // only ids are kept, messages are fetched again batch by batch
+        let mut matched_ids = Vec::new();
+
+        while let Some(message_raw) = messages
+            .next()
+            .await
+            .context("failed to get next message")?
+        {
+            let message_origin = TelegramMessage::new(telegram_user.clone(), message_raw);
+
+            // only new messages since the last mirror
+            if cursor.is_some_and(|cursor| message_origin.id() <= cursor) {
+                break;
+            }
+
+            let date = message_origin.raw.date().with_timezone(&Local).date_naive();
+
+            if filter.from.is_some_and(|from| date < from) {
+                break;
+            }
+
+            if filter.to.is_some_and(|to| date > to) {
+                continue;
+            }
+
+            if filter.media.matches(&message_origin) {
+                matched_ids.push(message_origin.id());
+            }
+        }
+
+        matched_ids.reverse();
+
+        let response = format!(
+            "Mirroring {} files from {} to {}.",
+            matched_ids.len(),
+            chat.name(),
+            chat_dir
+        );
+        message.respond(response.as_str()).await.context(response)?;
+
+        for message_ids in matched_ids.chunks(BATCH_SIZE) {
+            let message_origins = telegram_user.get_messages(&chat, message_ids).await?;
+
+            for message_origin in &message_origins {
+                let message_link = get_message_link(&chat_entity, message_origin.id());
+
+                let date = message_origin.raw.date().with_timezone(&Local);
+                // chat/2024-01
+                let sub_dir = format!("{}/{}", chat_dir, date.format("%Y-%m"));
+
+                // stop at the first failure so that the cursor never passes it
+                if let Err(e) = insert_link_task(
+                    message,
+                    message_origin,
+                    &message_link,
+                    flags,
+                    Some(&sub_dir),
+                    state,
+                )
+                .await
+                {
+                    message
+                        .reply(format!("failed to mirror {}, stopped: {}", message_link, e))
+                        .await
+                        .trace();
+
+                    return Ok(());
+                }
+
+                settings
+                    .set_mirror_cursor(chat.id(), group_id, message_origin.id())
+                    .await?;
+
+                tokio::time::sleep(INSERT_INTERVAL).await;
+            }
+        }
+
+        Ok::<(), Error>(())
+    };
+
+    tokio::select! {
+        result = fut => result,
+        () = cancellation_token.cancelled() => Ok(()),
+    }
+}
@@ -8,16 +8,37 @@
 pub mod auth;
 pub mod auto_delete;
 // pub mod batch;
+pub mod browse;
 pub mod clear;
+pub mod conflict;
 pub mod dir;
 mod docs;
 pub mod drive;
 pub mod file;
+pub mod filter;
+pub mod get;
 pub mod help;
+pub mod history;
+pub mod limit;
 pub mod link;
 pub mod links;
 pub mod logs;
+pub mod ls;
+pub mod mirror;
+pub mod mkdir;
+pub mod mv;
+pub mod pause;
+pub mod queue;
+pub mod quota;
+pub mod resume;
+pub mod retry;
+pub mod rm;
+pub mod route;
+pub mod schedule;
+pub mod share;
 pub mod start;
+pub mod template;
 pub mod url;
 mod utils;
 pub mod version;
+pub mod watch;
@@ -0,0 +1,120 @@
+/*
+:project: telegram-onedrive
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use super::{
+    docs::{format_help, format_unknown_command_help},
+    utils::text::cmd_parser,
+};
+use crate::{message::TelegramMessage, state::AppState, tasker::TaskStatus};
+use anyhow::{anyhow, Context, Result};
+use grammers_client::InputMessage;
+use proc_macros::{check_in_group, check_senders};
+
+pub const PATTERN: &str = "/pause";
+
+#[check_senders]
+#[check_in_group]
+pub async fn handler(message: TelegramMessage, state: AppState) -> Result<()> {
+    let cmd = cmd_parser(message.text());
+
+    let chat_id = message.chat().id();
+
+    if cmd.len() == 1 {
+        match message.reply_to_message_id() {
+            // /pause as a reply to the indicator of a task
+            Some(message_indicator_id) => {
+                pause_task(chat_id, message_indicator_id, &state).await?;
+
+                let response = "Task paused.";
+                message.respond(response).await.context(response)?;
+            }
+            // /pause
+            None => {
+                let paused_num = pause_tasks(Some(chat_id), &state).await?;
+
+                let response = format!("Paused {} tasks of this chat.", paused_num);
+                message.respond(response.as_str()).await.context(response)?;
+            }
+        }
+    } else if cmd.len() == 2 && cmd[1] == "all" {
+        // /pause all
+        state.task_session.set_queue_paused(true);
+        state.settings.set_queue_paused(true).await?;
+
+        let paused_num = pause_tasks(None, &state).await?;
+
+        let response = format!(
+            "Paused {} tasks, new tasks wait until /resume all.",
+            paused_num
+        );
+        message.respond(response.as_str()).await.context(response)?;
+    } else if cmd.len() == 2 && cmd[1] == "help" {
+        // /pause help
+        message
+            .respond(InputMessage::html(format_help(PATTERN)))
+            .await
+            .context("help")?;
+    } else {
+        return Err(anyhow!("command error")).context(format_unknown_command_help(PATTERN));
+    }
+
+    Ok(())
+}
+
+async fn pause_task(chat_id: i64, message_indicator_id: i32, state: &AppState) -> Result<()> {
+    let task_session = &state.task_session;
+
+    // hold the aborters, so that the task won't be fetched meanwhile
+    let task_aborters = task_session.task_aborters.lock().await;
+
+    if let Some(task_aborter) = task_aborters.get(&(chat_id, message_indicator_id)) {
+        task_aborter.pause();
+
+        return Ok(());
+    }
+
+    let task = task_session
+        .get_task_from_message_indicator_id(chat_id, message_indicator_id)
+        .await?
+        .ok_or_else(|| anyhow!("the replied message is not a task"))?;
+
+    if !task_session
+        .switch_task_status(task.id, TaskStatus::Waiting, TaskStatus::Paused)
+        .await?
+    {
+        return Err(anyhow!("task {} is {}", task.filename, task.status))
+            .context("only waiting or running tasks can be paused");
+    }
+
+    Ok(())
+}
+
+// all chats if chat id is none, returns the number of paused tasks
+async fn pause_tasks(chat_id: Option<i64>, state: &AppState) -> Result<u64> {
+    let task_session = &state.task_session;
+
+    // hold the aborters, so that no task is fetched between pausing running and waiting tasks
+    let task_aborters = task_session.task_aborters.lock().await;
+
+    let mut paused_num = 0;
+
+    for ((task_chat_id, _), task_aborter) in task_aborters.iter() {
+        if chat_id.is_none_or(|chat_id| chat_id == *task_chat_id)
+            && !task_aborter.pause_token.is_cancelled()
+        {
+            task_aborter.pause();
+
+            paused_num += 1;
+        }
+    }
+
+    paused_num += task_session.pause_waiting_tasks(chat_id).await?;
+
+    drop(task_aborters);
+
+    Ok(paused_num)
+}
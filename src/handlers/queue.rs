@@ -0,0 +1,134 @@
+/*
+:project: telegram-onedrive
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use super::{
+    docs::{format_help, format_unknown_command_help},
+    utils::text::cmd_parser,
+};
+use crate::{
+    client::utils::chat_from_hex, message::TelegramMessage, state::AppState, tasker::TaskStatus,
+};
+use anyhow::{anyhow, Context, Result};
+use grammers_client::InputMessage;
+use proc_macros::{check_in_group, check_senders};
+
+pub const PATTERN: &str = "/queue";
+
+#[check_senders]
+#[check_in_group]
+pub async fn handler(message: TelegramMessage, state: AppState) -> Result<()> {
+    let cmd = cmd_parser(message.text());
+
+    let task_session = &state.task_session;
+    let chat_id = message.chat().id();
+
+    if cmd.len() == 1 {
+        // /queue
+        let tasks = task_session.get_queued_tasks(chat_id).await?;
+
+        if tasks.is_empty() {
+            let response = "No queued task.";
+            message.respond(response).await.context(response)?;
+
+            return Ok(());
+        }
+
+        let mut response = "Queued tasks:".to_string();
+
+        for (index, task) in tasks.iter().enumerate() {
+            response.push_str(&format!("\n{}. {}", index + 1, task.filename));
+
+            if let Some(total_length) = task.total_length {
+                response.push_str(&format!(
+                    " ({:.2}MB)",
+                    total_length as f64 / 1024.0 / 1024.0
+                ));
+            }
+
+            if task.status == TaskStatus::Paused {
+                response.push_str(" [paused]");
+            }
+        }
+
+        message.respond(response.as_str()).await.context(response)?;
+    } else if cmd.len() == 2 && cmd[1] == "help" {
+        // /queue help
+        message
+            .respond(InputMessage::html(format_help(PATTERN)))
+            .await
+            .context("help")?;
+    } else if cmd.len() == 3 {
+        // /queue $action $position
+        let position = cmd[2]
+            .parse::<usize>()
+            .ok()
+            .filter(|position| *position > 0)
+            .ok_or_else(|| anyhow!("position should be a positive number"))
+            .context(format_unknown_command_help(PATTERN))?;
+
+        let tasks = task_session.get_queued_tasks(chat_id).await?;
+        let task = tasks
+            .get(position - 1)
+            .ok_or_else(|| anyhow!("no queued task at position {}", position))?;
+
+        let done = match cmd[1].as_str() {
+            "bump" => task_session.bump_task(task.id).await?,
+            "pause" => {
+                task_session
+                    .switch_task_status(task.id, TaskStatus::Waiting, TaskStatus::Paused)
+                    .await?
+            }
+            "resume" => {
+                task_session
+                    .switch_task_status(task.id, TaskStatus::Paused, TaskStatus::Waiting)
+                    .await?
+            }
+            "cancel" => {
+                let deleted = task_session.delete_queued_task(task.id).await?;
+
+                if deleted {
+                    let chat_bot = chat_from_hex(&task.chat_bot_hex)?;
+
+                    state
+                        .telegram_bot
+                        .delete_messages(chat_bot, &[task.message_indicator_id])
+                        .await?;
+                }
+
+                deleted
+            }
+            _ => {
+                return Err(anyhow!("command error")).context(format_unknown_command_help(PATTERN))
+            }
+        };
+
+        let response = if done {
+            format!("Task {} {}.", task.filename, past_tense(&cmd[1]))
+        } else {
+            format!(
+                "Task {} can't be {}, its status has changed.",
+                task.filename,
+                past_tense(&cmd[1])
+            )
+        };
+        message.respond(response.as_str()).await.context(response)?;
+    } else {
+        return Err(anyhow!("command error")).context(format_unknown_command_help(PATTERN));
+    }
+
+    Ok(())
+}
+
+fn past_tense(action: &str) -> &str {
+    match action {
+        "bump" => "bumped",
+        "pause" => "paused",
+        "resume" => "resumed",
+        "cancel" => "cancelled",
+        _ => action,
+    }
+}
@@ -0,0 +1,114 @@
+/*
+:project: telegram-onedrive
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use super::{
+    docs::{format_help, format_unknown_command_help},
+    utils::text::cmd_parser,
+};
+use crate::{env::ENV, message::TelegramMessage, state::AppState};
+use anyhow::{anyhow, Context, Result};
+use grammers_client::InputMessage;
+use proc_macros::{check_in_group, check_od_login, check_senders};
+
+pub const PATTERN: &str = "/quota";
+
+#[check_od_login]
+#[check_senders]
+#[check_in_group]
+pub async fn handler(message: TelegramMessage, state: AppState) -> Result<()> {
+    let cmd = cmd_parser(message.text());
+
+    let settings = &state.settings;
+    let chat_id = message.chat().id();
+
+    if cmd.len() == 1 {
+        // /quota
+        show_quota(message, &state).await?;
+    } else if cmd.len() == 2 && cmd[1] == "help" {
+        // /quota help
+        message
+            .respond(InputMessage::html(format_help(PATTERN)))
+            .await
+            .context("help")?;
+    } else if cmd.len() == 3 && cmd[1] == "alert" && cmd[2] == "on" {
+        // /quota alert on
+        let chat_bot_hex = message.chat().pack().to_hex();
+
+        settings
+            .set_quota_alert_chat(chat_id, &chat_bot_hex)
+            .await?;
+
+        let response = format!(
+            "This chat will be warned if a drive has less than {}MB left or queued tasks don't fit.",
+            ENV.get().unwrap().quota_alert_threshold
+        );
+        message.respond(response.as_str()).await.context(response)?;
+    } else if cmd.len() == 3 && cmd[1] == "alert" && cmd[2] == "off" {
+        // /quota alert off
+        settings.delete_quota_alert_chat(chat_id).await?;
+
+        let response = "Quota alerts of this chat turned off.";
+        message.respond(response).await.context(response)?;
+    } else {
+        return Err(anyhow!("command error")).context(format_unknown_command_help(PATTERN));
+    }
+
+    Ok(())
+}
+
+async fn show_quota(message: TelegramMessage, state: &AppState) -> Result<()> {
+    let onedrive = &state.onedrive;
+
+    let usernames = onedrive.get_usernames().await?;
+
+    let mut response = String::new();
+
+    for username in &usernames {
+        response.push_str(&format!("{}\n", username));
+
+        match onedrive.get_quota(Some(username)).await? {
+            Some(quota) => response.push_str(&format!(
+                "Total {}\nUsed {}\nDeleted {}\nRemaining {}\n",
+                format_size(quota.total),
+                format_size(quota.used),
+                format_size(quota.deleted),
+                format_size(quota.remaining)
+            )),
+            None => response.push_str("Quota unknown\n"),
+        }
+
+        let root_path = onedrive.get_account_root_path(username, false).await?;
+
+        match onedrive.get_dir_size(&root_path, Some(username)).await? {
+            Some(dir_size) => {
+                response.push_str(&format!("{} {}\n", root_path, format_size(dir_size)));
+            }
+            None => response.push_str(&format!("{} not created yet\n", root_path)),
+        }
+
+        response.push('\n');
+    }
+
+    let alert = if state
+        .settings
+        .is_quota_alert_chat(message.chat().id())
+        .await?
+    {
+        "on"
+    } else {
+        "off"
+    };
+    response.push_str(&format!("Quota alerts of this chat: {}", alert));
+
+    message.respond(response.as_str()).await.context(response)?;
+
+    Ok(())
+}
+
+fn format_size(size: u64) -> String {
+    format!("{:.2}GB", size as f64 / 1024.0 / 1024.0 / 1024.0)
+}
@@ -0,0 +1,97 @@
+/*
+:project: telegram-onedrive
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use super::{
+    docs::{format_help, format_unknown_command_help},
+    utils::text::cmd_parser,
+};
+use crate::{
+    error::{ErrorExt, ResultUnwrapExt},
+    message::TelegramMessage,
+    state::AppState,
+    tasker::{resume_paused_task, TaskStatus},
+};
+use anyhow::{anyhow, Context, Result};
+use grammers_client::InputMessage;
+use proc_macros::{check_in_group, check_od_login, check_senders, check_tg_login};
+
+pub const PATTERN: &str = "/resume";
+
+#[check_od_login]
+#[check_tg_login]
+#[check_senders]
+#[check_in_group]
+pub async fn handler(message: TelegramMessage, state: AppState) -> Result<()> {
+    let cmd = cmd_parser(message.text());
+
+    let task_session = &state.task_session;
+    let chat_id = message.chat().id();
+
+    let tasks = if cmd.len() == 1 {
+        match message.reply_to_message_id() {
+            // /resume as a reply to the indicator of a paused task
+            Some(message_indicator_id) => {
+                let task = task_session
+                    .get_task_from_message_indicator_id(chat_id, message_indicator_id)
+                    .await?
+                    .filter(|task| task.status == TaskStatus::Paused)
+                    .ok_or_else(|| anyhow!("the replied message is not a paused task"))?;
+
+                vec![task]
+            }
+            // /resume
+            None => task_session.get_paused_tasks(Some(chat_id)).await?,
+        }
+    } else if cmd.len() == 2 && cmd[1] == "all" {
+        // /resume all
+        task_session.set_queue_paused(false);
+        state.settings.set_queue_paused(false).await?;
+
+        task_session.get_paused_tasks(None).await?
+    } else if cmd.len() == 2 && cmd[1] == "help" {
+        // /resume help
+        message
+            .respond(InputMessage::html(format_help(PATTERN)))
+            .await
+            .context("help")?;
+
+        return Ok(());
+    } else {
+        return Err(anyhow!("command error")).context(format_unknown_command_help(PATTERN));
+    };
+
+    if tasks.is_empty() {
+        let response = "No paused task to resume.";
+        message.respond(response).await.context(response)?;
+
+        return Ok(());
+    }
+
+    let mut resumed_num = 0;
+
+    for task in tasks {
+        match resume_paused_task(&task, &state).await {
+            Ok(true) => resumed_num += 1,
+            Ok(false) => {
+                let response = format!("Task {} is not paused anymore.", task.filename);
+                message.respond(response.as_str()).await.context(response)?;
+            }
+            Err(e) => {
+                e.context(format!("failed to resume task {}", task.filename))
+                    .send(message.clone())
+                    .await
+                    .unwrap_both()
+                    .trace();
+            }
+        }
+    }
+
+    let response = format!("Resumed {} paused tasks.", resumed_num);
+    message.respond(response.as_str()).await.context(response)?;
+
+    Ok(())
+}
@@ -0,0 +1,84 @@
+/*
+:project: telegram-onedrive
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use super::{
+    docs::{format_help, format_unknown_command_help},
+    utils::text::cmd_parser,
+};
+use crate::{
+    error::{ErrorExt, ResultUnwrapExt},
+    message::TelegramMessage,
+    state::AppState,
+    tasker::retry_failed_task,
+};
+use anyhow::{anyhow, Context, Result};
+use grammers_client::InputMessage;
+use proc_macros::{check_in_group, check_od_login, check_senders, check_tg_login};
+
+pub const PATTERN: &str = "/retry";
+
+#[check_od_login]
+#[check_tg_login]
+#[check_senders]
+#[check_in_group]
+pub async fn handler(message: TelegramMessage, state: AppState) -> Result<()> {
+    let cmd = cmd_parser(message.text());
+
+    if cmd.len() == 1 {
+        let task_session = &state.task_session;
+        let chat_id = message.chat().id();
+
+        let tasks = match message.reply_to_message_id() {
+            // /retry as a reply to the indicator of a failed task
+            Some(message_indicator_id) => {
+                let task = task_session
+                    .get_failed_task_from_message_indicator_id(chat_id, message_indicator_id)
+                    .await?
+                    .ok_or_else(|| anyhow!("the replied message is not a failed task"))?;
+
+                vec![task]
+            }
+            // /retry
+            None => task_session.get_failed_tasks(chat_id).await?,
+        };
+
+        if tasks.is_empty() {
+            let response = "No failed task to retry.";
+            message.respond(response).await.context(response)?;
+
+            return Ok(());
+        }
+
+        let mut retried_num = 0;
+
+        for task in tasks {
+            match retry_failed_task(&task, &state).await {
+                Ok(()) => retried_num += 1,
+                Err(e) => {
+                    e.context(format!("failed to retry task {}", task.filename))
+                        .send(message.clone())
+                        .await
+                        .unwrap_both()
+                        .trace();
+                }
+            }
+        }
+
+        let response = format!("Retrying {} failed tasks.", retried_num);
+        message.respond(response.as_str()).await.context(response)?;
+    } else if cmd.len() == 2 && cmd[1] == "help" {
+        // /retry help
+        message
+            .respond(InputMessage::html(format_help(PATTERN)))
+            .await
+            .context("help")?;
+    } else {
+        return Err(anyhow!(format_unknown_command_help(PATTERN)));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,99 @@
+/*
+:project: telegram-onedrive
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use super::{
+    docs::{format_help, format_unknown_command_help},
+    utils::{resolve_remote_path, text::cmd_parser},
+};
+use crate::{message::TelegramMessage, state::AppState};
+use anyhow::{anyhow, Context, Result};
+use grammers_client::{button, reply_markup, InputMessage};
+use proc_macros::{check_in_group, check_od_login, check_senders};
+
+pub const PATTERN: &str = "/rm";
+// first word of the data of the buttons
+pub const CALLBACK_PATTERN: &str = "rm";
+
+// the path is kept in the first line of the confirmation
+const PATH_PREFIX: &str = "Delete: ";
+
+#[check_od_login]
+#[check_senders]
+#[check_in_group]
+pub async fn handler(message: TelegramMessage, state: AppState) -> Result<()> {
+    let onedrive = &state.onedrive;
+
+    let cmd = cmd_parser(message.text());
+
+    if cmd.len() == 2 && cmd[1] == "help" {
+        // /rm help
+        message
+            .respond(InputMessage::html(format_help(PATTERN)))
+            .await
+            .context("help")?;
+    } else if cmd.len() == 2 {
+        // /rm $path
+        let path = resolve_remote_path(onedrive, &cmd[1]).await?;
+
+        if path == "/" {
+            return Err(anyhow!("root directory can't be deleted"));
+        }
+
+        let response = format!(
+            "{}{}\n\nIt will be moved to the OneDrive recycle bin.",
+            PATH_PREFIX, path
+        );
+
+        let buttons = reply_markup::inline(vec![vec![
+            button::inline("Delete", format!("{} confirm", CALLBACK_PATTERN)),
+            button::inline("Cancel", format!("{} cancel", CALLBACK_PATTERN)),
+        ]]);
+
+        message
+            .respond(InputMessage::text(&response).reply_markup(&buttons))
+            .await
+            .context(response)?;
+    } else {
+        return Err(anyhow!("command error")).context(format_unknown_command_help(PATTERN));
+    }
+
+    Ok(())
+}
+
+// the message is the confirmation, its text is the data of the pressed button
+#[check_od_login]
+#[check_senders]
+#[check_in_group]
+pub async fn callback_handler(message: TelegramMessage, state: AppState) -> Result<()> {
+    let cmd = cmd_parser(message.text());
+
+    let path = message
+        .raw
+        .text()
+        .lines()
+        .next()
+        .and_then(|line| line.strip_prefix(PATH_PREFIX))
+        .map(|path| path.to_string())
+        .ok_or_else(|| anyhow!("message is not a confirmation of /rm"))?;
+
+    let response = match cmd.get(1).map(String::as_str) {
+        Some("confirm") => {
+            state.onedrive.delete_item_from_path(&path).await?;
+
+            format!("Deleted {}.", path)
+        }
+        Some("cancel") => format!("Canceled deleting {}.", path),
+        _ => return Err(anyhow!("unknown button: {}", message.text())),
+    };
+
+    message
+        .edit(message.id(), response.as_str())
+        .await
+        .context(response)?;
+
+    Ok(())
+}
@@ -0,0 +1,140 @@
+/*
+:project: telegram-onedrive
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use super::{
+    docs::{format_help, format_unknown_command_help},
+    utils::text::cmd_parser,
+};
+use crate::{
+    message::TelegramMessage,
+    settings::{RouteKind, FILE_TYPES},
+    state::AppState,
+};
+use anyhow::{anyhow, Context, Result};
+use grammers_client::InputMessage;
+use proc_macros::{check_in_group, check_od_login, check_senders};
+
+pub const PATTERN: &str = "/route";
+
+#[check_od_login]
+#[check_senders]
+#[check_in_group]
+pub async fn handler(message: TelegramMessage, state: AppState) -> Result<()> {
+    let cmd = cmd_parser(message.text());
+
+    if cmd.len() == 1 {
+        // /route
+        show_routes(message, &state).await?;
+    } else if cmd.len() == 2 && cmd[1] == "help" {
+        // /route help
+        message
+            .respond(InputMessage::html(format_help(PATTERN)))
+            .await
+            .context("help")?;
+    } else if cmd.len() == 3 && cmd[1] == "chat" {
+        // /route chat $index|reset
+        let chat_id = message.chat().id().to_string();
+
+        set_route(RouteKind::Chat, &chat_id, &cmd[2], message, &state).await?;
+    } else if cmd.len() == 4 && cmd[1] == "sender" {
+        // /route sender $user_id $index|reset
+        let sender_id = cmd[2]
+            .parse::<i64>()
+            .context("user id should be a number")
+            .context(format_unknown_command_help(PATTERN))?;
+
+        set_route(
+            RouteKind::Sender,
+            &sender_id.to_string(),
+            &cmd[3],
+            message,
+            &state,
+        )
+        .await?;
+    } else if cmd.len() == 4 && cmd[1] == "type" {
+        // /route type $type $index|reset
+        if !FILE_TYPES.contains(&cmd[2].as_str()) {
+            return Err(anyhow!(
+                "file type should be one of {}",
+                FILE_TYPES.join(", ")
+            ))
+            .context(format_unknown_command_help(PATTERN));
+        }
+
+        set_route(RouteKind::Type, &cmd[2], &cmd[3], message, &state).await?;
+    } else {
+        return Err(anyhow!("command error")).context(format_unknown_command_help(PATTERN));
+    }
+
+    Ok(())
+}
+
+async fn show_routes(message: TelegramMessage, state: &AppState) -> Result<()> {
+    let routes = state.settings.get_routes().await?;
+
+    if routes.is_empty() {
+        let response = "No route, new tasks are uploaded to the current account.";
+        message.respond(response).await.context(response)?;
+
+        return Ok(());
+    }
+
+    let usernames = state.onedrive.get_usernames().await?;
+
+    let mut response = "Routes:".to_string();
+
+    for (kind, value, account) in routes {
+        response.push_str(&format!("\n{} {}: {}", kind, value, account));
+
+        if !usernames.contains(&account) {
+            response.push_str(" (logged out)");
+        }
+    }
+
+    message.respond(response.as_str()).await.context(response)?;
+
+    Ok(())
+}
+
+// the account is chosen by its index in /drive
+async fn set_route(
+    kind: RouteKind,
+    value: &str,
+    index: &str,
+    message: TelegramMessage,
+    state: &AppState,
+) -> Result<()> {
+    let settings = &state.settings;
+
+    if index == "reset" {
+        settings.delete_route(kind, value).await?;
+
+        let response = format!("Route of {} {} removed.", kind, value);
+        message.respond(response.as_str()).await.context(response)?;
+
+        return Ok(());
+    }
+
+    let index = index
+        .parse::<usize>()
+        .context("account index should be a number")
+        .context(format_unknown_command_help(PATTERN))?;
+
+    let usernames = state.onedrive.get_usernames().await?;
+
+    let account = index
+        .checked_sub(1)
+        .and_then(|index| usernames.get(index))
+        .ok_or_else(|| anyhow!("account index out of range"))?;
+
+    settings.set_route(kind, value, account).await?;
+
+    let response = format!("Route of {} {} set to {}.", kind, value, account);
+    message.respond(response.as_str()).await.context(response)?;
+
+    Ok(())
+}
@@ -0,0 +1,78 @@
+/*
+:project: telegram-onedrive
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use super::{
+    docs::{format_help, format_unknown_command_help},
+    utils::text::cmd_parser,
+};
+use crate::{
+    message::TelegramMessage,
+    state::AppState,
+    tasker::{Schedule, Scheduler},
+};
+use anyhow::{Context, Result};
+use grammers_client::InputMessage;
+use proc_macros::{check_in_group, check_senders};
+
+pub const PATTERN: &str = "/schedule";
+
+#[check_senders]
+#[check_in_group]
+pub async fn handler(message: TelegramMessage, state: AppState) -> Result<()> {
+    let cmd = cmd_parser(message.text());
+
+    let scheduler = &state.scheduler;
+
+    if cmd.len() == 1 {
+        // /schedule
+        let schedule = scheduler.get_schedule().await;
+
+        let mut response = format!("Schedule: {}", schedule);
+
+        if let Some(next_open) = scheduler.next_open().await {
+            response.push_str(&format!(
+                "\nClosed now, waiting tasks start at {}.",
+                next_open.format("%a %H:%M")
+            ));
+        } else if scheduler.is_open().await {
+            response.push_str("\nOpen now.");
+        } else {
+            response.push_str("\nNever open, only tasks with --now start.");
+        }
+
+        message.respond(response.as_str()).await.context(response)?;
+    } else if cmd.len() == 2 && cmd[1] == "help" {
+        // /schedule help
+        message
+            .respond(InputMessage::html(format_help(PATTERN)))
+            .await
+            .context("help")?;
+    } else if cmd.len() == 2 && cmd[1] == "reset" {
+        // /schedule reset
+        let schedule = Scheduler::default_schedule();
+        scheduler.set_schedule(schedule.clone()).await;
+        state.settings.delete_schedule_rules().await?;
+
+        let response = format!("Schedule reset to {}.", schedule);
+        message.respond(response.as_str()).await.context(response)?;
+    } else {
+        // /schedule always
+        // /schedule $rules
+        let schedule = if cmd.len() == 2 && cmd[1] == "always" {
+            Schedule::default()
+        } else {
+            Schedule::parse(&cmd[1..].join(" ")).context(format_unknown_command_help(PATTERN))?
+        };
+        scheduler.set_schedule(schedule.clone()).await;
+        state.settings.set_schedule_rules(schedule.rules()).await?;
+
+        let response = format!("Schedule set to {}.", schedule);
+        message.respond(response.as_str()).await.context(response)?;
+    }
+
+    Ok(())
+}
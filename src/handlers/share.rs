@@ -0,0 +1,155 @@
+/*
+:project: telegram-onedrive
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use super::{
+    docs::{format_help, format_unknown_command_help},
+    utils::{resolve_remote_path, text::cmd_parser},
+};
+use crate::{
+    client::onedrive::manage::ShareLinkOptions, message::TelegramMessage, settings::ChatShareLink,
+    state::AppState,
+};
+use anyhow::{anyhow, Context, Result};
+use chrono::{Duration, Local, Utc};
+use grammers_client::InputMessage;
+use proc_macros::{check_in_group, check_od_login, check_senders};
+
+pub const PATTERN: &str = "/share";
+
+#[check_od_login]
+#[check_senders]
+#[check_in_group]
+pub async fn handler(message: TelegramMessage, state: AppState) -> Result<()> {
+    let cmd = cmd_parser(message.text());
+
+    let settings = &state.settings;
+    let chat_id = message.chat().id();
+
+    if cmd.len() == 2 && cmd[1] == "help" {
+        // /share help
+        message
+            .respond(InputMessage::html(format_help(PATTERN)))
+            .await
+            .context("help")?;
+    } else if cmd.len() == 2 && cmd[1] == "auto" {
+        // /share auto
+        let response = match settings.get_chat_share_link(chat_id).await? {
+            Some(chat_share_link) => format!(
+                "Share links of uploaded files: {}",
+                format_chat_share_link(&chat_share_link)
+            ),
+            None => "Share links of uploaded files: off".to_string(),
+        };
+        message.respond(response.as_str()).await.context(response)?;
+    } else if cmd.len() == 3 && cmd[1] == "auto" && cmd[2] == "off" {
+        // /share auto off
+        settings.delete_chat_share_link(chat_id).await?;
+
+        let response = "Share links of uploaded files turned off.";
+        message.respond(response).await.context(response)?;
+    } else if cmd.len() >= 3 && cmd[1] == "auto" && cmd[2] == "on" {
+        // /share auto on [anonymous|organization] [view|edit] [$expiration] [password:$password]
+        let mut chat_share_link = ChatShareLink {
+            chat_id,
+            link_type: "view".to_string(),
+            scope: "anonymous".to_string(),
+            expiration_hours: None,
+            password: None,
+        };
+
+        for arg in &cmd[3..] {
+            match arg.as_str() {
+                "view" | "edit" => chat_share_link.link_type.clone_from(arg),
+                "anonymous" | "organization" => chat_share_link.scope.clone_from(arg),
+                _ => match arg.strip_prefix("password:") {
+                    Some(password) => chat_share_link.password = Some(password.to_string()),
+                    None => chat_share_link.expiration_hours = Some(parse_expiration(arg)?),
+                },
+            }
+        }
+
+        let response = format!(
+            "Share links of uploaded files: {}",
+            format_chat_share_link(&chat_share_link)
+        );
+
+        settings.set_chat_share_link(chat_share_link).await?;
+
+        message.respond(response.as_str()).await.context(response)?;
+    } else if (2..=4).contains(&cmd.len()) {
+        // /share $path [view|edit] [$expiration]
+        let path = resolve_remote_path(&state.onedrive, &cmd[1]).await?;
+
+        let mut options = ShareLinkOptions {
+            link_type: "view".to_string(),
+            scope: "anonymous".to_string(),
+            expiration: None,
+            password: None,
+        };
+
+        for arg in &cmd[2..] {
+            match arg.as_str() {
+                "view" | "edit" => options.link_type.clone_from(arg),
+                _ => {
+                    options.expiration = Some(Utc::now() + Duration::hours(parse_expiration(arg)?))
+                }
+            }
+        }
+
+        let share_link = state.onedrive.create_share_link(&path, &options).await?;
+
+        let mut response = format!("Link to {} {}\n{}", options.link_type, path, share_link);
+        if let Some(expiration) = options.expiration {
+            response.push_str(&format!(
+                "\nExpires at {}",
+                expiration.with_timezone(&Local).format("%Y-%m-%d %H:%M")
+            ));
+        }
+
+        message.respond(response.as_str()).await.context(response)?;
+    } else {
+        return Err(anyhow!("command error")).context(format_unknown_command_help(PATTERN));
+    }
+
+    Ok(())
+}
+
+fn format_chat_share_link(chat_share_link: &ChatShareLink) -> String {
+    let mut response = format!("{} {}", chat_share_link.scope, chat_share_link.link_type);
+
+    if let Some(expiration_hours) = chat_share_link.expiration_hours {
+        response.push_str(&format!(", expires in {}h", expiration_hours));
+    }
+
+    if chat_share_link.password.is_some() {
+        response.push_str(", with password");
+    }
+
+    response
+}
+
+// like 12h or 7d, in hours
+fn parse_expiration(expiration: &str) -> Result<i64> {
+    let (number, unit) = expiration.split_at(expiration.len().saturating_sub(1));
+
+    let number = number
+        .parse::<i64>()
+        .ok()
+        .filter(|number| *number > 0)
+        .ok_or_else(|| anyhow!("expiration should be like 12h or 7d: {}", expiration))
+        .context(format_unknown_command_help(PATTERN))?;
+
+    match unit {
+        "h" => Ok(number),
+        "d" => Ok(number * 24),
+        _ => Err(anyhow!(
+            "expiration should be like 12h or 7d: {}",
+            expiration
+        ))
+        .context(format_unknown_command_help(PATTERN)),
+    }
+}
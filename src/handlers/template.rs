@@ -0,0 +1,122 @@
+/*
+:project: telegram-onedrive
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use super::{
+    docs::{format_help, format_unknown_command_help},
+    utils::{
+        template::{render_path, TemplateContext},
+        text::cmd_parser,
+        validate_root_path,
+    },
+};
+use crate::{message::TelegramMessage, state::AppState};
+use anyhow::{anyhow, Context, Result};
+use grammers_client::InputMessage;
+use proc_macros::{check_in_group, check_od_login, check_senders};
+
+pub const PATTERN: &str = "/template";
+
+#[check_od_login]
+#[check_senders]
+#[check_in_group]
+pub async fn handler(message: TelegramMessage, state: AppState) -> Result<()> {
+    let cmd = cmd_parser(message.text());
+
+    let settings = &state.settings;
+    let chat_id = message.chat().id();
+
+    if cmd.len() == 1 {
+        // /template
+        show_name_templates(message, &state).await?;
+    } else if cmd[1] == "help" && cmd.len() == 2 {
+        // /template help
+        message
+            .respond(InputMessage::html(format_help(PATTERN)))
+            .await
+            .context("help")?;
+    } else if cmd[1] == "reset" && cmd.len() == 2 {
+        // /template reset
+        settings.delete_chat_name_template(chat_id).await?;
+
+        let response = "Name template of this chat removed.";
+        message.respond(response).await.context(response)?;
+    } else if cmd[1] == "dir" && cmd.len() >= 3 {
+        // /template dir $template applies to the current directory
+        let (path, template) = if cmd.len() >= 4 && cmd[2].starts_with('/') {
+            validate_root_path(&cmd[2]).await?;
+
+            (cmd[2].clone(), cmd[3..].join(" "))
+        } else {
+            (
+                state.onedrive.get_root_path(false).await?,
+                cmd[2..].join(" "),
+            )
+        };
+
+        if template == "reset" {
+            // /template dir [$path] reset
+            settings.delete_dir_name_template(&path).await?;
+
+            let response = format!("Name template of {} removed.", path);
+            message.respond(response.as_str()).await.context(response)?;
+        } else {
+            // /template dir [$path] $template
+            validate_template(&template)?;
+
+            settings.set_dir_name_template(&path, &template).await?;
+
+            let response = format!("Name template of {} set to {}", path, template);
+            message.respond(response.as_str()).await.context(response)?;
+        }
+    } else if cmd[1] != "dir" {
+        // /template $template
+        let template = cmd[1..].join(" ");
+
+        validate_template(&template)?;
+
+        settings.set_chat_name_template(chat_id, &template).await?;
+
+        let response = format!("Name template of this chat set to {}", template);
+        message.respond(response.as_str()).await.context(response)?;
+    } else {
+        return Err(anyhow!("command error")).context(format_unknown_command_help(PATTERN));
+    }
+
+    Ok(())
+}
+
+async fn show_name_templates(message: TelegramMessage, state: &AppState) -> Result<()> {
+    let settings = &state.settings;
+
+    let chat_template = settings
+        .get_chat_name_template(message.chat().id())
+        .await?
+        .unwrap_or_else(|| "none".to_string());
+
+    let mut response = format!("Name template of this chat: {}", chat_template);
+
+    let dir_templates = settings.get_dir_name_templates().await?;
+
+    if !dir_templates.is_empty() {
+        response.push_str("\n\nName templates of directories:");
+
+        for (path, template) in dir_templates {
+            response.push_str(&format!("\n{}: {}", path, template));
+        }
+    }
+
+    message.respond(response.as_str()).await.context(response)?;
+
+    Ok(())
+}
+
+// rendered with a sample file so that mistakes are found before any upload
+fn validate_template(template: &str) -> Result<()> {
+    render_path(template, &TemplateContext::new("example.jpg"), "/")
+        .map(|_| ())
+        .context(format_unknown_command_help(PATTERN))
+}
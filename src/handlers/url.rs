@@ -11,11 +11,17 @@ use super::{
     docs::{format_help, format_unknown_command_help},
     utils::{
         get_filename,
-        text::{cmd_parser, TextExt},
+        text::{cmd_parser, flags_parser, TextExt},
     },
 };
 use crate::{
-    handlers::utils::message::format_message_link,
+    handlers::utils::{
+        dedup::{find_existing_item, report_skipped},
+        message::format_message_link,
+        route::get_account,
+        schedule::report_scheduled,
+        template::{apply_name_template, TemplateContext},
+    },
     message::{ChatEntity, TelegramMessage},
     state::AppState,
     tasker::{CmdType, InsertTask},
@@ -33,7 +39,8 @@ pub const PATTERN: &str = "/url";
 #[check_senders]
 #[check_in_group]
 pub async fn handler(message: TelegramMessage, state: AppState) -> Result<()> {
-    let cmd = cmd_parser(message.text());
+    let (cmd, flags) =
+        flags_parser(cmd_parser(message.text())).context(format_unknown_command_help(PATTERN))?;
 
     if cmd.len() == 2 {
         if cmd[1] == "help" {
@@ -45,7 +52,7 @@ pub async fn handler(message: TelegramMessage, state: AppState) -> Result<()> {
 
             Ok(())
         } else {
-            // /url $url
+            // /url $url [--rename|--replace|--skip]
             let telegram_user = &state.telegram_user;
             let onedrive = &state.onedrive;
             let task_session = &state.task_session;
@@ -87,17 +94,16 @@ pub async fn handler(message: TelegramMessage, state: AppState) -> Result<()> {
                     default_filename
                 };
 
+                // the file is uploaded as a stream if Content-Length is missing, e.g. chunked responses
                 let total_length = match response.headers().get(header::CONTENT_LENGTH) {
-                    Some(content_length) => content_length
-                        .to_str()
-                        .context("header Content-Length has invisible ASCII chars")?
-                        .parse::<u64>()
-                        .context("failed to parse header Content-Length to u64")?,
-                    None => return Err(anyhow!(
-                        "Content-Length not found in response headers.\nStatus code:\n{}\nResponse headers:\n{:#?}",
-                        response.status(),
-                        response.headers()
-                    )),
+                    Some(content_length) => Some(
+                        content_length
+                            .to_str()
+                            .context("header Content-Length has invisible ASCII chars")?
+                            .parse::<u64>()
+                            .context("failed to parse header Content-Length to u64")?,
+                    ),
+                    None => None,
                 };
 
                 let chat_user = telegram_user
@@ -112,13 +118,59 @@ pub async fn handler(message: TelegramMessage, state: AppState) -> Result<()> {
                 let message_indicator_id = message
                     .respond(InputMessage::html(&response))
                     .await
-                    .context(response)?
+                    .context(response.clone())?
                     .id();
 
-                let root_path = onedrive.get_root_path(true).await?;
+                let account = get_account(&message, &filename, total_length, &state).await?;
+
+                let root_path = onedrive.get_account_root_path(&account, true).await?;
+
+                let mut context = TemplateContext::from_message(&message, &filename);
+                // the text is the command, not a caption
+                context.caption = None;
+
+                let (root_path, filename) = apply_name_template(
+                    &state,
+                    message.chat().id(),
+                    &root_path,
+                    &account,
+                    &context,
+                )
+                .await?;
+
+                let conflict_policy = match flags.conflict_policy {
+                    Some(conflict_policy) => conflict_policy,
+                    None => {
+                        state
+                            .settings
+                            .get_conflict_policy(message.chat().id(), &root_path)
+                            .await?
+                    }
+                };
+
+                if let Some(drive_item) = find_existing_item(
+                    &state,
+                    None,
+                    &root_path,
+                    &filename,
+                    total_length,
+                    conflict_policy,
+                    &account,
+                )
+                .await?
+                {
+                    report_skipped(&message, message_indicator_id, &response, &drive_item).await?;
+
+                    return Ok(());
+                }
 
                 let (upload_session, upload_session_meta) = onedrive
-                    .multipart_upload_session_builder(&root_path, &filename)
+                    .multipart_upload_session_builder(
+                        &root_path,
+                        &filename,
+                        conflict_policy,
+                        Some(&account),
+                    )
                     .await?;
 
                 let current_length = upload_session_meta
@@ -151,10 +203,31 @@ pub async fn handler(message: TelegramMessage, state: AppState) -> Result<()> {
                         message_indicator_id,
                         message_origin_id: None,
                         auto_delete,
+                        media_key: None,
+                        conflict_policy,
+                        account: Some(account),
+                        ignore_schedule: flags.ignore_schedule,
+                        zip: false,
                     })
                     .await?;
 
-                tracing::info!("inserted url task: {} size: {}", filename, total_length);
+                report_scheduled(
+                    &message,
+                    message_indicator_id,
+                    &response,
+                    flags.ignore_schedule,
+                    &state,
+                )
+                .await?;
+
+                tracing::info!(
+                    "inserted url task: {} size: {}",
+                    filename,
+                    total_length.map_or_else(
+                        || "unknown".to_string(),
+                        |total_length| total_length.to_string()
+                    )
+                );
 
                 Ok(())
             } else {
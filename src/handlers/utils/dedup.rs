@@ -0,0 +1,170 @@
+/*
+:project: telegram-onedrive
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use crate::{
+    client::onedrive::item::get_quick_xor_hash, env::ENV, message::TelegramMessage,
+    settings::ConflictPolicy, state::AppState,
+};
+use anyhow::{Context, Result};
+use grammers_client::{types::Media, InputMessage};
+use onedrive_api::resource::DriveItem;
+
+// telegram keeps the id of a media when it's forwarded, so it identifies the media across chats
+pub fn get_tg_media_key(media: &Media) -> Option<String> {
+    match media {
+        Media::Photo(file) => Some(format!("photo:{}", file.id())),
+        Media::Document(file) => Some(format!("document:{}", file.id())),
+        Media::Sticker(file) => Some(format!("document:{}", file.document.id())),
+        _ => None,
+    }
+}
+
+// the existing item if the file shouldn't be uploaded,
+// either the policy is skip and the target path is taken, or the file is a duplicate
+pub async fn find_existing_item(
+    state: &AppState,
+    media_key: Option<&str>,
+    root_path: &str,
+    filename: &str,
+    total_length: Option<u64>,
+    conflict_policy: ConflictPolicy,
+    account: &str,
+) -> Result<Option<DriveItem>> {
+    if conflict_policy == ConflictPolicy::Skip {
+        if let Some(drive_item) = state
+            .onedrive
+            .get_item_from_path(root_path, filename, Some(account))
+            .await?
+        {
+            return Ok(Some(drive_item));
+        }
+    }
+
+    // files from url have no media key, only the skip policy skips them
+    if ENV.get().unwrap().dedup {
+        if let (Some(media_key), Some(total_length)) = (media_key, total_length) {
+            return find_duplicate(state, media_key, root_path, filename, total_length, account)
+                .await;
+        }
+    }
+
+    Ok(None)
+}
+
+// the existing item if the media has been uploaded before, or the target path holds the same file
+async fn find_duplicate(
+    state: &AppState,
+    media_key: &str,
+    root_path: &str,
+    filename: &str,
+    total_length: u64,
+    account: &str,
+) -> Result<Option<DriveItem>> {
+    let onedrive = &state.onedrive;
+    let task_session = &state.task_session;
+
+    let mut quick_xor_hash = None;
+
+    if let Some(uploaded_media) = task_session.get_uploaded_media(media_key).await? {
+        // the item is looked up in the drive it was uploaded to
+        let drive_item = onedrive
+            .get_item_from_id(
+                &uploaded_media.item_id,
+                Some(uploaded_media.account.as_deref().unwrap_or(account)),
+            )
+            .await?;
+
+        match drive_item {
+            Some(drive_item)
+                if is_same_file(
+                    &drive_item,
+                    uploaded_media.size as u64,
+                    uploaded_media.quick_xor_hash.as_deref(),
+                ) =>
+            {
+                tracing::debug!("found uploaded media {} in index", media_key);
+
+                return Ok(Some(drive_item));
+            }
+            // media indexed before accounts were recorded may be in another drive
+            _ if uploaded_media.account.is_none() => {
+                quick_xor_hash = uploaded_media.quick_xor_hash;
+            }
+            _ => {
+                // the item has been deleted or changed since it was uploaded
+                task_session.delete_uploaded_media(media_key).await?;
+
+                quick_xor_hash = uploaded_media.quick_xor_hash;
+            }
+        }
+    }
+
+    // the same name and size alone may be another file, and the media can't be hashed before downloading,
+    // so a file at the target path is only a duplicate if an earlier upload tells the hash
+    let Some(quick_xor_hash) = quick_xor_hash else {
+        return Ok(None);
+    };
+
+    if let Some(drive_item) = onedrive
+        .get_item_from_path(root_path, filename, Some(account))
+        .await?
+    {
+        if is_same_file(&drive_item, total_length, Some(&quick_xor_hash)) {
+            tracing::debug!("found media {} at {}/{}", media_key, root_path, filename);
+
+            task_session
+                .set_uploaded_media(media_key, &drive_item, Some(account))
+                .await?;
+
+            return Ok(Some(drive_item));
+        }
+    }
+
+    Ok(None)
+}
+
+// size must match, and the hash too if one is expected
+fn is_same_file(drive_item: &DriveItem, size: u64, quick_xor_hash: Option<&str>) -> bool {
+    if drive_item.file.is_none() || drive_item.size != Some(size as i64) {
+        return false;
+    }
+
+    quick_xor_hash.is_none_or(|quick_xor_hash| {
+        get_quick_xor_hash(drive_item).as_deref() == Some(quick_xor_hash)
+    })
+}
+
+// edit the indicator instead of inserting a task
+pub async fn report_skipped(
+    message: &TelegramMessage,
+    message_indicator_id: i32,
+    indicator_text: &str,
+    drive_item: &DriveItem,
+) -> Result<()> {
+    let name = drive_item.name.clone().unwrap_or_default();
+
+    let item = match &drive_item.web_url {
+        Some(web_url) => format!("<a href=\"{}\">{}</a>", web_url, name),
+        None => name,
+    };
+
+    let response = format!(
+        "{}\n\nSkipped.\nFile already exists: {}",
+        indicator_text, item
+    );
+    message
+        .edit(message_indicator_id, InputMessage::html(&response))
+        .await
+        .context(response)?;
+
+    tracing::info!(
+        "skipped existing file: {}",
+        drive_item.name.as_deref().unwrap_or_default()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,378 @@
+/*
+:project: telegram-onedrive
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use super::{get_tg_file_size, get_tg_media_type, preprocess_tg_file_name};
+use crate::{message::TelegramMessage, settings::ChatUploadFilter, state::AppState};
+use anyhow::{anyhow, Context, Result};
+use grammers_client::types::Media;
+use regex::Regex;
+use std::path::Path;
+
+const MEDIA_TYPES: [&str; 6] = ["photo", "video", "audio", "image", "document", "sticker"];
+
+// which media messages are transferred by /mirror and /watch
+#[derive(Default)]
+pub struct MediaFilter {
+    media_types: Option<Vec<String>>,
+    // in bytes
+    min_size: Option<u64>,
+    max_size: Option<u64>,
+    caption: Option<Regex>,
+}
+
+impl MediaFilter {
+    // type:$types min:$mb max:$mb caption:$regex
+    pub fn parse(args: &[String]) -> Result<Self> {
+        let mut filter = Self::default();
+
+        for arg in args {
+            let (key, value) = arg
+                .split_once(':')
+                .ok_or_else(|| anyhow!("filter should be like key:value"))?;
+
+            filter.set(key, value)?;
+        }
+
+        Ok(filter)
+    }
+
+    pub fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        match key {
+            "type" => {
+                let media_types = value
+                    .split(',')
+                    .map(|media_type| media_type.to_string())
+                    .collect::<Vec<String>>();
+
+                if let Some(media_type) = media_types
+                    .iter()
+                    .find(|media_type| !MEDIA_TYPES.contains(&media_type.as_str()))
+                {
+                    return Err(anyhow!("unknown media type: {}", media_type));
+                }
+
+                self.media_types = Some(media_types);
+            }
+            "min" => self.min_size = Some(parse_size(value)?),
+            "max" => self.max_size = Some(parse_size(value)?),
+            "caption" => {
+                self.caption = Some(Regex::new(value).context("invalid caption regex")?);
+            }
+            _ => return Err(anyhow!("unknown filter: {}", key)),
+        }
+
+        Ok(())
+    }
+
+    pub fn matches(&self, message: &TelegramMessage) -> bool {
+        let Some(media) = message.media() else {
+            return false;
+        };
+
+        let Some(media_type) = get_tg_media_type(&media) else {
+            return false;
+        };
+
+        let size = get_tg_file_size(&media);
+
+        self.media_types
+            .as_ref()
+            .is_none_or(|media_types| media_types.iter().any(|t| t == media_type))
+            && self.min_size.is_none_or(|min_size| size >= min_size)
+            && self.max_size.is_none_or(|max_size| size <= max_size)
+            && self
+                .caption
+                .as_ref()
+                .is_none_or(|caption| caption.is_match(message.raw.text()))
+    }
+}
+
+// reply with the rule if the upload filter of the chat of the message rejects the media
+pub async fn reject_filtered_media(
+    message: &TelegramMessage,
+    media: &Media,
+    state: &AppState,
+) -> Result<bool> {
+    let Some(upload_filter) = state
+        .settings
+        .get_chat_upload_filter(message.chat().id())
+        .await?
+    else {
+        return Ok(false);
+    };
+
+    let Some(rule) = check_upload_filter(&upload_filter, media) else {
+        return Ok(false);
+    };
+
+    let response = format!(
+        "{} is not uploaded: {}.",
+        preprocess_tg_file_name(media),
+        rule
+    );
+    message.reply(response.as_str()).await.context(response)?;
+
+    Ok(true)
+}
+
+// what the upload filter checks of a media
+struct FilteredFile {
+    mime_type: String,
+    // lowercase without dot, empty if there is none
+    ext: String,
+    size: u64,
+    is_sticker: bool,
+    is_voice: bool,
+}
+
+// the rule that rejects the media, none if the media is accepted
+pub fn check_upload_filter(upload_filter: &ChatUploadFilter, media: &Media) -> Option<String> {
+    let mime_type = match media {
+        Media::Photo(_) => "image/jpeg",
+        Media::Document(document) => document.mime_type().unwrap_or_default(),
+        Media::Sticker(sticker) => sticker.document.mime_type().unwrap_or_default(),
+        _ => return None,
+    }
+    .to_lowercase();
+
+    let filename = preprocess_tg_file_name(media);
+    let ext = Path::new(&filename)
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    let file = FilteredFile {
+        mime_type,
+        ext,
+        size: get_tg_file_size(media),
+        is_sticker: matches!(media, Media::Sticker(_)),
+        is_voice: matches!(media, Media::Document(document) if document.raw.voice),
+    };
+
+    check_file(upload_filter, &file)
+}
+
+fn check_file(upload_filter: &ChatUploadFilter, file: &FilteredFile) -> Option<String> {
+    let mime_type = file.mime_type.as_str();
+    let ext = file.ext.as_str();
+    let size = file.size;
+
+    if upload_filter.ignore_stickers && file.is_sticker {
+        return Some("stickers are ignored".to_string());
+    }
+
+    if upload_filter.ignore_voices && file.is_voice {
+        return Some("voice notes are ignored".to_string());
+    }
+
+    if split_list(&upload_filter.denied_types).any(|t| match_mime_type(t, mime_type)) {
+        return Some(format!("type {} is denied", mime_type));
+    }
+
+    if !upload_filter.allowed_types.is_empty()
+        && !split_list(&upload_filter.allowed_types).any(|t| match_mime_type(t, mime_type))
+    {
+        return Some(format!("type {} is not allowed", mime_type));
+    }
+
+    if split_list(&upload_filter.denied_exts).any(|e| e == ext) {
+        return Some(format!("extension {} is denied", ext));
+    }
+
+    if !upload_filter.allowed_exts.is_empty()
+        && !split_list(&upload_filter.allowed_exts).any(|e| e == ext)
+    {
+        return Some(format!("extension {} is not allowed", ext));
+    }
+
+    if let Some(min_size) = upload_filter.min_size {
+        if size < min_size as u64 {
+            return Some(format!("smaller than {}", format_size(min_size as u64)));
+        }
+    }
+
+    if let Some(max_size) = upload_filter.max_size {
+        if size > max_size as u64 {
+            return Some(format!("larger than {}", format_size(max_size as u64)));
+        }
+    }
+
+    None
+}
+
+pub fn split_list(list: &str) -> impl Iterator<Item = &str> {
+    list.split(',')
+        .map(|item| item.trim())
+        .filter(|item| !item.is_empty())
+}
+
+// video/* matches any video
+fn match_mime_type(pattern: &str, mime_type: &str) -> bool {
+    pattern
+        .strip_suffix("/*")
+        .map_or(pattern == mime_type, |prefix| {
+            mime_type.split('/').next() == Some(prefix)
+        })
+}
+
+pub fn format_size(size: u64) -> String {
+    format!("{:.2}MB", size as f64 / 1024.0 / 1024.0)
+}
+
+// in MB
+pub fn parse_size(value: &str) -> Result<u64> {
+    let size = value
+        .parse::<f64>()
+        .ok()
+        .filter(|size| *size >= 0.0)
+        .ok_or_else(|| anyhow!("size should be a number in MB"))?;
+
+    Ok((size * 1024.0 * 1024.0) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_file, parse_size, FilteredFile, MediaFilter};
+    use crate::settings::ChatUploadFilter;
+
+    fn build_file(mime_type: &str, ext: &str, size: u64) -> FilteredFile {
+        FilteredFile {
+            mime_type: mime_type.to_string(),
+            ext: ext.to_string(),
+            size,
+            is_sticker: false,
+            is_voice: false,
+        }
+    }
+
+    #[test]
+    fn test_check_file_types() {
+        let upload_filter = ChatUploadFilter {
+            allowed_types: "video/*, image/png".to_string(),
+            denied_types: "video/webm".to_string(),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            check_file(&upload_filter, &build_file("video/mp4", "mp4", 1)),
+            None
+        );
+        assert_eq!(
+            check_file(&upload_filter, &build_file("image/png", "png", 1)),
+            None
+        );
+        // denied goes before allowed
+        assert_eq!(
+            check_file(&upload_filter, &build_file("video/webm", "webm", 1)),
+            Some("type video/webm is denied".to_string())
+        );
+        assert_eq!(
+            check_file(&upload_filter, &build_file("image/jpeg", "jpg", 1)),
+            Some("type image/jpeg is not allowed".to_string())
+        );
+        // a wildcard only matches the whole top level type
+        assert_eq!(
+            check_file(&upload_filter, &build_file("videos/mp4", "mp4", 1)),
+            Some("type videos/mp4 is not allowed".to_string())
+        );
+    }
+
+    #[test]
+    fn test_check_file_exts() {
+        let upload_filter = ChatUploadFilter {
+            allowed_exts: "mp4,mkv".to_string(),
+            denied_exts: "mkv".to_string(),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            check_file(&upload_filter, &build_file("video/mp4", "mp4", 1)),
+            None
+        );
+        assert_eq!(
+            check_file(&upload_filter, &build_file("video/x-matroska", "mkv", 1)),
+            Some("extension mkv is denied".to_string())
+        );
+        assert_eq!(
+            check_file(&upload_filter, &build_file("application/zip", "", 1)),
+            Some("extension  is not allowed".to_string())
+        );
+    }
+
+    #[test]
+    fn test_check_file_size() {
+        let upload_filter = ChatUploadFilter {
+            min_size: Some(1024),
+            max_size: Some(2048),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            check_file(&upload_filter, &build_file("video/mp4", "mp4", 1024)),
+            None
+        );
+        assert_eq!(
+            check_file(&upload_filter, &build_file("video/mp4", "mp4", 2048)),
+            None
+        );
+        assert!(check_file(&upload_filter, &build_file("video/mp4", "mp4", 1023)).is_some());
+        assert!(check_file(&upload_filter, &build_file("video/mp4", "mp4", 2049)).is_some());
+    }
+
+    #[test]
+    fn test_check_file_stickers_and_voices() {
+        let upload_filter = ChatUploadFilter {
+            ignore_stickers: true,
+            ignore_voices: true,
+            ..Default::default()
+        };
+
+        let sticker = FilteredFile {
+            is_sticker: true,
+            ..build_file("image/webp", "webp", 1)
+        };
+        let voice = FilteredFile {
+            is_voice: true,
+            ..build_file("audio/ogg", "ogg", 1)
+        };
+
+        assert_eq!(
+            check_file(&upload_filter, &sticker),
+            Some("stickers are ignored".to_string())
+        );
+        assert_eq!(
+            check_file(&upload_filter, &voice),
+            Some("voice notes are ignored".to_string())
+        );
+        assert_eq!(check_file(&ChatUploadFilter::default(), &sticker), None);
+    }
+
+    #[test]
+    fn test_parse_filter() {
+        assert_eq!(parse_size("1.5").unwrap(), 1024 * 1024 * 3 / 2);
+        assert!(parse_size("-1").is_err());
+        assert!(parse_size("ten").is_err());
+
+        let filter = MediaFilter::parse(&[
+            "type:video,photo".to_string(),
+            "min:1".to_string(),
+            "caption:^#\\w+".to_string(),
+        ])
+        .unwrap();
+        assert_eq!(
+            filter.media_types,
+            Some(vec!["video".to_string(), "photo".to_string()])
+        );
+        assert_eq!(filter.min_size, Some(1024 * 1024));
+        assert!(filter.caption.is_some());
+
+        assert!(MediaFilter::parse(&["type:gif".to_string()]).is_err());
+        assert!(MediaFilter::parse(&["caption:(".to_string()]).is_err());
+        assert!(MediaFilter::parse(&["size:1".to_string()]).is_err());
+        assert!(MediaFilter::parse(&["min".to_string()]).is_err());
+    }
+}
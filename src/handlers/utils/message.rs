@@ -56,6 +56,32 @@ pub fn get_message_info(link: &str) -> Result<MessageInfo> {
     Ok(MessageInfo::new(chat_entity, message_id))
 }
 
+// a chat link like https://t.me/name or https://t.me/c/id, a message link also points to its chat
+pub fn get_chat_entity(link: &str) -> Result<ChatEntity> {
+    if let Some(chat_info) = link.strip_prefix("https://t.me/c/") {
+        // link from private group
+        let chat_id = chat_info
+            .split('/')
+            .next()
+            .unwrap_or_default()
+            .parse::<i64>()
+            .context("failed to parse chat id")?;
+
+        Ok(ChatEntity::from(chat_id))
+    } else if let Some(chat_info) = link.strip_prefix("https://t.me/") {
+        // link from public group
+        let chat_name = chat_info.split('/').next().unwrap_or_default();
+
+        if chat_name.is_empty() {
+            return Err(anyhow!("chat name not found in link"));
+        }
+
+        Ok(ChatEntity::from(chat_name.to_string()))
+    } else {
+        Err(anyhow!("not a chat link"))
+    }
+}
+
 pub async fn get_message_from_link(
     telegram_user: &TelegramClient,
     link: &str,
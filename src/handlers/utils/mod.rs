@@ -5,27 +5,38 @@
 :license: MIT, see LICENSE for more details.
 */
 
+pub mod dedup;
+pub mod filter;
 pub mod message;
+pub mod route;
+pub mod schedule;
+pub mod template;
 pub mod text;
 pub mod upload;
 pub mod zip;
 
 use crate::{
-    client::onedrive::invalid_name::{INVALID_COMPONENT, INVALID_NAME, INVALID_NAME_PREFIX},
+    client::{
+        onedrive::invalid_name::{INVALID_COMPONENT, INVALID_NAME, INVALID_NAME_PREFIX},
+        OneDriveClient,
+    },
     error::ResultExt,
     utils::{get_current_timestamp, get_ext},
 };
 use anyhow::{anyhow, Context, Result};
 use grammers_client::types::media::{Document, Media};
 use mime_guess::get_mime_extensions_str;
+use path_slash::PathBufExt;
 use percent_encoding::percent_decode_str;
 use regex::Regex;
 use reqwest::{header, Response, StatusCode};
-use std::collections::HashMap;
+use std::{collections::HashMap, path::Path};
 use url::Url;
 
 // according to https://support.microsoft.com/en-us/office/restrictions-and-limitations-in-onedrive-and-sharepoint-64883a5d-228e-48f5-b3d2-eb39e07630fa#filenamepathlengths
 const MAX_FILE_NAME_LEN: usize = 400;
+// names from captions are cut to this many chars
+const MAX_DIR_NAME_CHARS: usize = 64;
 
 pub fn get_filename(url: &str, response: &Response, od_root_path: &str) -> Result<String> {
     if response.status() != StatusCode::OK {
@@ -210,6 +221,24 @@ fn validate_filename(filename: &str) -> bool {
     true
 }
 
+// invalid chars are replaced, none if nothing valid is left
+pub fn preprocess_dir_name(name: &str) -> Option<String> {
+    let dir_name = sanitize_name(&name.chars().take(MAX_DIR_NAME_CHARS).collect::<String>());
+
+    validate_filename(&dir_name).then_some(dir_name)
+}
+
+// replace chars that onedrive doesn't allow in a name
+fn sanitize_name(name: &str) -> String {
+    let mut name = name.trim_start_matches(INVALID_NAME_PREFIX).to_string();
+
+    for component in INVALID_COMPONENT {
+        name = name.replace(component, "_");
+    }
+
+    name.trim().trim_end_matches('.').to_string()
+}
+
 pub async fn validate_root_path(root_path: &str) -> Result<()> {
     if !root_path.starts_with('/') {
         return Err(anyhow!("directory path should start with /"));
@@ -218,6 +247,26 @@ pub async fn validate_root_path(root_path: &str) -> Result<()> {
     Ok(())
 }
 
+// relative paths are in current directory
+pub async fn resolve_remote_path(onedrive: &OneDriveClient, path: &str) -> Result<String> {
+    let path = if path.starts_with('/') {
+        path.trim_end_matches('/').to_string()
+    } else {
+        let root_path = onedrive.get_root_path(false).await?;
+
+        Path::new(&root_path)
+            .join(path.trim_end_matches('/'))
+            .to_slash_lossy()
+            .to_string()
+    };
+
+    if path.is_empty() {
+        Ok("/".to_string())
+    } else {
+        Ok(path)
+    }
+}
+
 fn preprocess_url_file_name(filename: &str) -> String {
     if validate_filename(filename) {
         filename
@@ -278,6 +327,23 @@ fn get_tg_document_name_and_id(document: &Document) -> (String, i64) {
     (filename, file_id)
 }
 
+// photo, sticker, video, audio, image or document, none if the media isn't a file
+pub fn get_tg_media_type(media: &Media) -> Option<&'static str> {
+    match media {
+        Media::Photo(_) => Some("photo"),
+        Media::Sticker(_) => Some("sticker"),
+        Media::Document(document) => {
+            match document.mime_type().and_then(|mime| mime.split('/').next()) {
+                Some("video") => Some("video"),
+                Some("audio") => Some("audio"),
+                Some("image") => Some("image"),
+                _ => Some("document"),
+            }
+        }
+        _ => None,
+    }
+}
+
 pub fn get_tg_file_size(media: &Media) -> u64 {
     let size = match media {
         Media::Photo(file) => file.size(),
@@ -0,0 +1,56 @@
+/*
+:project: telegram-onedrive
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use crate::{message::TelegramMessage, state::AppState};
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+
+// the account a new task is uploaded to, routes to logged out accounts are skipped,
+// and the next account with room is used if the file doesn't fit
+pub async fn get_account(
+    message: &TelegramMessage,
+    filename: &str,
+    total_length: Option<u64>,
+    state: &AppState,
+) -> Result<String> {
+    let onedrive = &state.onedrive;
+
+    let usernames = onedrive.get_usernames().await?;
+    let sender_id = message.sender().map(|sender| sender.id());
+
+    let routed_account = state
+        .settings
+        .get_routed_accounts(message.chat().id(), sender_id, filename)
+        .await?
+        .into_iter()
+        .find(|account| usernames.contains(account));
+
+    let current_username = onedrive.get_current_username().await?;
+
+    let account = match routed_account {
+        Some(account) => account,
+        None => current_username
+            .clone()
+            .ok_or_else(|| anyhow!("no onedrive account is logged in"))?,
+    };
+
+    let Some(total_length) = total_length else {
+        return Ok(account);
+    };
+
+    // tasks without an account go to the current one
+    let mut queued_lengths = HashMap::new();
+    for (queued_account, queued_length) in state.task_session.get_queued_lengths().await? {
+        if let Some(queued_account) = queued_account.or_else(|| current_username.clone()) {
+            *queued_lengths.entry(queued_account).or_default() += queued_length;
+        }
+    }
+
+    onedrive
+        .find_account_with_room(&account, total_length, &queued_lengths)
+        .await
+}
@@ -0,0 +1,42 @@
+/*
+:project: telegram-onedrive
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use crate::{message::TelegramMessage, state::AppState};
+use anyhow::{Context, Result};
+use chrono::Local;
+use grammers_client::InputMessage;
+
+// note on the indicator when the task has to wait for the next schedule window
+pub async fn report_scheduled(
+    message: &TelegramMessage,
+    message_indicator_id: i32,
+    indicator_text: &str,
+    ignore_schedule: bool,
+    state: &AppState,
+) -> Result<()> {
+    if ignore_schedule {
+        return Ok(());
+    }
+
+    let Some(next_open) = state.scheduler.next_open().await else {
+        return Ok(());
+    };
+
+    let time = if next_open.date_naive() == Local::now().date_naive() {
+        next_open.format("%H:%M")
+    } else {
+        next_open.format("%a %H:%M")
+    };
+
+    let response = format!("{}\n\nScheduled for {}.", indicator_text, time);
+    message
+        .edit(message_indicator_id, InputMessage::html(&response))
+        .await
+        .context(response)?;
+
+    Ok(())
+}
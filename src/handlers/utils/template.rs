@@ -0,0 +1,373 @@
+/*
+:project: telegram-onedrive
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use super::{get_tg_media_type, sanitize_name, validate_filename, MAX_FILE_NAME_LEN};
+use crate::{message::TelegramMessage, state::AppState};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Local};
+use grammers_client::types::Media;
+use path_slash::PathBufExt;
+use std::{fmt::Write, path::Path};
+
+const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d";
+const MAX_SLUG_CHARS: usize = 64;
+// for metadata the source doesn't have
+const UNKNOWN: &str = "unknown";
+
+// metadata that placeholders are filled with
+pub struct TemplateContext {
+    pub filename: String,
+    pub caption: Option<String>,
+    pub sender: Option<String>,
+    pub chat_title: Option<String>,
+    pub date: DateTime<Local>,
+    pub message_id: Option<i32>,
+    pub media_type: Option<String>,
+    // in seconds
+    pub duration: Option<u64>,
+    // width and height
+    pub resolution: Option<(i32, i32)>,
+}
+
+impl TemplateContext {
+    pub fn new(filename: &str) -> Self {
+        Self {
+            filename: filename.to_string(),
+            caption: None,
+            sender: None,
+            chat_title: None,
+            date: Local::now(),
+            message_id: None,
+            media_type: None,
+            duration: None,
+            resolution: None,
+        }
+    }
+
+    pub fn from_message(message: &TelegramMessage, filename: &str) -> Self {
+        let caption = message.raw.text().trim();
+
+        let mut context = Self {
+            caption: (!caption.is_empty()).then(|| caption.to_string()),
+            sender: message.sender().map(|sender| sender.name().to_string()),
+            chat_title: Some(message.chat().name().to_string()),
+            date: message.raw.date().with_timezone(&Local),
+            message_id: Some(message.id()),
+            ..Self::new(filename)
+        };
+
+        if let Some(media) = message.media() {
+            context.set_media(&media);
+        }
+
+        context
+    }
+
+    fn set_media(&mut self, media: &Media) {
+        self.media_type = get_tg_media_type(media).map(|media_type| media_type.to_string());
+
+        if let Media::Document(document) = media {
+            self.duration = document.duration().map(|duration| duration as u64);
+            self.resolution = document.resolution();
+        }
+    }
+}
+
+// the directory and the file name given by the template of the directory or the chat,
+// missing directories are created, unchanged if there is no template
+pub async fn apply_name_template(
+    state: &AppState,
+    chat_id: i64,
+    root_path: &str,
+    account: &str,
+    context: &TemplateContext,
+) -> Result<(String, String)> {
+    let Some(template) = state.settings.get_name_template(chat_id, root_path).await? else {
+        return Ok((root_path.to_string(), context.filename.clone()));
+    };
+
+    let (template_root_path, filename) = render_path(&template, context, root_path)?;
+
+    if template_root_path != root_path {
+        state
+            .onedrive
+            .create_dir_all(&template_root_path, Some(account))
+            .await?;
+    }
+
+    tracing::debug!(
+        "rendered template {} to {}/{}",
+        template,
+        template_root_path,
+        filename
+    );
+
+    Ok((template_root_path, filename))
+}
+
+// / in the rendered template separates directories under root path, the last component is the file name
+pub fn render_path(
+    template: &str,
+    context: &TemplateContext,
+    root_path: &str,
+) -> Result<(String, String)> {
+    let rendered = render_template(template, context)?;
+
+    let mut components = rendered
+        .split('/')
+        .map(sanitize_component)
+        .filter(|component| !component.is_empty())
+        .collect::<Vec<String>>();
+
+    let filename = components
+        .pop()
+        .ok_or_else(|| anyhow!("template {} renders an empty name", template))?;
+
+    let root_path = components
+        .iter()
+        .fold(Path::new(root_path).to_path_buf(), |path, component| {
+            path.join(component)
+        })
+        .to_slash_lossy()
+        .to_string();
+
+    let filename = fit_filename(&filename, &root_path)?;
+
+    Ok((root_path, filename))
+}
+
+fn render_template(template: &str, context: &TemplateContext) -> Result<String> {
+    let mut rendered = String::new();
+    let mut chars = template.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '{' {
+            rendered.push(c);
+
+            continue;
+        }
+
+        let mut placeholder = String::new();
+
+        loop {
+            match chars.next() {
+                Some('}') => break,
+                Some(c) => placeholder.push(c),
+                None => return Err(anyhow!("unclosed placeholder in template: {}", template)),
+            }
+        }
+
+        rendered.push_str(&render_placeholder(&placeholder, context)?);
+    }
+
+    Ok(rendered)
+}
+
+fn render_placeholder(placeholder: &str, context: &TemplateContext) -> Result<String> {
+    let (name, format) = match placeholder.split_once(':') {
+        Some((name, format)) => (name, Some(format)),
+        None => (placeholder, None),
+    };
+
+    let path = Path::new(&context.filename);
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let value = match (name, format) {
+        // the date may contain / to create directories
+        ("date", format) => {
+            return format_date(&context.date, format.unwrap_or(DEFAULT_DATE_FORMAT));
+        }
+        (_, Some(_)) => {
+            return Err(anyhow!(
+                "only date takes a format in template: {{{}}}",
+                placeholder
+            ))
+        }
+        ("filename", None) => context.filename.clone(),
+        ("name", None) => stem,
+        ("ext", None) => path
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_string())
+            .unwrap_or_default(),
+        // the file name stands in for a missing caption
+        ("caption", None) => context
+            .caption
+            .as_deref()
+            .and_then(|caption| caption.lines().next())
+            .map_or(stem, |line| line.to_string()),
+        ("caption_slug", None) => context
+            .caption
+            .as_deref()
+            .map(slugify)
+            .filter(|slug| !slug.is_empty())
+            .unwrap_or(stem),
+        ("sender", None) => format_unknown(context.sender.clone()),
+        ("chat_title", None) => format_unknown(context.chat_title.clone()),
+        ("message_id", None) => format_unknown(context.message_id.map(|id| id.to_string())),
+        ("media_type", None) => format_unknown(context.media_type.clone()),
+        ("duration", None) => format_unknown(context.duration.map(|duration| duration.to_string())),
+        ("resolution", None) => format_unknown(
+            context
+                .resolution
+                .map(|(width, height)| format!("{}x{}", width, height)),
+        ),
+        _ => {
+            return Err(anyhow!(
+                "unknown placeholder in template: {{{}}}",
+                placeholder
+            ))
+        }
+    };
+
+    // metadata can't create directories
+    Ok(value.replace(['/', '\\'], "_"))
+}
+
+fn format_date(date: &DateTime<Local>, format: &str) -> Result<String> {
+    let mut formatted = String::new();
+
+    write!(formatted, "{}", date.format(format))
+        .map_err(|_| anyhow!("invalid date format in template: {}", format))?;
+
+    Ok(formatted)
+}
+
+fn format_unknown(value: Option<String>) -> String {
+    value.unwrap_or_else(|| UNKNOWN.to_string())
+}
+
+fn slugify(text: &str) -> String {
+    let slug = text
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<&str>>()
+        .join("-");
+
+    slug.chars()
+        .take(MAX_SLUG_CHARS)
+        .collect::<String>()
+        .trim_end_matches('-')
+        .to_string()
+}
+
+fn sanitize_component(component: &str) -> String {
+    let component = sanitize_name(component);
+
+    // reserved names like CON
+    if !component.is_empty() && !validate_filename(&component) {
+        format!("_{}", component)
+    } else {
+        component
+    }
+}
+
+// the file stem is cut so that the whole path fits the length limit
+fn fit_filename(filename: &str, root_path: &str) -> Result<String> {
+    let max_len = MAX_FILE_NAME_LEN.saturating_sub(root_path.len() + 1);
+
+    if filename.len() <= max_len {
+        return Ok(filename.to_string());
+    }
+
+    let path = Path::new(filename);
+
+    let ext = path
+        .extension()
+        .map(|ext| format!(".{}", ext.to_string_lossy()))
+        .unwrap_or_default();
+
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let stem_len = max_len.saturating_sub(ext.len());
+
+    if stem_len == 0 {
+        return Err(anyhow!("directory path is too long: {}", root_path));
+    }
+
+    let stem = stem
+        .char_indices()
+        .take_while(|(index, c)| index + c.len_utf8() <= stem_len)
+        .map(|(_, c)| c)
+        .collect::<String>();
+
+    Ok(format!("{}{}", stem, ext))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{render_path, TemplateContext};
+    use chrono::{Local, TimeZone};
+
+    fn build_context() -> TemplateContext {
+        TemplateContext {
+            caption: Some("Summer Trip: Day 1!\nsecond line".to_string()),
+            sender: Some("Alice".to_string()),
+            chat_title: Some("Photos/Videos".to_string()),
+            date: Local.with_ymd_and_hms(2024, 7, 5, 12, 30, 0).unwrap(),
+            message_id: Some(42),
+            media_type: Some("video".to_string()),
+            duration: Some(90),
+            resolution: Some((1920, 1080)),
+            ..TemplateContext::new("clip.mp4")
+        }
+    }
+
+    #[test]
+    fn test_render_path() {
+        let context = build_context();
+
+        let (root_path, filename) = render_path(
+            "{chat_title}/{date:%Y/%m}/{sender}_{filename}",
+            &context,
+            "/Telegram",
+        )
+        .unwrap();
+        assert_eq!(root_path, "/Telegram/Photos_Videos/2024/07");
+        assert_eq!(filename, "Alice_clip.mp4");
+
+        let (root_path, filename) =
+            render_path("{caption_slug}.{ext}", &context, "/Telegram").unwrap();
+        assert_eq!(root_path, "/Telegram");
+        assert_eq!(filename, "summer-trip-day-1-second-line.mp4");
+
+        let (_, filename) = render_path(
+            "{caption} {media_type} {resolution} {duration}s #{message_id}",
+            &context,
+            "/",
+        )
+        .unwrap();
+        assert_eq!(filename, "Summer Trip_ Day 1! video 1920x1080 90s _42");
+
+        // missing metadata
+        let context = TemplateContext::new("photo.jpg");
+        let (_, filename) = render_path("{caption_slug}_{sender}.{ext}", &context, "/").unwrap();
+        assert_eq!(filename, "photo_unknown.jpg");
+
+        // reserved names and the length limit
+        let (root_path, filename) = render_path("CON/{name}", &context, "/").unwrap();
+        assert_eq!(root_path, "/_CON");
+        assert_eq!(filename, "photo");
+
+        let long_root_path = format!("/{}", "a".repeat(389));
+        let (_, filename) = render_path("{name}-{name}.{ext}", &context, &long_root_path).unwrap();
+        assert_eq!(filename, "photo.jpg");
+
+        let too_long_root_path = format!("/{}", "a".repeat(395));
+        assert!(render_path("{name}.{ext}", &context, &too_long_root_path).is_err());
+
+        assert!(render_path("{unknown}", &context, "/").is_err());
+        assert!(render_path("{name", &context, "/").is_err());
+        assert!(render_path("{sender:%Y}", &context, "/").is_err());
+    }
+}
@@ -5,8 +5,8 @@
 :license: MIT, see LICENSE for more details.
 */
 
-use crate::error::ResultExt;
-use anyhow::Context;
+use crate::{error::ResultExt, settings::ConflictPolicy};
+use anyhow::{anyhow, Context, Result};
 use regex::Regex;
 use std::fmt::Display;
 use url::Url;
@@ -22,6 +22,58 @@ where
         .collect()
 }
 
+// flags like --replace that can be put anywhere after the command
+#[derive(Default)]
+pub struct Flags {
+    pub conflict_policy: Option<ConflictPolicy>,
+    // --now, start the task even outside the schedule windows
+    pub ignore_schedule: bool,
+    // --zip, send the file as a split zip for /get
+    pub zip: bool,
+}
+
+impl Flags {
+    pub fn to_args(&self) -> Vec<String> {
+        let mut args = self
+            .conflict_policy
+            .map(|conflict_policy| format!("--{}", conflict_policy))
+            .into_iter()
+            .collect::<Vec<_>>();
+
+        if self.ignore_schedule {
+            args.push("--now".to_string());
+        }
+
+        if self.zip {
+            args.push("--zip".to_string());
+        }
+
+        args
+    }
+}
+
+// split the flags from the parsed command
+pub fn flags_parser(cmd: Vec<String>) -> Result<(Vec<String>, Flags)> {
+    let (flags, cmd): (Vec<String>, Vec<String>) =
+        cmd.into_iter().partition(|arg| arg.starts_with("--"));
+
+    let mut parsed_flags = Flags::default();
+
+    for flag in flags {
+        if let Some(conflict_policy) = ConflictPolicy::from_flag(&flag) {
+            parsed_flags.conflict_policy = Some(conflict_policy);
+        } else if flag == "--now" {
+            parsed_flags.ignore_schedule = true;
+        } else if flag == "--zip" {
+            parsed_flags.zip = true;
+        } else {
+            return Err(anyhow!("unknown flag {}", flag));
+        }
+    }
+
+    Ok((cmd, parsed_flags))
+}
+
 pub trait TextExt {
     fn purify(&self) -> String;
     fn url_encode(&self) -> String;
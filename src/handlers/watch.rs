@@ -0,0 +1,238 @@
+/*
+:project: telegram-onedrive
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use super::{
+    docs::{format_help, format_unknown_command_help},
+    link::insert_link_task,
+    utils::{
+        filter::MediaFilter,
+        message::{get_chat_entity, get_message_link},
+        text::{cmd_parser, Flags},
+        validate_root_path,
+    },
+};
+use crate::{
+    client::utils::chat_from_hex,
+    error::{ErrorExt, ResultExt},
+    message::{ChatEntity, TelegramMessage},
+    settings::Watch,
+    state::AppState,
+};
+use anyhow::{anyhow, Context, Result};
+use grammers_client::{types::Chat, InputMessage};
+use proc_macros::{check_in_group, check_od_login, check_senders, check_tg_login};
+
+pub const PATTERN: &str = "/watch";
+
+#[check_od_login]
+#[check_tg_login]
+#[check_senders]
+#[check_in_group]
+pub async fn handler(message: TelegramMessage, state: AppState) -> Result<()> {
+    let cmd = cmd_parser(message.text());
+
+    let settings = &state.settings;
+    let group_id = message.chat().id();
+
+    if cmd.len() == 1 {
+        // /watch
+        show_watches(message, &state).await?;
+    } else if cmd.len() == 2 && cmd[1] == "help" {
+        // /watch help
+        message
+            .respond(InputMessage::html(format_help(PATTERN)))
+            .await
+            .context("help")?;
+    } else if cmd.len() == 3 && cmd[1] == "remove" {
+        // /watch remove $chat_link
+        let chat = get_watched_chat(&cmd[2], &state).await?;
+
+        settings.delete_watch(chat.id(), group_id).await?;
+        reload_watched_chat_ids(&state).await?;
+
+        let response = format!("Stopped watching {}.", chat.name());
+        message.respond(response.as_str()).await.context(response)?;
+    } else {
+        // /watch $chat_link [$filters] [$dir]
+        let chat = get_watched_chat(&cmd[1], &state).await?;
+
+        if chat.id() == group_id {
+            return Err(anyhow!("this chat can't watch itself"));
+        }
+
+        let (dirs, filters) = cmd[2..]
+            .iter()
+            .cloned()
+            .partition::<Vec<String>, _>(|arg| arg.starts_with('/'));
+
+        let dir = match dirs.as_slice() {
+            [] => None,
+            [dir] => {
+                validate_root_path(dir).await?;
+
+                Some(dir.clone())
+            }
+            _ => {
+                return Err(anyhow!("too many directories"))
+                    .context(format_unknown_command_help(PATTERN))
+            }
+        };
+
+        MediaFilter::parse(&filters).context(format_unknown_command_help(PATTERN))?;
+
+        settings
+            .set_watch(Watch {
+                chat_id: chat.id(),
+                group_id,
+                chat_name: chat.name().to_string(),
+                group_bot_hex: message.chat().pack().to_hex(),
+                filters: filters.join(" "),
+                dir: dir.clone(),
+            })
+            .await?;
+        reload_watched_chat_ids(&state).await?;
+
+        let response = format!(
+            "Watching {}, new files are transferred to {}.",
+            chat.name(),
+            dir.as_deref().unwrap_or("the current directory")
+        );
+        message.respond(response.as_str()).await.context(response)?;
+    }
+
+    Ok(())
+}
+
+async fn get_watched_chat(link: &str, state: &AppState) -> Result<Chat> {
+    let chat_entity = get_chat_entity(link).context(format_unknown_command_help(PATTERN))?;
+
+    state.telegram_user.get_chat(&chat_entity).await
+}
+
+// checked by the listener before a new message is handled
+async fn reload_watched_chat_ids(state: &AppState) -> Result<()> {
+    let watched_chat_ids = state
+        .settings
+        .get_watches()
+        .await?
+        .into_iter()
+        .map(|watch| watch.chat_id)
+        .collect();
+
+    *state.watched_chat_ids.lock().await = watched_chat_ids;
+
+    Ok(())
+}
+
+async fn show_watches(message: TelegramMessage, state: &AppState) -> Result<()> {
+    let watches = state
+        .settings
+        .get_watches()
+        .await?
+        .into_iter()
+        .filter(|watch| watch.group_id == message.chat().id())
+        .collect::<Vec<Watch>>();
+
+    if watches.is_empty() {
+        let response = "No chat is watched.";
+        message.respond(response).await.context(response)?;
+
+        return Ok(());
+    }
+
+    let mut response = "Watched chats:".to_string();
+
+    for watch in watches {
+        response.push_str(&format!(
+            "\n{}: {}",
+            watch.chat_name,
+            watch.dir.as_deref().unwrap_or("current directory")
+        ));
+
+        if !watch.filters.is_empty() {
+            response.push_str(&format!(" {}", watch.filters));
+        }
+    }
+
+    message.respond(response.as_str()).await.context(response)?;
+
+    Ok(())
+}
+
+// new messages that the user receives, transferred for each group that watches the chat
+pub async fn handle_watched_message(
+    message_origin: TelegramMessage,
+    state: AppState,
+) -> Result<()> {
+    let chat_id = message_origin.chat().id();
+
+    let watches = state
+        .settings
+        .get_watches()
+        .await?
+        .into_iter()
+        .filter(|watch| watch.chat_id == chat_id);
+
+    for watch in watches {
+        let filters = watch
+            .filters
+            .split_whitespace()
+            .map(|filter| filter.to_string())
+            .collect::<Vec<String>>();
+
+        // filters were checked by /watch, a broken one only skips its own watch
+        let media_filter = match MediaFilter::parse(&filters) {
+            Ok(media_filter) => media_filter,
+            Err(e) => {
+                e.context(format!(
+                    "invalid filters of watched chat {}",
+                    watch.chat_name
+                ))
+                .trace();
+
+                continue;
+            }
+        };
+
+        if !media_filter.matches(&message_origin) {
+            continue;
+        }
+
+        let message_link = get_message_link(
+            &ChatEntity::from(message_origin.chat()),
+            message_origin.id(),
+        );
+
+        // stands for the command message, deleting it cancels the task
+        let notice = state
+            .telegram_bot
+            .send_message(
+                chat_from_hex(&watch.group_bot_hex)?,
+                format!("New file in {}:\n{}", watch.chat_name, message_link),
+            )
+            .await?;
+
+        match insert_link_task(
+            &notice,
+            &message_origin,
+            &message_link,
+            &Flags::default(),
+            watch.dir.as_deref(),
+            &state,
+        )
+        .await
+        {
+            Ok(()) => tracing::info!("inserted watched message: {}", message_link),
+            Err(e) => notice
+                .reply(format!("failed to transfer {}: {}", message_link, e))
+                .await
+                .trace(),
+        }
+    }
+
+    Ok(())
+}
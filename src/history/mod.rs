@@ -0,0 +1,178 @@
+/*
+:project: telegram-onedrive
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+mod records;
+
+use anyhow::{Context, Result};
+use chrono::{Local, NaiveDate};
+pub use records::{Model as Record, Outcome};
+use sea_orm::{
+    ActiveValue, ColumnTrait, ConnectionTrait, DatabaseConnection, EntityName, EntityTrait,
+    PaginatorTrait, QueryFilter, QueryOrder, Schema, Select, Set,
+};
+
+const PAGE_SIZE: u64 = 10;
+
+// transfer history, kept until it's cleared by hand
+pub struct HistorySession {
+    connection: DatabaseConnection,
+}
+
+#[derive(Default)]
+pub struct HistoryFilter {
+    // chat where the tasks were created
+    pub chat_id: Option<i64>,
+    // part of the file name
+    pub filename: Option<String>,
+    pub outcome: Option<Outcome>,
+    // local date when the task finished
+    pub date: Option<NaiveDate>,
+}
+
+impl HistorySession {
+    pub async fn new(session_path: &str) -> Result<Self> {
+        let connection = sea_orm::Database::connect(format!("sqlite://{}?mode=rwc", session_path))
+            .await
+            .context("failed to connect to history session")?;
+
+        if records::Entity::find().all(&connection).await.is_err() {
+            let backend = connection.get_database_backend();
+
+            let table_create_statement =
+                Schema::new(backend).create_table_from_entity(records::Entity);
+
+            connection
+                .execute(backend.build(&table_create_statement))
+                .await
+                .context(format!(
+                    "failed to create table {}",
+                    records::Entity.table_name()
+                ))?;
+        }
+
+        Ok(Self { connection })
+    }
+
+    // id of the record is ignored
+    pub async fn insert_record(
+        &self,
+        Record {
+            cmd_type,
+            filename,
+            path,
+            item_id,
+            url,
+            chat_id,
+            message_id,
+            origin_chat_id,
+            origin_message_id,
+            account,
+            size,
+            duration,
+            average_speed,
+            outcome,
+            error,
+            finished_at,
+            ..
+        }: Record,
+    ) -> Result<()> {
+        let insert_item = records::ActiveModel {
+            id: ActiveValue::default(),
+            cmd_type: Set(cmd_type),
+            filename: Set(filename),
+            path: Set(path),
+            item_id: Set(item_id),
+            url: Set(url),
+            chat_id: Set(chat_id),
+            message_id: Set(message_id),
+            origin_chat_id: Set(origin_chat_id),
+            origin_message_id: Set(origin_message_id),
+            account: Set(account),
+            size: Set(size),
+            duration: Set(duration),
+            average_speed: Set(average_speed),
+            outcome: Set(outcome),
+            error: Set(error),
+            finished_at: Set(finished_at),
+        };
+
+        records::Entity::insert(insert_item)
+            .exec(&self.connection)
+            .await
+            .context("failed to insert history record")?;
+
+        Ok(())
+    }
+
+    // page starts from 1, returns the records and the number of pages
+    pub async fn get_records(
+        &self,
+        filter: &HistoryFilter,
+        page: u64,
+    ) -> Result<(Vec<Record>, u64)> {
+        let paginator = Self::filter_records(filter).paginate(&self.connection, PAGE_SIZE);
+
+        let pages = paginator
+            .num_pages()
+            .await
+            .context("failed to count history pages")?;
+
+        let records = paginator
+            .fetch_page(page.saturating_sub(1))
+            .await
+            .context("failed to get history records")?;
+
+        Ok((records, pages))
+    }
+
+    pub async fn get_all_records(&self, filter: &HistoryFilter) -> Result<Vec<Record>> {
+        Self::filter_records(filter)
+            .all(&self.connection)
+            .await
+            .context("failed to get all history records")
+    }
+
+    pub async fn clear(&self, chat_id: i64) -> Result<()> {
+        records::Entity::delete_many()
+            .filter(records::Column::ChatId.eq(chat_id))
+            .exec(&self.connection)
+            .await
+            .context("failed to clear history")?;
+
+        Ok(())
+    }
+
+    fn filter_records(filter: &HistoryFilter) -> Select<records::Entity> {
+        let mut select = records::Entity::find().order_by_desc(records::Column::Id);
+
+        if let Some(chat_id) = filter.chat_id {
+            select = select.filter(records::Column::ChatId.eq(chat_id));
+        }
+
+        if let Some(filename) = &filter.filename {
+            select = select.filter(records::Column::Filename.contains(filename));
+        }
+
+        if let Some(outcome) = filter.outcome {
+            select = select.filter(records::Column::Outcome.eq(outcome));
+        }
+
+        if let Some(date) = filter.date {
+            let day_start = |date: NaiveDate| {
+                date.and_hms_opt(0, 0, 0)
+                    .and_then(|datetime| datetime.and_local_timezone(Local).earliest())
+                    .map_or(0, |datetime| datetime.timestamp())
+            };
+
+            select = select
+                .filter(records::Column::FinishedAt.gte(day_start(date)))
+                .filter(records::Column::FinishedAt.lt(day_start(date + chrono::Days::new(1))));
+        }
+
+        select
+    }
+}
@@ -0,0 +1,124 @@
+/*
+:project: telegram-onedrive
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use crate::tasker::CmdType;
+use sea_orm::{
+    entity::prelude::DeriveEntityModel,
+    sea_query::{ArrayType, ValueType, ValueTypeErr},
+    ActiveModelBehavior, ColIdx, ColumnType, DbErr, DerivePrimaryKey, DeriveRelation, EntityTrait,
+    EnumIter, PrimaryKeyTrait, QueryResult, TryGetError, TryGetable, Value,
+};
+use std::fmt::Display;
+
+// a finished attempt of a task, kept after the task is deleted
+#[derive(Clone, Debug, DeriveEntityModel)]
+#[sea_orm(table_name = "records")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub cmd_type: CmdType,
+    pub filename: String,
+    // onedrive path of the uploaded file
+    pub path: String,
+    // onedrive item id, none if the file wasn't uploaded
+    pub item_id: Option<String>,
+    // for /url
+    pub url: Option<String>,
+    pub chat_id: i64,
+    pub message_id: i32,
+    // for link
+    pub origin_chat_id: Option<i64>,
+    // for link
+    pub origin_message_id: Option<i32>,
+    // onedrive username
+    pub account: Option<String>,
+    pub size: i64,
+    // milliseconds
+    pub duration: i64,
+    // bytes per second of this attempt
+    pub average_speed: i64,
+    pub outcome: Outcome,
+    pub error: Option<String>,
+    // timestamp in seconds
+    pub finished_at: i64,
+}
+
+#[derive(Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Outcome {
+    Completed,
+    Failed,
+    Skipped,
+    Cancelled,
+}
+
+impl Outcome {
+    pub fn parse(outcome: &str) -> Option<Self> {
+        match outcome {
+            "completed" => Some(Self::Completed),
+            "failed" => Some(Self::Failed),
+            "skipped" => Some(Self::Skipped),
+            "cancelled" => Some(Self::Cancelled),
+            _ => None,
+        }
+    }
+}
+
+impl ValueType for Outcome {
+    fn try_from(v: Value) -> Result<Self, ValueTypeErr> {
+        match v {
+            Value::String(Some(value)) => Self::parse(&value).ok_or(ValueTypeErr),
+            _ => Err(ValueTypeErr),
+        }
+    }
+
+    fn type_name() -> String {
+        "Outcome".to_string()
+    }
+
+    fn array_type() -> ArrayType {
+        ArrayType::String
+    }
+
+    fn column_type() -> ColumnType {
+        ColumnType::String(None)
+    }
+}
+
+impl From<Outcome> for Value {
+    fn from(value: Outcome) -> Self {
+        Self::String(Some(Box::new(value.to_string())))
+    }
+}
+
+impl TryGetable for Outcome {
+    fn try_get_by<I: ColIdx>(res: &QueryResult, index: I) -> Result<Self, TryGetError> {
+        let value: String = res.try_get_by(index)?;
+
+        Self::parse(&value).ok_or_else(|| {
+            TryGetError::DbErr(DbErr::Type(format!(
+                "outcome value should be one of completed, failed, skipped and cancelled: {}",
+                value
+            )))
+        })
+    }
+}
+
+impl Display for Outcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Completed => write!(f, "completed"),
+            Self::Failed => write!(f, "failed"),
+            Self::Skipped => write!(f, "skipped"),
+            Self::Cancelled => write!(f, "cancelled"),
+        }
+    }
+}
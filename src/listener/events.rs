@@ -34,10 +34,14 @@ impl HashMapExt for Events {
     }
 }
 
+const CALLBACK_PREFIX: &str = "__CALLBACK__";
+
 pub enum EventType {
     Command(String),
     Text,
     Media,
+    // inline keyboard buttons, keyed by the first word of their data
+    Callback(String),
 }
 
 impl EventType {
@@ -53,9 +57,21 @@ impl EventType {
         Self::Media
     }
 
+    pub fn callback(pattern: &str) -> Self {
+        Self::Callback(format!("{}{}", CALLBACK_PREFIX, pattern))
+    }
+
+    // the first word of the button data, none if it's not a callback
+    pub fn callback_pattern(&self) -> Option<&str> {
+        match self {
+            Self::Callback(callback) => callback.strip_prefix(CALLBACK_PREFIX),
+            _ => None,
+        }
+    }
+
     pub fn to_str(&self) -> &str {
         match self {
-            Self::Command(command) => command.as_str(),
+            Self::Command(command) | Self::Callback(command) => command.as_str(),
             Self::Text => "__TEXT__",
             Self::Media => "__MEDIA__",
         }
@@ -65,7 +81,7 @@ impl EventType {
 impl Display for EventType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Self::Command(command) => write!(f, "{}", command),
+            Self::Command(command) | Self::Callback(command) => write!(f, "{}", command),
             _ => write!(f, "{}", self.to_str()),
         }
     }
@@ -77,6 +93,8 @@ impl From<&String> for EventType {
             Self::Text
         } else if value == Self::Media.to_str() {
             Self::Media
+        } else if value.starts_with(CALLBACK_PREFIX) {
+            Self::Callback(value.to_string())
         } else {
             Self::Command(value.to_string())
         }
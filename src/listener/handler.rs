@@ -44,6 +44,23 @@ impl<'h> Handler<'h> {
         Ok(())
     }
 
+    // the message is the one with the button, its text is the data of the button
+    pub async fn handle_callback(&self, message: TelegramMessage) -> Result<()> {
+        let data = message.text();
+        let pattern = data.split_whitespace().next().unwrap_or_default();
+
+        for event in self.get_event_names() {
+            if event.callback_pattern() == Some(pattern) {
+                tracing::info!("handle callback {}", pattern);
+
+                self.trigger(event, message).await?;
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
     async fn handle_command(&self, message: TelegramMessage) -> Result<()> {
         let text = message.text();
 
@@ -11,14 +11,15 @@ mod handler;
 use crate::{
     client::utils::chat_from_hex,
     error::{ErrorExt, ResultExt, ResultUnwrapExt},
+    handlers,
     message::{ChatEntity, TelegramMessage},
     state::{AppState, State},
     tasker::Tasker,
 };
-use anyhow::{Ok, Result};
+use anyhow::{Context, Ok, Result};
 use events::Events;
 pub use events::{EventType, HashMapExt};
-use grammers_client::Update;
+use grammers_client::{types::MessageDeletion, Update};
 use handler::Handler;
 use std::sync::Arc;
 
@@ -45,9 +46,7 @@ impl Listener {
         let state = self.state.clone();
         tokio::spawn(async move {
             loop {
-                handle_batch_cancellation(state.clone())
-                    .await
-                    .unwrap_or_trace();
+                handle_user_update(state.clone()).await.unwrap_or_trace();
             }
         });
 
@@ -74,6 +73,28 @@ impl Listener {
                     }
                 }
             }
+            Update::CallbackQuery(query) => {
+                let message_raw = query
+                    .load_message()
+                    .await
+                    .context("failed to load message of callback query")?;
+
+                let mut message = TelegramMessage::new(client.clone(), message_raw);
+                message.override_text(String::from_utf8_lossy(query.data()).to_string());
+                message.override_sender(query.sender().clone());
+
+                let handler = Handler::new(&self.events, self.state.clone());
+                if let Err(e) = handler.handle_callback(message.clone()).await {
+                    e.send(message).await.unwrap_both().trace();
+                }
+
+                // stop the loading animation of the button
+                query
+                    .answer()
+                    .send()
+                    .await
+                    .context("failed to answer callback query")?;
+            }
             Update::MessageDeleted(messages_info) => {
                 // abort the task if the related message is deleted
                 // bot can only catch deleted message immediately if it is sent by itself
@@ -100,8 +121,12 @@ impl Listener {
                                 .unwrap_or_trace()
                                 && !batch_is_processing;
 
+                            // items of an album waiting behind it share the indicator
                             task_session
-                                .delete_task(task_aborter.id)
+                                .delete_task_from_message_indicator_id_if_exists(
+                                    chat_id,
+                                    *message_indicator_id,
+                                )
                                 .await
                                 .unwrap_or_trace();
 
@@ -133,28 +158,55 @@ impl Listener {
     }
 }
 
-async fn handle_batch_cancellation(state: AppState) -> Result<()> {
+async fn handle_user_update(state: AppState) -> Result<()> {
+    let telegram_user = &state.telegram_user;
+
+    match telegram_user.next_update().await? {
+        Update::NewMessage(message_raw) => {
+            let message = TelegramMessage::new(telegram_user.clone(), message_raw);
+
+            // a new post in a watched chat
+            if state
+                .watched_chat_ids
+                .lock()
+                .await
+                .contains(&message.chat().id())
+            {
+                tokio::spawn(async move {
+                    handlers::watch::handle_watched_message(message, state)
+                        .await
+                        .trace();
+                });
+            }
+        }
+        Update::MessageDeleted(messages_info) => {
+            handle_batch_cancellation(messages_info, &state).await?;
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+async fn handle_batch_cancellation(messages_info: MessageDeletion, state: &AppState) -> Result<()> {
     let telegram_user = &state.telegram_user;
     let task_session = &state.task_session;
-    let update = telegram_user.next_update().await?;
-
-    if let Update::MessageDeleted(messages_info) = update {
-        if let Some(chat_id) = messages_info.channel_id() {
-            for message_id in messages_info.messages() {
-                let mut batch_aborters = task_session.batch_aborters.lock().await;
-                if let Some(batch_aborter) = batch_aborters.remove(&(chat_id, *message_id)) {
-                    batch_aborter.abort();
-                }
-                drop(batch_aborters);
 
-                let mut task_aborters = task_session.task_aborters.lock().await;
-                let message_indicator_ids = task_session
-                    .get_message_indicator_ids(chat_id, *message_id)
-                    .await?;
-                for message_indicator_id in message_indicator_ids {
-                    let chat_user = if let Some(aborter) =
-                        task_aborters.remove(&(chat_id, message_indicator_id))
-                    {
+    if let Some(chat_id) = messages_info.channel_id() {
+        for message_id in messages_info.messages() {
+            let mut batch_aborters = task_session.batch_aborters.lock().await;
+            if let Some(batch_aborter) = batch_aborters.remove(&(chat_id, *message_id)) {
+                batch_aborter.abort();
+            }
+            drop(batch_aborters);
+
+            let mut task_aborters = task_session.task_aborters.lock().await;
+            let message_indicator_ids = task_session
+                .get_message_indicator_ids(chat_id, *message_id)
+                .await?;
+            for message_indicator_id in message_indicator_ids {
+                let chat_user =
+                    if let Some(aborter) = task_aborters.remove(&(chat_id, message_indicator_id)) {
                         aborter.abort();
                         task_session.delete_task(aborter.id).await?;
 
@@ -173,10 +225,9 @@ async fn handle_batch_cancellation(state: AppState) -> Result<()> {
                             .pack()
                     };
 
-                    telegram_user
-                        .delete_messages(chat_user, &[message_indicator_id])
-                        .await?;
-                }
+                telegram_user
+                    .delete_messages(chat_user, &[message_indicator_id])
+                    .await?;
             }
         }
     }
@@ -10,8 +10,10 @@ mod client;
 mod env;
 mod error;
 mod handlers;
+mod history;
 mod listener;
 mod message;
+mod settings;
 mod state;
 mod tasker;
 mod trace;
@@ -19,7 +21,9 @@ mod utils;
 
 use env::{Env, ENV};
 use handlers::{
-    auth, auto_delete, clear, dir, drive, file, help, link, links, logs, start, url, version,
+    auth, auto_delete, browse, clear, conflict, dir, drive, file, filter, get, help, history,
+    limit, link, links, logs, ls, mirror, mkdir, mv, pause, queue, quota, resume, retry, rm, route,
+    schedule, share, start, template, url, version, watch,
 };
 use listener::{EventType, HashMapExt, Listener};
 use std::collections::HashMap;
@@ -48,6 +52,35 @@ async fn main() {
         .on(EventType::command(drive::PATTERN), drive::handler)
         .on(EventType::command(url::PATTERN), url::handler)
         .on(EventType::command(links::PATTERN), links::handler)
+        .on(EventType::command(retry::PATTERN), retry::handler)
+        .on(EventType::command(limit::PATTERN), limit::handler)
+        .on(EventType::command(conflict::PATTERN), conflict::handler)
+        .on(EventType::command(history::PATTERN), history::handler)
+        .on(EventType::command(queue::PATTERN), queue::handler)
+        .on(EventType::command(pause::PATTERN), pause::handler)
+        .on(EventType::command(resume::PATTERN), resume::handler)
+        .on(EventType::command(schedule::PATTERN), schedule::handler)
+        .on(EventType::command(route::PATTERN), route::handler)
+        .on(EventType::command(quota::PATTERN), quota::handler)
+        .on(EventType::command(browse::PATTERN), browse::handler)
+        .on(
+            EventType::callback(browse::CALLBACK_PATTERN),
+            browse::callback_handler,
+        )
+        .on(EventType::command(ls::PATTERN), ls::handler)
+        .on(EventType::command(mkdir::PATTERN), mkdir::handler)
+        .on(EventType::command(mv::PATTERN), mv::handler)
+        .on(EventType::command(rm::PATTERN), rm::handler)
+        .on(
+            EventType::callback(rm::CALLBACK_PATTERN),
+            rm::callback_handler,
+        )
+        .on(EventType::command(share::PATTERN), share::handler)
+        .on(EventType::command(get::PATTERN), get::handler)
+        .on(EventType::command(mirror::PATTERN), mirror::handler)
+        .on(EventType::command(watch::PATTERN), watch::handler)
+        .on(EventType::command(filter::PATTERN), filter::handler)
+        .on(EventType::command(template::PATTERN), template::handler)
         .on(EventType::command(version::PATTERN), version::handler)
         .on(EventType::media(), file::handler)
         .on(EventType::text(), link::handler);
@@ -16,6 +16,7 @@ pub struct TelegramMessage {
     pub raw: Arc<Message>,
     client: TelegramClient,
     text_override: Option<String>,
+    sender_override: Option<Chat>,
 }
 
 impl TelegramMessage {
@@ -24,6 +25,7 @@ impl TelegramMessage {
             raw: Arc::new(message),
             client,
             text_override: None,
+            sender_override: None,
         }
     }
 
@@ -31,6 +33,11 @@ impl TelegramMessage {
         self.text_override = Some(text);
     }
 
+    // the user who pressed the button, instead of the bot that sent the message
+    pub fn override_sender(&mut self, sender: Chat) {
+        self.sender_override = Some(sender);
+    }
+
     pub fn chat(&self) -> Chat {
         self.raw.chat()
     }
@@ -50,7 +57,16 @@ impl TelegramMessage {
     }
 
     pub fn sender(&self) -> Option<Chat> {
-        self.raw.sender()
+        self.sender_override.clone().or_else(|| self.raw.sender())
+    }
+
+    // items of an album share it
+    pub fn grouped_id(&self) -> Option<i64> {
+        self.raw.grouped_id()
+    }
+
+    pub fn reply_to_message_id(&self) -> Option<i32> {
+        self.raw.reply_to_message_id()
     }
 
     pub async fn respond<M: Into<InputMessage>>(&self, message: M) -> Result<Self> {
@@ -0,0 +1,31 @@
+/*
+:project: telegram-onedrive
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use sea_orm::{
+    entity::prelude::DeriveEntityModel, ActiveModelBehavior, DerivePrimaryKey, DeriveRelation,
+    EntityTrait, EnumIter, PrimaryKeyTrait,
+};
+
+// share link created for files uploaded from a chat
+#[derive(Clone, Debug, DeriveEntityModel)]
+#[sea_orm(table_name = "chat_share_links")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub chat_id: i64,
+    // view or edit
+    pub link_type: String,
+    // anonymous or organization
+    pub scope: String,
+    // the link expires this many hours after the upload
+    pub expiration_hours: Option<i64>,
+    pub password: Option<String>,
+}
+
+#[derive(Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
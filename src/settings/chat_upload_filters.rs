@@ -0,0 +1,35 @@
+/*
+:project: telegram-onedrive
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use sea_orm::{
+    entity::prelude::DeriveEntityModel, ActiveModelBehavior, DerivePrimaryKey, DeriveRelation,
+    EntityTrait, EnumIter, PrimaryKeyTrait,
+};
+
+// which media sent to a chat are uploaded, lists are comma separated and empty for no rule
+#[derive(Clone, Debug, Default, DeriveEntityModel)]
+#[sea_orm(table_name = "chat_upload_filters")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub chat_id: i64,
+    // mime types like video/mp4 or video/*
+    pub allowed_types: String,
+    pub denied_types: String,
+    // extensions without dot like mp4
+    pub allowed_exts: String,
+    pub denied_exts: String,
+    // in bytes
+    pub min_size: Option<i64>,
+    pub max_size: Option<i64>,
+    pub ignore_stickers: bool,
+    pub ignore_voices: bool,
+}
+
+#[derive(Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,97 @@
+/*
+:project: telegram-onedrive
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use anyhow::{anyhow, Result};
+use onedrive_api::ConflictBehavior;
+use sea_orm::{
+    sea_query::{ArrayType, ValueType, ValueTypeErr},
+    ColIdx, ColumnType, DbErr, QueryResult, TryGetError, TryGetable, Value,
+};
+use std::fmt::Display;
+
+// what to do if a file with the same name exists in the target directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConflictPolicy {
+    #[default]
+    Rename,
+    Replace,
+    Skip,
+}
+
+impl ConflictPolicy {
+    pub fn parse(policy: &str) -> Result<Self> {
+        match policy {
+            "rename" => Ok(Self::Rename),
+            "replace" => Ok(Self::Replace),
+            "skip" => Ok(Self::Skip),
+            _ => Err(anyhow!(
+                "conflict policy should be one of rename, replace and skip: {}",
+                policy
+            )),
+        }
+    }
+
+    // like /url $url --replace
+    pub fn from_flag(flag: &str) -> Option<Self> {
+        flag.strip_prefix("--")
+            .and_then(|policy| Self::parse(policy).ok())
+    }
+
+    // skip makes onedrive respond 409 if the file exists
+    pub const fn to_conflict_behavior(self) -> ConflictBehavior {
+        match self {
+            Self::Rename => ConflictBehavior::Rename,
+            Self::Replace => ConflictBehavior::Replace,
+            Self::Skip => ConflictBehavior::Fail,
+        }
+    }
+}
+
+impl ValueType for ConflictPolicy {
+    fn try_from(v: Value) -> Result<Self, ValueTypeErr> {
+        match v {
+            Value::String(Some(value)) => Self::parse(&value).map_err(|_| ValueTypeErr),
+            _ => Err(ValueTypeErr),
+        }
+    }
+
+    fn type_name() -> String {
+        "ConflictPolicy".to_string()
+    }
+
+    fn array_type() -> ArrayType {
+        ArrayType::String
+    }
+
+    fn column_type() -> ColumnType {
+        ColumnType::String(None)
+    }
+}
+
+impl From<ConflictPolicy> for Value {
+    fn from(value: ConflictPolicy) -> Self {
+        Self::String(Some(Box::new(value.to_string())))
+    }
+}
+
+impl TryGetable for ConflictPolicy {
+    fn try_get_by<I: ColIdx>(res: &QueryResult, index: I) -> Result<Self, TryGetError> {
+        let value: String = res.try_get_by(index)?;
+
+        Self::parse(&value).map_err(|e| TryGetError::DbErr(DbErr::Type(e.to_string())))
+    }
+}
+
+impl Display for ConflictPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Rename => write!(f, "rename"),
+            Self::Replace => write!(f, "replace"),
+            Self::Skip => write!(f, "skip"),
+        }
+    }
+}
@@ -0,0 +1,722 @@
+/*
+:project: telegram-onedrive
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+mod chat_bandwidth_limits;
+mod chat_conflict_policies;
+mod chat_name_templates;
+mod chat_share_links;
+mod chat_upload_filters;
+mod conflict_policy;
+mod dir_conflict_policies;
+mod dir_name_templates;
+mod mirror_cursors;
+mod options;
+mod quota_alert_chats;
+mod route;
+mod routes;
+mod watches;
+
+use anyhow::{Context, Result};
+pub use chat_bandwidth_limits::Model as ChatBandwidthLimit;
+pub use chat_share_links::Model as ChatShareLink;
+pub use chat_upload_filters::Model as ChatUploadFilter;
+pub use conflict_policy::ConflictPolicy;
+pub use route::{get_file_type, RouteKind, FILE_TYPES};
+use sea_orm::{
+    sea_query::OnConflict, ConnectionTrait, DatabaseConnection, EntityName, EntityTrait, Schema,
+    Set,
+};
+use std::path::Path;
+pub use watches::Model as Watch;
+
+const SCHEDULE_KEY: &str = "schedule";
+const QUEUE_PAUSED_KEY: &str = "queue_paused";
+const DOWNLOAD_LIMIT_KEY: &str = "download_limit";
+const UPLOAD_LIMIT_KEY: &str = "upload_limit";
+
+// settings changed by commands, kept after restart
+pub struct SettingsSession {
+    connection: DatabaseConnection,
+}
+
+impl SettingsSession {
+    pub async fn new(session_path: &str) -> Result<Self> {
+        let connection = sea_orm::Database::connect(format!("sqlite://{}?mode=rwc", session_path))
+            .await
+            .context("failed to connect to settings session")?;
+
+        Self::create_table_if_not_exists(&connection, chat_conflict_policies::Entity).await?;
+        Self::create_table_if_not_exists(&connection, dir_conflict_policies::Entity).await?;
+        Self::create_table_if_not_exists(&connection, routes::Entity).await?;
+        Self::create_table_if_not_exists(&connection, quota_alert_chats::Entity).await?;
+        Self::create_table_if_not_exists(&connection, chat_share_links::Entity).await?;
+        Self::create_table_if_not_exists(&connection, chat_name_templates::Entity).await?;
+        Self::create_table_if_not_exists(&connection, dir_name_templates::Entity).await?;
+        Self::create_table_if_not_exists(&connection, mirror_cursors::Entity).await?;
+        Self::create_table_if_not_exists(&connection, watches::Entity).await?;
+        Self::create_table_if_not_exists(&connection, chat_upload_filters::Entity).await?;
+        Self::create_table_if_not_exists(&connection, options::Entity).await?;
+        Self::create_table_if_not_exists(&connection, chat_bandwidth_limits::Entity).await?;
+
+        Ok(Self { connection })
+    }
+
+    async fn create_table_if_not_exists<E>(connection: &DatabaseConnection, entity: E) -> Result<()>
+    where
+        E: EntityTrait + EntityName,
+    {
+        if E::find().all(connection).await.is_err() {
+            let backend = connection.get_database_backend();
+
+            let table_create_statement = Schema::new(backend).create_table_from_entity(entity);
+
+            connection
+                .execute(backend.build(&table_create_statement))
+                .await
+                .context(format!("failed to create table {}", entity.table_name()))?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn get_chat_conflict_policy(&self, chat_id: i64) -> Result<Option<ConflictPolicy>> {
+        let chat_conflict_policy = chat_conflict_policies::Entity::find_by_id(chat_id)
+            .one(&self.connection)
+            .await
+            .context("failed to get chat conflict policy")?;
+
+        Ok(chat_conflict_policy.map(|chat_conflict_policy| chat_conflict_policy.policy))
+    }
+
+    pub async fn set_chat_conflict_policy(
+        &self,
+        chat_id: i64,
+        policy: ConflictPolicy,
+    ) -> Result<()> {
+        let insert_item = chat_conflict_policies::ActiveModel {
+            chat_id: Set(chat_id),
+            policy: Set(policy),
+        };
+
+        chat_conflict_policies::Entity::insert(insert_item)
+            .on_conflict(
+                OnConflict::column(chat_conflict_policies::Column::ChatId)
+                    .update_column(chat_conflict_policies::Column::Policy)
+                    .to_owned(),
+            )
+            .exec(&self.connection)
+            .await
+            .context("failed to set chat conflict policy")?;
+
+        Ok(())
+    }
+
+    pub async fn delete_chat_conflict_policy(&self, chat_id: i64) -> Result<()> {
+        chat_conflict_policies::Entity::delete_by_id(chat_id)
+            .exec(&self.connection)
+            .await
+            .context("failed to delete chat conflict policy")?;
+
+        Ok(())
+    }
+
+    pub async fn get_dir_conflict_policies(&self) -> Result<Vec<(String, ConflictPolicy)>> {
+        let dir_conflict_policies = dir_conflict_policies::Entity::find()
+            .all(&self.connection)
+            .await
+            .context("failed to get dir conflict policies")?;
+
+        Ok(dir_conflict_policies
+            .into_iter()
+            .map(|dir_conflict_policy| (dir_conflict_policy.path, dir_conflict_policy.policy))
+            .collect())
+    }
+
+    pub async fn set_dir_conflict_policy(&self, path: &str, policy: ConflictPolicy) -> Result<()> {
+        let insert_item = dir_conflict_policies::ActiveModel {
+            path: Set(path.to_string()),
+            policy: Set(policy),
+        };
+
+        dir_conflict_policies::Entity::insert(insert_item)
+            .on_conflict(
+                OnConflict::column(dir_conflict_policies::Column::Path)
+                    .update_column(dir_conflict_policies::Column::Policy)
+                    .to_owned(),
+            )
+            .exec(&self.connection)
+            .await
+            .context("failed to set dir conflict policy")?;
+
+        Ok(())
+    }
+
+    pub async fn delete_dir_conflict_policy(&self, path: &str) -> Result<()> {
+        dir_conflict_policies::Entity::delete_by_id(path.to_string())
+            .exec(&self.connection)
+            .await
+            .context("failed to delete dir conflict policy")?;
+
+        Ok(())
+    }
+
+    // the policy of the nearest directory, then the policy of the chat, rename by default
+    pub async fn get_conflict_policy(
+        &self,
+        chat_id: i64,
+        root_path: &str,
+    ) -> Result<ConflictPolicy> {
+        let root_path = Path::new(root_path);
+
+        let dir_conflict_policy = self
+            .get_dir_conflict_policies()
+            .await?
+            .into_iter()
+            .filter(|(path, _)| root_path.starts_with(path))
+            .max_by_key(|(path, _)| path.len())
+            .map(|(_, policy)| policy);
+
+        if let Some(policy) = dir_conflict_policy {
+            return Ok(policy);
+        }
+
+        Ok(self
+            .get_chat_conflict_policy(chat_id)
+            .await?
+            .unwrap_or_default())
+    }
+
+    pub async fn get_chat_name_template(&self, chat_id: i64) -> Result<Option<String>> {
+        let chat_name_template = chat_name_templates::Entity::find_by_id(chat_id)
+            .one(&self.connection)
+            .await
+            .context("failed to get chat name template")?;
+
+        Ok(chat_name_template.map(|chat_name_template| chat_name_template.template))
+    }
+
+    pub async fn set_chat_name_template(&self, chat_id: i64, template: &str) -> Result<()> {
+        let insert_item = chat_name_templates::ActiveModel {
+            chat_id: Set(chat_id),
+            template: Set(template.to_string()),
+        };
+
+        chat_name_templates::Entity::insert(insert_item)
+            .on_conflict(
+                OnConflict::column(chat_name_templates::Column::ChatId)
+                    .update_column(chat_name_templates::Column::Template)
+                    .to_owned(),
+            )
+            .exec(&self.connection)
+            .await
+            .context("failed to set chat name template")?;
+
+        Ok(())
+    }
+
+    pub async fn delete_chat_name_template(&self, chat_id: i64) -> Result<()> {
+        chat_name_templates::Entity::delete_by_id(chat_id)
+            .exec(&self.connection)
+            .await
+            .context("failed to delete chat name template")?;
+
+        Ok(())
+    }
+
+    pub async fn get_dir_name_templates(&self) -> Result<Vec<(String, String)>> {
+        let dir_name_templates = dir_name_templates::Entity::find()
+            .all(&self.connection)
+            .await
+            .context("failed to get dir name templates")?;
+
+        Ok(dir_name_templates
+            .into_iter()
+            .map(|dir_name_template| (dir_name_template.path, dir_name_template.template))
+            .collect())
+    }
+
+    pub async fn set_dir_name_template(&self, path: &str, template: &str) -> Result<()> {
+        let insert_item = dir_name_templates::ActiveModel {
+            path: Set(path.to_string()),
+            template: Set(template.to_string()),
+        };
+
+        dir_name_templates::Entity::insert(insert_item)
+            .on_conflict(
+                OnConflict::column(dir_name_templates::Column::Path)
+                    .update_column(dir_name_templates::Column::Template)
+                    .to_owned(),
+            )
+            .exec(&self.connection)
+            .await
+            .context("failed to set dir name template")?;
+
+        Ok(())
+    }
+
+    pub async fn delete_dir_name_template(&self, path: &str) -> Result<()> {
+        dir_name_templates::Entity::delete_by_id(path.to_string())
+            .exec(&self.connection)
+            .await
+            .context("failed to delete dir name template")?;
+
+        Ok(())
+    }
+
+    // the template of the nearest directory, then the template of the chat, none if neither is set
+    pub async fn get_name_template(&self, chat_id: i64, root_path: &str) -> Result<Option<String>> {
+        let root_path = Path::new(root_path);
+
+        let dir_name_template = self
+            .get_dir_name_templates()
+            .await?
+            .into_iter()
+            .filter(|(path, _)| root_path.starts_with(path))
+            .max_by_key(|(path, _)| path.len())
+            .map(|(_, template)| template);
+
+        if dir_name_template.is_some() {
+            return Ok(dir_name_template);
+        }
+
+        self.get_chat_name_template(chat_id).await
+    }
+
+    pub async fn get_mirror_cursor(&self, chat_id: i64, group_id: i64) -> Result<Option<i32>> {
+        let mirror_cursor = mirror_cursors::Entity::find_by_id((chat_id, group_id))
+            .one(&self.connection)
+            .await
+            .context("failed to get mirror cursor")?;
+
+        Ok(mirror_cursor.map(|mirror_cursor| mirror_cursor.message_id))
+    }
+
+    pub async fn set_mirror_cursor(
+        &self,
+        chat_id: i64,
+        group_id: i64,
+        message_id: i32,
+    ) -> Result<()> {
+        let insert_item = mirror_cursors::ActiveModel {
+            chat_id: Set(chat_id),
+            group_id: Set(group_id),
+            message_id: Set(message_id),
+        };
+
+        mirror_cursors::Entity::insert(insert_item)
+            .on_conflict(
+                OnConflict::columns([
+                    mirror_cursors::Column::ChatId,
+                    mirror_cursors::Column::GroupId,
+                ])
+                .update_column(mirror_cursors::Column::MessageId)
+                .to_owned(),
+            )
+            .exec(&self.connection)
+            .await
+            .context("failed to set mirror cursor")?;
+
+        Ok(())
+    }
+
+    pub async fn delete_mirror_cursor(&self, chat_id: i64, group_id: i64) -> Result<()> {
+        mirror_cursors::Entity::delete_by_id((chat_id, group_id))
+            .exec(&self.connection)
+            .await
+            .context("failed to delete mirror cursor")?;
+
+        Ok(())
+    }
+
+    pub async fn get_watches(&self) -> Result<Vec<Watch>> {
+        watches::Entity::find()
+            .all(&self.connection)
+            .await
+            .context("failed to get watches")
+    }
+
+    pub async fn set_watch(&self, watch: Watch) -> Result<()> {
+        let insert_item = watches::ActiveModel {
+            chat_id: Set(watch.chat_id),
+            group_id: Set(watch.group_id),
+            chat_name: Set(watch.chat_name),
+            group_bot_hex: Set(watch.group_bot_hex),
+            filters: Set(watch.filters),
+            dir: Set(watch.dir),
+        };
+
+        watches::Entity::insert(insert_item)
+            .on_conflict(
+                OnConflict::columns([watches::Column::ChatId, watches::Column::GroupId])
+                    .update_columns([
+                        watches::Column::ChatName,
+                        watches::Column::GroupBotHex,
+                        watches::Column::Filters,
+                        watches::Column::Dir,
+                    ])
+                    .to_owned(),
+            )
+            .exec(&self.connection)
+            .await
+            .context("failed to set watch")?;
+
+        Ok(())
+    }
+
+    pub async fn delete_watch(&self, chat_id: i64, group_id: i64) -> Result<()> {
+        watches::Entity::delete_by_id((chat_id, group_id))
+            .exec(&self.connection)
+            .await
+            .context("failed to delete watch")?;
+
+        Ok(())
+    }
+
+    pub async fn get_chat_upload_filter(&self, chat_id: i64) -> Result<Option<ChatUploadFilter>> {
+        chat_upload_filters::Entity::find_by_id(chat_id)
+            .one(&self.connection)
+            .await
+            .context("failed to get chat upload filter")
+    }
+
+    pub async fn set_chat_upload_filter(&self, chat_upload_filter: ChatUploadFilter) -> Result<()> {
+        let insert_item = chat_upload_filters::ActiveModel {
+            chat_id: Set(chat_upload_filter.chat_id),
+            allowed_types: Set(chat_upload_filter.allowed_types),
+            denied_types: Set(chat_upload_filter.denied_types),
+            allowed_exts: Set(chat_upload_filter.allowed_exts),
+            denied_exts: Set(chat_upload_filter.denied_exts),
+            min_size: Set(chat_upload_filter.min_size),
+            max_size: Set(chat_upload_filter.max_size),
+            ignore_stickers: Set(chat_upload_filter.ignore_stickers),
+            ignore_voices: Set(chat_upload_filter.ignore_voices),
+        };
+
+        chat_upload_filters::Entity::insert(insert_item)
+            .on_conflict(
+                OnConflict::column(chat_upload_filters::Column::ChatId)
+                    .update_columns([
+                        chat_upload_filters::Column::AllowedTypes,
+                        chat_upload_filters::Column::DeniedTypes,
+                        chat_upload_filters::Column::AllowedExts,
+                        chat_upload_filters::Column::DeniedExts,
+                        chat_upload_filters::Column::MinSize,
+                        chat_upload_filters::Column::MaxSize,
+                        chat_upload_filters::Column::IgnoreStickers,
+                        chat_upload_filters::Column::IgnoreVoices,
+                    ])
+                    .to_owned(),
+            )
+            .exec(&self.connection)
+            .await
+            .context("failed to set chat upload filter")?;
+
+        Ok(())
+    }
+
+    pub async fn delete_chat_upload_filter(&self, chat_id: i64) -> Result<()> {
+        chat_upload_filters::Entity::delete_by_id(chat_id)
+            .exec(&self.connection)
+            .await
+            .context("failed to delete chat upload filter")?;
+
+        Ok(())
+    }
+
+    pub async fn get_chat_bandwidth_limits(&self) -> Result<Vec<ChatBandwidthLimit>> {
+        chat_bandwidth_limits::Entity::find()
+            .all(&self.connection)
+            .await
+            .context("failed to get chat bandwidth limits")
+    }
+
+    pub async fn set_chat_bandwidth_limit(
+        &self,
+        chat_bandwidth_limit: ChatBandwidthLimit,
+    ) -> Result<()> {
+        let insert_item = chat_bandwidth_limits::ActiveModel {
+            chat_id: Set(chat_bandwidth_limit.chat_id),
+            download: Set(chat_bandwidth_limit.download),
+            upload: Set(chat_bandwidth_limit.upload),
+        };
+
+        chat_bandwidth_limits::Entity::insert(insert_item)
+            .on_conflict(
+                OnConflict::column(chat_bandwidth_limits::Column::ChatId)
+                    .update_columns([
+                        chat_bandwidth_limits::Column::Download,
+                        chat_bandwidth_limits::Column::Upload,
+                    ])
+                    .to_owned(),
+            )
+            .exec(&self.connection)
+            .await
+            .context("failed to set chat bandwidth limit")?;
+
+        Ok(())
+    }
+
+    pub async fn delete_chat_bandwidth_limit(&self, chat_id: i64) -> Result<()> {
+        chat_bandwidth_limits::Entity::delete_by_id(chat_id)
+            .exec(&self.connection)
+            .await
+            .context("failed to delete chat bandwidth limit")?;
+
+        Ok(())
+    }
+
+    pub async fn get_chat_share_link(&self, chat_id: i64) -> Result<Option<ChatShareLink>> {
+        chat_share_links::Entity::find_by_id(chat_id)
+            .one(&self.connection)
+            .await
+            .context("failed to get chat share link")
+    }
+
+    pub async fn set_chat_share_link(&self, chat_share_link: ChatShareLink) -> Result<()> {
+        let insert_item = chat_share_links::ActiveModel {
+            chat_id: Set(chat_share_link.chat_id),
+            link_type: Set(chat_share_link.link_type),
+            scope: Set(chat_share_link.scope),
+            expiration_hours: Set(chat_share_link.expiration_hours),
+            password: Set(chat_share_link.password),
+        };
+
+        chat_share_links::Entity::insert(insert_item)
+            .on_conflict(
+                OnConflict::column(chat_share_links::Column::ChatId)
+                    .update_columns([
+                        chat_share_links::Column::LinkType,
+                        chat_share_links::Column::Scope,
+                        chat_share_links::Column::ExpirationHours,
+                        chat_share_links::Column::Password,
+                    ])
+                    .to_owned(),
+            )
+            .exec(&self.connection)
+            .await
+            .context("failed to set chat share link")?;
+
+        Ok(())
+    }
+
+    pub async fn delete_chat_share_link(&self, chat_id: i64) -> Result<()> {
+        chat_share_links::Entity::delete_by_id(chat_id)
+            .exec(&self.connection)
+            .await
+            .context("failed to delete chat share link")?;
+
+        Ok(())
+    }
+
+    // chat bot hexes of chats to send quota alerts to
+    pub async fn get_quota_alert_chats(&self) -> Result<Vec<String>> {
+        let quota_alert_chats = quota_alert_chats::Entity::find()
+            .all(&self.connection)
+            .await
+            .context("failed to get quota alert chats")?;
+
+        Ok(quota_alert_chats
+            .into_iter()
+            .map(|quota_alert_chat| quota_alert_chat.chat_bot_hex)
+            .collect())
+    }
+
+    pub async fn is_quota_alert_chat(&self, chat_id: i64) -> Result<bool> {
+        let quota_alert_chat = quota_alert_chats::Entity::find_by_id(chat_id)
+            .one(&self.connection)
+            .await
+            .context("failed to get quota alert chat")?;
+
+        Ok(quota_alert_chat.is_some())
+    }
+
+    pub async fn set_quota_alert_chat(&self, chat_id: i64, chat_bot_hex: &str) -> Result<()> {
+        let insert_item = quota_alert_chats::ActiveModel {
+            chat_id: Set(chat_id),
+            chat_bot_hex: Set(chat_bot_hex.to_string()),
+        };
+
+        quota_alert_chats::Entity::insert(insert_item)
+            .on_conflict(
+                OnConflict::column(quota_alert_chats::Column::ChatId)
+                    .update_column(quota_alert_chats::Column::ChatBotHex)
+                    .to_owned(),
+            )
+            .exec(&self.connection)
+            .await
+            .context("failed to set quota alert chat")?;
+
+        Ok(())
+    }
+
+    pub async fn delete_quota_alert_chat(&self, chat_id: i64) -> Result<()> {
+        quota_alert_chats::Entity::delete_by_id(chat_id)
+            .exec(&self.connection)
+            .await
+            .context("failed to delete quota alert chat")?;
+
+        Ok(())
+    }
+
+    pub async fn get_routes(&self) -> Result<Vec<(RouteKind, String, String)>> {
+        let routes = routes::Entity::find()
+            .all(&self.connection)
+            .await
+            .context("failed to get routes")?;
+
+        Ok(routes
+            .into_iter()
+            .map(|route| (route.kind, route.value, route.account))
+            .collect())
+    }
+
+    pub async fn set_route(&self, kind: RouteKind, value: &str, account: &str) -> Result<()> {
+        let insert_item = routes::ActiveModel {
+            kind: Set(kind),
+            value: Set(value.to_string()),
+            account: Set(account.to_string()),
+        };
+
+        routes::Entity::insert(insert_item)
+            .on_conflict(
+                OnConflict::columns([routes::Column::Kind, routes::Column::Value])
+                    .update_column(routes::Column::Account)
+                    .to_owned(),
+            )
+            .exec(&self.connection)
+            .await
+            .context("failed to set route")?;
+
+        Ok(())
+    }
+
+    pub async fn delete_route(&self, kind: RouteKind, value: &str) -> Result<()> {
+        routes::Entity::delete_by_id((kind, value.to_string()))
+            .exec(&self.connection)
+            .await
+            .context("failed to delete route")?;
+
+        Ok(())
+    }
+
+    // the account of the sender, then the account of the chat, then the account of the file type
+    pub async fn get_routed_accounts(
+        &self,
+        chat_id: i64,
+        sender_id: Option<i64>,
+        filename: &str,
+    ) -> Result<Vec<String>> {
+        let routes = self.get_routes().await?;
+
+        let mut candidates = Vec::new();
+        if let Some(sender_id) = sender_id {
+            candidates.push((RouteKind::Sender, sender_id.to_string()));
+        }
+        candidates.push((RouteKind::Chat, chat_id.to_string()));
+        candidates.push((RouteKind::Type, get_file_type(filename).to_string()));
+
+        Ok(candidates
+            .into_iter()
+            .filter_map(|(kind, value)| {
+                routes
+                    .iter()
+                    .find(|(route_kind, route_value, _)| {
+                        *route_kind == kind && *route_value == value
+                    })
+                    .map(|(_, _, account)| account.clone())
+            })
+            .collect())
+    }
+
+    async fn get_option(&self, key: &str) -> Result<Option<String>> {
+        let option = options::Entity::find_by_id(key.to_string())
+            .one(&self.connection)
+            .await
+            .context(format!("failed to get option {}", key))?;
+
+        Ok(option.map(|option| option.value))
+    }
+
+    async fn set_option(&self, key: &str, value: &str) -> Result<()> {
+        let insert_item = options::ActiveModel {
+            key: Set(key.to_string()),
+            value: Set(value.to_string()),
+        };
+
+        options::Entity::insert(insert_item)
+            .on_conflict(
+                OnConflict::column(options::Column::Key)
+                    .update_column(options::Column::Value)
+                    .to_owned(),
+            )
+            .exec(&self.connection)
+            .await
+            .context(format!("failed to set option {}", key))?;
+
+        Ok(())
+    }
+
+    async fn delete_option(&self, key: &str) -> Result<()> {
+        options::Entity::delete_by_id(key.to_string())
+            .exec(&self.connection)
+            .await
+            .context(format!("failed to delete option {}", key))?;
+
+        Ok(())
+    }
+
+    // rules set by /schedule, none if it follows env
+    pub async fn get_schedule_rules(&self) -> Result<Option<String>> {
+        self.get_option(SCHEDULE_KEY).await
+    }
+
+    pub async fn set_schedule_rules(&self, rules: &str) -> Result<()> {
+        self.set_option(SCHEDULE_KEY, rules).await
+    }
+
+    pub async fn delete_schedule_rules(&self) -> Result<()> {
+        self.delete_option(SCHEDULE_KEY).await
+    }
+
+    // set by /pause all and cleared by /resume all
+    pub async fn get_queue_paused(&self) -> Result<bool> {
+        Ok(self.get_option(QUEUE_PAUSED_KEY).await?.is_some())
+    }
+
+    pub async fn set_queue_paused(&self, paused: bool) -> Result<()> {
+        if paused {
+            self.set_option(QUEUE_PAUSED_KEY, "true").await
+        } else {
+            self.delete_option(QUEUE_PAUSED_KEY).await
+        }
+    }
+
+    // global limits set by /limit in bytes per second, none if it follows env
+    pub async fn get_download_limit(&self) -> Result<Option<u64>> {
+        self.get_rate_option(DOWNLOAD_LIMIT_KEY).await
+    }
+
+    pub async fn set_download_limit(&self, rate: u64) -> Result<()> {
+        self.set_option(DOWNLOAD_LIMIT_KEY, &rate.to_string()).await
+    }
+
+    pub async fn get_upload_limit(&self) -> Result<Option<u64>> {
+        self.get_rate_option(UPLOAD_LIMIT_KEY).await
+    }
+
+    pub async fn set_upload_limit(&self, rate: u64) -> Result<()> {
+        self.set_option(UPLOAD_LIMIT_KEY, &rate.to_string()).await
+    }
+
+    async fn get_rate_option(&self, key: &str) -> Result<Option<u64>> {
+        self.get_option(key)
+            .await?
+            .map(|rate| rate.parse::<u64>())
+            .transpose()
+            .context(format!("invalid option {}", key))
+    }
+}
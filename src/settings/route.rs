@@ -0,0 +1,92 @@
+/*
+:project: telegram-onedrive
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use anyhow::{anyhow, Result};
+use sea_orm::{
+    sea_query::{ArrayType, ValueType, ValueTypeErr},
+    ColIdx, ColumnType, DbErr, QueryResult, TryGetError, TryGetable, Value,
+};
+use std::fmt::Display;
+
+pub const FILE_TYPES: [&str; 4] = ["image", "video", "audio", "document"];
+
+// what a routing rule matches, rules of a sender go before rules of a chat, then rules of a file type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RouteKind {
+    Sender,
+    Chat,
+    Type,
+}
+
+impl RouteKind {
+    pub fn parse(kind: &str) -> Result<Self> {
+        match kind {
+            "sender" => Ok(Self::Sender),
+            "chat" => Ok(Self::Chat),
+            "type" => Ok(Self::Type),
+            _ => Err(anyhow!(
+                "route kind should be one of sender, chat and type: {}",
+                kind
+            )),
+        }
+    }
+}
+
+impl ValueType for RouteKind {
+    fn try_from(v: Value) -> Result<Self, ValueTypeErr> {
+        match v {
+            Value::String(Some(value)) => Self::parse(&value).map_err(|_| ValueTypeErr),
+            _ => Err(ValueTypeErr),
+        }
+    }
+
+    fn type_name() -> String {
+        "RouteKind".to_string()
+    }
+
+    fn array_type() -> ArrayType {
+        ArrayType::String
+    }
+
+    fn column_type() -> ColumnType {
+        ColumnType::String(None)
+    }
+}
+
+impl From<RouteKind> for Value {
+    fn from(value: RouteKind) -> Self {
+        Self::String(Some(Box::new(value.to_string())))
+    }
+}
+
+impl TryGetable for RouteKind {
+    fn try_get_by<I: ColIdx>(res: &QueryResult, index: I) -> Result<Self, TryGetError> {
+        let value: String = res.try_get_by(index)?;
+
+        Self::parse(&value).map_err(|e| TryGetError::DbErr(DbErr::Type(e.to_string())))
+    }
+}
+
+impl Display for RouteKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Sender => write!(f, "sender"),
+            Self::Chat => write!(f, "chat"),
+            Self::Type => write!(f, "type"),
+        }
+    }
+}
+
+// one of FILE_TYPES, guessed from the extension
+pub fn get_file_type(filename: &str) -> &'static str {
+    match mime_guess::from_path(filename).first() {
+        Some(mime) if mime.type_() == mime_guess::mime::IMAGE => "image",
+        Some(mime) if mime.type_() == mime_guess::mime::VIDEO => "video",
+        Some(mime) if mime.type_() == mime_guess::mime::AUDIO => "audio",
+        _ => "document",
+    }
+}
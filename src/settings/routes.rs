@@ -0,0 +1,30 @@
+/*
+:project: telegram-onedrive
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use super::RouteKind;
+use sea_orm::{
+    entity::prelude::DeriveEntityModel, ActiveModelBehavior, DerivePrimaryKey, DeriveRelation,
+    EntityTrait, EnumIter, PrimaryKeyTrait,
+};
+
+// the onedrive account that new tasks matching the rule are uploaded to
+#[derive(Clone, Debug, DeriveEntityModel)]
+#[sea_orm(table_name = "routes")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub kind: RouteKind,
+    // chat id, sender id or file type
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub value: String,
+    // onedrive username
+    pub account: String,
+}
+
+#[derive(Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,35 @@
+/*
+:project: telegram-onedrive
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use sea_orm::{
+    entity::prelude::DeriveEntityModel, ActiveModelBehavior, DerivePrimaryKey, DeriveRelation,
+    EntityTrait, EnumIter, PrimaryKeyTrait,
+};
+
+// new media in the watched chat is transferred and reported to the group that created the watch
+#[derive(Clone, Debug, DeriveEntityModel)]
+#[sea_orm(table_name = "watches")]
+pub struct Model {
+    // id of the watched chat
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub chat_id: i64,
+    // id of the group that created the watch
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub group_id: i64,
+    pub chat_name: String,
+    // chat hex of the group used by bot
+    pub group_bot_hex: String,
+    // like type:video min:10, empty for every media
+    pub filters: String,
+    // none for the current directory
+    pub dir: Option<String>,
+}
+
+#[derive(Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
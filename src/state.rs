@@ -9,9 +9,15 @@ use crate::{
     client::{OneDriveClient, TelegramClient},
     env::ENV,
     error::ResultExt,
-    tasker::TaskSession,
+    history::HistorySession,
+    settings::SettingsSession,
+    tasker::{BandwidthLimiter, Scheduler, TaskSession},
 };
-use std::sync::{atomic::AtomicBool, Arc};
+use std::{
+    collections::HashSet,
+    sync::{atomic::AtomicBool, Arc},
+};
+use tokio::sync::Mutex;
 
 pub struct State {
     pub telegram_bot: TelegramClient,
@@ -19,6 +25,12 @@ pub struct State {
     pub onedrive: OneDriveClient,
     pub should_auto_delete: AtomicBool,
     pub task_session: TaskSession,
+    pub bandwidth_limiter: BandwidthLimiter,
+    pub scheduler: Scheduler,
+    pub settings: SettingsSession,
+    pub history: HistorySession,
+    // chats that have a watch, so that other messages the user receives are dropped at once
+    pub watched_chat_ids: Mutex<HashSet<i64>>,
 }
 
 impl State {
@@ -32,6 +44,27 @@ impl State {
         let task_session = TaskSession::new(&env.tasker_session_path)
             .await
             .unwrap_or_trace();
+        let settings = SettingsSession::new(&env.settings_session_path)
+            .await
+            .unwrap_or_trace();
+        let bandwidth_limiter = BandwidthLimiter::new(
+            BandwidthLimiter::load_global_limits(&settings)
+                .await
+                .unwrap_or_trace(),
+            settings.get_chat_bandwidth_limits().await.unwrap_or_trace(),
+        );
+        let scheduler = Scheduler::new(Scheduler::load_schedule(&settings).await.unwrap_or_trace());
+        task_session.set_queue_paused(settings.get_queue_paused().await.unwrap_or_trace());
+        let history = HistorySession::new(&env.history_session_path)
+            .await
+            .unwrap_or_trace();
+        let watched_chat_ids = settings
+            .get_watches()
+            .await
+            .unwrap_or_trace()
+            .into_iter()
+            .map(|watch| watch.chat_id)
+            .collect();
 
         Self {
             telegram_bot,
@@ -39,6 +72,11 @@ impl State {
             onedrive,
             should_auto_delete,
             task_session,
+            bandwidth_limiter,
+            scheduler,
+            settings,
+            history,
+            watched_chat_ids: Mutex::new(watched_chat_ids),
         }
     }
 }
@@ -0,0 +1,485 @@
+/*
+:project: telegram-onedrive
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use super::{
+    limit::{ChatLimiter, Direction},
+    retry::RetryPolicy,
+};
+use crate::{client::TelegramClient, env::ENV, error::TaskAbortError};
+use anyhow::{anyhow, Context, Result};
+use grammers_client::{client::files::MAX_CHUNK_SIZE, types::Media};
+use reqwest::{header, Response, StatusCode};
+use std::{collections::VecDeque, ops::Range, sync::Arc};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+// each telegram worker downloads this many chunks in a row with a single download iterator
+const TG_SEGMENT_CHUNKS_NUM: i32 = 8;
+
+pub enum Downloader {
+    Url(UrlDownloader),
+    Telegram(TgDownloader),
+}
+
+impl Downloader {
+    // parts are at most part_size long, and only the last one can be shorter
+    pub async fn next_part(&mut self, part_size: usize) -> Result<Option<Vec<u8>>> {
+        match self {
+            Self::Url(downloader) => downloader.next_part(part_size).await,
+            Self::Telegram(downloader) => downloader.next_part(part_size).await,
+        }
+    }
+}
+
+pub struct UrlDownloader {
+    retry_policy: RetryPolicy,
+    limiter: ChatLimiter,
+    source: Source,
+}
+
+enum Source {
+    // the server supports range requests, parts are fetched independently
+    Ranges {
+        http_client: reqwest::Client,
+        url: String,
+        next_start: u64,
+        total_length: u64,
+        // parts being downloaded, in the order they should be uploaded
+        workers: VecDeque<JoinHandle<Result<Vec<u8>>>>,
+        worker_num: usize,
+    },
+    // a single response read from the beginning
+    Stream {
+        response: Response,
+        pending_buffer: Vec<u8>,
+    },
+}
+
+impl UrlDownloader {
+    pub async fn new(
+        http_client: &reqwest::Client,
+        url: &str,
+        current_length: u64,
+        total_length: Option<u64>,
+        retry_policy: RetryPolicy,
+        limiter: ChatLimiter,
+    ) -> Result<Self> {
+        // parts can only be split by range if the total length is known
+        let range_total_length = match total_length {
+            Some(total_length) if supports_range(http_client, url).await => Some(total_length),
+            _ => None,
+        };
+
+        let source = if let Some(total_length) = range_total_length {
+            tracing::debug!("url supports range requests: {}", url);
+
+            let prefetch_num = ENV.get().unwrap().url_prefetch_num;
+
+            Source::Ranges {
+                http_client: http_client.clone(),
+                url: url.to_string(),
+                next_start: current_length,
+                total_length,
+                workers: VecDeque::new(),
+                worker_num: prefetch_num + 1,
+            }
+        } else {
+            tracing::debug!("url is downloaded as a stream: {}", url);
+
+            let mut response = http_client
+                .get(url)
+                .send()
+                .await
+                .context("failed to send request for /url")?;
+
+            // the file is downloaded from the beginning when resuming,
+            // drop the bytes that onedrive has already received
+            let mut pending_buffer = Vec::new();
+            let mut skipped_length = current_length;
+
+            while skipped_length > 0 {
+                let chunk = response
+                    .chunk()
+                    .await
+                    .context("failed to get chunk")?
+                    .ok_or_else(|| anyhow!("url stream ended before the resuming offset"))?;
+
+                if chunk.len() as u64 <= skipped_length {
+                    skipped_length -= chunk.len() as u64;
+                } else {
+                    pending_buffer.extend_from_slice(&chunk[skipped_length as usize..]);
+                    skipped_length = 0;
+                }
+            }
+
+            Source::Stream {
+                response,
+                pending_buffer,
+            }
+        };
+
+        Ok(Self {
+            retry_policy,
+            limiter,
+            source,
+        })
+    }
+
+    pub async fn next_part(&mut self, part_size: usize) -> Result<Option<Vec<u8>>> {
+        let retry_policy = self.retry_policy;
+        let limiter = &self.limiter;
+
+        match &mut self.source {
+            Source::Ranges {
+                http_client,
+                url,
+                next_start,
+                total_length,
+                workers,
+                worker_num,
+            } => {
+                while workers.len() < *worker_num && *next_start < *total_length {
+                    let range = Range {
+                        start: *next_start,
+                        end: (*next_start + part_size as u64).min(*total_length),
+                    };
+
+                    *next_start = range.end;
+
+                    let http_client = http_client.clone();
+                    let url = url.clone();
+                    let limiter = limiter.clone();
+
+                    workers.push_back(tokio::spawn(async move {
+                        download_range(&http_client, &url, range, retry_policy, &limiter).await
+                    }));
+                }
+
+                match workers.pop_front() {
+                    Some(handle) => {
+                        let buffer = handle.await.context("failed to join handle")??;
+
+                        tracing::debug!("downloaded range from url");
+
+                        Ok(Some(buffer))
+                    }
+                    None => Ok(None),
+                }
+            }
+            Source::Stream {
+                response,
+                pending_buffer,
+            } => {
+                let mut buffer = std::mem::take(pending_buffer);
+                buffer.reserve(part_size);
+
+                while buffer.len() < part_size {
+                    match response.chunk().await.context("failed to get chunk")? {
+                        Some(chunk) => {
+                            limiter.acquire(Direction::Download, chunk.len()).await;
+
+                            buffer.extend_from_slice(&chunk);
+                        }
+                        None => break,
+                    }
+                }
+
+                // keep parts aligned, the rest goes to the next part
+                if buffer.len() > part_size {
+                    *pending_buffer = buffer.split_off(part_size);
+                }
+
+                tracing::debug!("downloaded chunk from url");
+
+                if buffer.is_empty() {
+                    Ok(None)
+                } else {
+                    Ok(Some(buffer))
+                }
+            }
+        }
+    }
+}
+
+impl Drop for UrlDownloader {
+    fn drop(&mut self) {
+        // stop prefetching if the task is aborted or failed
+        if let Source::Ranges { workers, .. } = &self.source {
+            for handle in workers {
+                handle.abort();
+            }
+        }
+    }
+}
+
+pub struct TgDownloader {
+    telegram_user: TelegramClient,
+    media: Arc<Media>,
+    next_chunk_num: i32,
+    total_chunks_num: i32,
+    // bytes of the first chunk that onedrive has already received when resuming
+    skipped_length: usize,
+    // segments being downloaded, in the order they should be uploaded
+    workers: VecDeque<JoinHandle<Result<Vec<u8>>>>,
+    worker_num: usize,
+    pending_buffer: Vec<u8>,
+    retry_policy: RetryPolicy,
+    limiter: ChatLimiter,
+    cancellation_token: CancellationToken,
+}
+
+impl TgDownloader {
+    pub fn new(
+        telegram_user: &TelegramClient,
+        media: Media,
+        current_length: u64,
+        total_length: u64,
+        retry_policy: RetryPolicy,
+        limiter: ChatLimiter,
+        cancellation_token: CancellationToken,
+    ) -> Self {
+        let total_chunks_num = total_length.div_ceil(MAX_CHUNK_SIZE as u64).max(1) as i32;
+
+        Self {
+            telegram_user: telegram_user.clone(),
+            media: Arc::new(media),
+            // when resuming, start from the chunk containing the offset onedrive expects
+            next_chunk_num: (current_length / MAX_CHUNK_SIZE as u64) as i32,
+            total_chunks_num,
+            skipped_length: (current_length % MAX_CHUNK_SIZE as u64) as usize,
+            workers: VecDeque::new(),
+            worker_num: ENV.get().unwrap().tg_download_workers.max(1),
+            pending_buffer: Vec::new(),
+            retry_policy,
+            limiter,
+            cancellation_token,
+        }
+    }
+
+    pub async fn next_part(&mut self, part_size: usize) -> Result<Option<Vec<u8>>> {
+        while self.pending_buffer.len() < part_size {
+            self.spawn_workers();
+
+            let Some(handle) = self.workers.pop_front() else {
+                break;
+            };
+
+            let mut segment = handle.await.context("failed to join handle")??;
+
+            if self.skipped_length > 0 {
+                segment.drain(..self.skipped_length.min(segment.len()));
+                self.skipped_length = 0;
+            }
+
+            self.pending_buffer.append(&mut segment);
+        }
+
+        // keep downloading while the part is uploaded
+        self.spawn_workers();
+
+        tracing::debug!("downloaded chunk from telegram");
+
+        if self.pending_buffer.is_empty() {
+            return Ok(None);
+        }
+
+        // the rest goes to the next part
+        let rest = if self.pending_buffer.len() > part_size {
+            self.pending_buffer.split_off(part_size)
+        } else {
+            Vec::new()
+        };
+
+        Ok(Some(std::mem::replace(&mut self.pending_buffer, rest)))
+    }
+
+    fn spawn_workers(&mut self) {
+        while self.workers.len() < self.worker_num && self.next_chunk_num < self.total_chunks_num {
+            let chunks = Range {
+                start: self.next_chunk_num,
+                end: (self.next_chunk_num + TG_SEGMENT_CHUNKS_NUM).min(self.total_chunks_num),
+            };
+
+            self.next_chunk_num = chunks.end;
+
+            let telegram_user = self.telegram_user.clone();
+            let media = self.media.clone();
+            let retry_policy = self.retry_policy;
+            let limiter = self.limiter.clone();
+            let cancellation_token = self.cancellation_token.clone();
+
+            self.workers.push_back(tokio::spawn(async move {
+                tokio::select! {
+                    result = download_segment(&telegram_user, &media, chunks, retry_policy, &limiter) => result,
+                    () = cancellation_token.cancelled() => Err(TaskAbortError.into())
+                }
+            }));
+        }
+    }
+}
+
+impl Drop for TgDownloader {
+    fn drop(&mut self) {
+        for handle in &self.workers {
+            handle.abort();
+        }
+    }
+}
+
+async fn download_segment(
+    telegram_user: &TelegramClient,
+    media: &Media,
+    chunks: Range<i32>,
+    retry_policy: RetryPolicy,
+    limiter: &ChatLimiter,
+) -> Result<Vec<u8>> {
+    let mut download = telegram_user.iter_download(media).skip_chunks(chunks.start);
+
+    let mut segment = Vec::new();
+    let mut chunk_num = chunks.start;
+
+    let mut retries = 0;
+
+    while chunk_num < chunks.end {
+        match download.next().await {
+            Ok(Some(chunk)) => {
+                limiter.acquire(Direction::Download, chunk.len()).await;
+
+                segment.extend(chunk);
+                chunk_num += 1;
+            }
+            Ok(None) => {
+                return Err(anyhow!(
+                    "tg file ended at chunk {} before chunk {}",
+                    chunk_num,
+                    chunks.end
+                ))
+            }
+            Err(e) => {
+                if retries < retry_policy.max_retries {
+                    retries += 1;
+
+                    retry_policy.wait(retries, None).await;
+
+                    // continue from the chunk that failed with a new iterator
+                    download = telegram_user.iter_download(media).skip_chunks(chunk_num);
+
+                    continue;
+                }
+
+                return Err(e).context("failed to get next chunk from tg file downloader");
+            }
+        }
+    }
+
+    Ok(segment)
+}
+
+async fn supports_range(http_client: &reqwest::Client, url: &str) -> bool {
+    let Ok(response) = http_client.head(url).send().await else {
+        return false;
+    };
+
+    response
+        .headers()
+        .get(header::ACCEPT_RANGES)
+        .and_then(|accept_ranges| accept_ranges.to_str().ok())
+        .is_some_and(|accept_ranges| accept_ranges.trim().eq_ignore_ascii_case("bytes"))
+}
+
+async fn download_range(
+    http_client: &reqwest::Client,
+    url: &str,
+    range: Range<u64>,
+    retry_policy: RetryPolicy,
+    limiter: &ChatLimiter,
+) -> Result<Vec<u8>> {
+    let length = (range.end - range.start) as usize;
+
+    let mut buffer = Vec::with_capacity(length);
+
+    let mut retries = 0;
+
+    loop {
+        // continue from the received bytes if the connection was dropped
+        let start = range.start + buffer.len() as u64;
+
+        match read_range(http_client, url, start..range.end, &mut buffer, limiter).await {
+            Ok(()) if buffer.len() >= length => {
+                buffer.truncate(length);
+
+                break;
+            }
+            Ok(()) => {
+                tracing::debug!(
+                    "range {}-{} interrupted at {}",
+                    range.start,
+                    range.end,
+                    range.start + buffer.len() as u64
+                );
+            }
+            Err(e) => {
+                if retries >= retry_policy.max_retries {
+                    return Err(e).context(format!(
+                        "failed to download range {}-{}",
+                        range.start, range.end
+                    ));
+                }
+
+                tracing::debug!("failed to download range, retry: {:?}", e);
+            }
+        }
+
+        if retries >= retry_policy.max_retries {
+            return Err(anyhow!(
+                "failed to download range {}-{}: received {} bytes",
+                range.start,
+                range.end,
+                buffer.len()
+            ));
+        }
+
+        retries += 1;
+
+        retry_policy.wait(retries, None).await;
+    }
+
+    Ok(buffer)
+}
+
+async fn read_range(
+    http_client: &reqwest::Client,
+    url: &str,
+    range: Range<u64>,
+    buffer: &mut Vec<u8>,
+    limiter: &ChatLimiter,
+) -> Result<()> {
+    let mut response = http_client
+        .get(url)
+        .header(
+            header::RANGE,
+            format!("bytes={}-{}", range.start, range.end - 1),
+        )
+        .send()
+        .await
+        .context("failed to send range request for /url")?;
+
+    if response.status() != StatusCode::PARTIAL_CONTENT {
+        return Err(anyhow!(
+            "range request responded with status code {}",
+            response.status()
+        ));
+    }
+
+    while let Some(chunk) = response.chunk().await.context("failed to get chunk")? {
+        limiter.acquire(Direction::Download, chunk.len()).await;
+
+        buffer.extend_from_slice(&chunk);
+    }
+
+    Ok(())
+}
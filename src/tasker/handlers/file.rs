@@ -7,7 +7,8 @@
 
 use super::{tasks, transfer::multi_parts_uploader_from_tg_file, Progress};
 use crate::{error::TaskAbortError, state::AppState};
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use onedrive_api::resource::DriveItem;
 use std::sync::Arc;
 use tokio_util::sync::CancellationToken;
 
@@ -16,21 +17,38 @@ pub async fn handler(
     progress: Arc<Progress>,
     cancellation_token: CancellationToken,
     state: AppState,
-) -> Result<()> {
-    let filename =
-        match multi_parts_uploader_from_tg_file(&task, progress.clone(), cancellation_token, state)
-            .await
-        {
-            Ok(filename) => filename,
-            Err(e) => {
-                if e.downcast_ref::<TaskAbortError>().is_some() {
-                    return Ok(());
-                }
-                return Err(e);
+) -> Result<Option<DriveItem>> {
+    let drive_item = match multi_parts_uploader_from_tg_file(
+        &task,
+        progress.clone(),
+        cancellation_token,
+        state.clone(),
+    )
+    .await
+    {
+        Ok(drive_item) => drive_item,
+        Err(e) => {
+            if e.downcast_ref::<TaskAbortError>().is_some() {
+                return Ok(None);
             }
-        };
+            return Err(e);
+        }
+    };
 
-    progress.update_filename(task.id, &filename).await?;
+    // so that the same media can be found as duplicate later
+    if let Some(media_key) = &task.media_key {
+        state
+            .task_session
+            .set_uploaded_media(media_key, &drive_item, task.account.as_deref())
+            .await?;
+    }
 
-    Ok(())
+    let filename = drive_item
+        .name
+        .as_ref()
+        .ok_or_else(|| anyhow!("drive item name not found"))?;
+
+    progress.update_filename(task.id, filename).await?;
+
+    Ok(Some(drive_item))
 }
@@ -0,0 +1,315 @@
+/*
+:project: telegram-onedrive
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use super::{
+    download::UrlDownloader,
+    limit::{ChatLimiter, Direction},
+    retry::RetryPolicy,
+    tasks, Progress,
+};
+use crate::{
+    client::utils::chat_from_hex, env::SESSION_DIR, state::AppState, utils::get_http_client,
+};
+use anyhow::{anyhow, Context, Error, Result};
+use async_zip::{tokio::write::ZipFileWriter, Compression, ZipEntryBuilder};
+use futures::AsyncWriteExt as _;
+use grammers_client::{types::media::Uploaded, InputMessage};
+use onedrive_api::resource::DriveItem;
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+use tokio::{
+    fs::{self, File},
+    io::{AsyncReadExt, AsyncWriteExt},
+};
+
+// bots can't send files larger than 2GB,
+// a multiple of the read chunk size so that every part starts at a chunk boundary
+pub const TG_PART_SIZE: u64 = 2000 * 1024 * 1024;
+const READ_CHUNK_SIZE: usize = 4 * 1024 * 1024;
+// bytes buffered between the onedrive download and the telegram upload
+const PIPE_BUFFER_SIZE: usize = 1024 * 1024;
+// zip files are named like get_{id}.zip in the session dir
+pub const ZIP_FILE_PREFIX: &str = "get_";
+
+pub async fn handler(
+    task: tasks::Model,
+    progress: Arc<Progress>,
+    state: AppState,
+) -> Result<Option<DriveItem>> {
+    let onedrive = &state.onedrive;
+
+    let drive_item = onedrive
+        .get_item_from_path(&task.root_path, &task.filename, task.account.as_deref())
+        .await?
+        .ok_or_else(|| anyhow!("file not found on OneDrive"))?;
+
+    let total_length = task
+        .total_length
+        .ok_or_else(|| anyhow!("total length of /get task is none"))? as u64;
+
+    // the download url expires soon, so it's fetched for every attempt
+    let download_url = onedrive
+        .get_download_url_from_path(&task.root_path, &task.filename, task.account.as_deref())
+        .await?;
+
+    let current_length = get_resume_length(&task);
+
+    let http_client = get_http_client()?;
+    let limiter = state.bandwidth_limiter.for_chat(task.chat_id);
+
+    let downloader = UrlDownloader::new(
+        &http_client,
+        &download_url,
+        current_length,
+        Some(total_length),
+        RetryPolicy::new(),
+        limiter.clone(),
+    )
+    .await?;
+
+    if task.zip {
+        send_zip(&task, downloader, progress, &state).await?;
+    } else {
+        send_parts(
+            &task,
+            downloader,
+            current_length,
+            total_length,
+            &limiter,
+            progress,
+            &state,
+        )
+        .await?;
+    }
+
+    tracing::info!(
+        "sent file from onedrive: {} size: {}",
+        task.filename,
+        total_length
+    );
+
+    Ok(Some(drive_item))
+}
+
+// parts sent before are kept, a split zip is built again from the beginning
+pub fn get_resume_length(task: &tasks::Model) -> u64 {
+    if task.zip {
+        0
+    } else {
+        task.current_length as u64 / TG_PART_SIZE * TG_PART_SIZE
+    }
+}
+
+async fn send_parts(
+    task: &tasks::Model,
+    mut downloader: UrlDownloader,
+    mut current_length: u64,
+    total_length: u64,
+    limiter: &ChatLimiter,
+    progress: Arc<Progress>,
+    state: &AppState,
+) -> Result<()> {
+    let parts_num = total_length.div_ceil(TG_PART_SIZE);
+    let in_flight_length = progress.get_in_flight_length(task.id)?;
+
+    progress.set_current_length(task.id, current_length).await?;
+
+    while current_length < total_length {
+        let part_index = current_length / TG_PART_SIZE;
+        let part_length = TG_PART_SIZE.min(total_length - current_length);
+
+        let uploaded = pipe_part(
+            &mut downloader,
+            part_length,
+            format_part_name(&task.filename, part_index, parts_num),
+            limiter,
+            &in_flight_length,
+            state,
+        )
+        .await?;
+
+        send_uploaded(task, uploaded, state).await?;
+
+        current_length += part_length;
+        progress.set_current_length(task.id, current_length).await?;
+
+        tracing::debug!("sent part {} of {}", part_index + 1, task.filename);
+    }
+
+    Ok(())
+}
+
+// the part is uploaded while it's downloaded, without being held in memory or on disk
+async fn pipe_part(
+    downloader: &mut UrlDownloader,
+    part_length: u64,
+    name: String,
+    limiter: &ChatLimiter,
+    in_flight_length: &AtomicU64,
+    state: &AppState,
+) -> Result<Uploaded> {
+    let (mut writer, mut reader) = tokio::io::duplex(PIPE_BUFFER_SIZE);
+
+    let feed = async {
+        let mut fed_length = 0;
+
+        while fed_length < part_length {
+            let chunk = downloader
+                .next_part(READ_CHUNK_SIZE)
+                .await?
+                .ok_or_else(|| anyhow!("download ended before the whole part was read"))?;
+
+            limiter.acquire(Direction::Upload, chunk.len()).await;
+
+            writer
+                .write_all(&chunk)
+                .await
+                .context("failed to write part to pipe")?;
+
+            fed_length += chunk.len() as u64;
+            in_flight_length.fetch_add(chunk.len() as u64, Ordering::AcqRel);
+        }
+
+        Ok::<(), Error>(())
+    };
+
+    let upload = state
+        .telegram_bot
+        .upload_stream(&mut reader, part_length as usize, name);
+
+    let ((), uploaded) = tokio::try_join!(feed, upload)?;
+
+    Ok(uploaded)
+}
+
+async fn send_zip(
+    task: &tasks::Model,
+    downloader: UrlDownloader,
+    progress: Arc<Progress>,
+    state: &AppState,
+) -> Result<()> {
+    let zip_file = ZipFile::new(task.id);
+
+    write_zip(task, downloader, &zip_file.path, &progress).await?;
+
+    send_volumes(task, &zip_file.path, state).await?;
+
+    let total_length = task.total_length.unwrap_or_default() as u64;
+    progress.set_current_length(task.id, total_length).await
+}
+
+// removed once sent, failed or cancelled, the ones left by a crash are removed when tasks are resumed
+struct ZipFile {
+    path: String,
+}
+
+impl ZipFile {
+    fn new(id: i64) -> Self {
+        Self {
+            path: format!("{}/{}{}.zip", SESSION_DIR, ZIP_FILE_PREFIX, id),
+        }
+    }
+}
+
+impl Drop for ZipFile {
+    fn drop(&mut self) {
+        std::fs::remove_file(&self.path).ok();
+    }
+}
+
+async fn write_zip(
+    task: &tasks::Model,
+    mut downloader: UrlDownloader,
+    zip_path: &str,
+    progress: &Progress,
+) -> Result<()> {
+    let in_flight_length = progress.get_in_flight_length(task.id)?;
+
+    let mut file = File::create(zip_path)
+        .await
+        .context("failed to create zip file")?;
+    let mut writer = ZipFileWriter::with_tokio(&mut file);
+
+    let builder = ZipEntryBuilder::new(task.filename.clone().into(), Compression::Stored);
+    let mut entry_writer = writer.write_entry_stream(builder).await?;
+
+    while let Some(chunk) = downloader.next_part(READ_CHUNK_SIZE).await? {
+        entry_writer.write_all(&chunk).await?;
+
+        in_flight_length.fetch_add(chunk.len() as u64, Ordering::AcqRel);
+    }
+
+    entry_writer
+        .close()
+        .await
+        .context("failed to close entry")?;
+
+    writer.close().await.context("failed to close zip file")?;
+    file.shutdown().await.context("failed to shutdown file")?;
+
+    Ok(())
+}
+
+// volumes can be joined by cat or opened by 7-Zip
+async fn send_volumes(task: &tasks::Model, zip_path: &str, state: &AppState) -> Result<()> {
+    let zip_length = fs::metadata(zip_path)
+        .await
+        .context("failed to get zip file metadata")?
+        .len();
+    let volumes_num = zip_length.div_ceil(TG_PART_SIZE);
+
+    let zip_name = format!("{}.zip", task.filename);
+
+    let mut file = File::open(zip_path)
+        .await
+        .context("failed to open zip file")?;
+
+    for volume_index in 0..volumes_num {
+        let volume_length = TG_PART_SIZE.min(zip_length - volume_index * TG_PART_SIZE);
+
+        let mut reader = (&mut file).take(volume_length);
+
+        let uploaded = state
+            .telegram_bot
+            .upload_stream(
+                &mut reader,
+                volume_length as usize,
+                format_part_name(&zip_name, volume_index, volumes_num),
+            )
+            .await?;
+
+        send_uploaded(task, uploaded, state).await?;
+    }
+
+    Ok(())
+}
+
+async fn send_uploaded(task: &tasks::Model, uploaded: Uploaded, state: &AppState) -> Result<()> {
+    let chat_bot = chat_from_hex(&task.chat_bot_hex)?;
+
+    state
+        .telegram_bot
+        .reply_message(
+            chat_bot,
+            task.message_indicator_id,
+            InputMessage::default().file(uploaded),
+        )
+        .await?;
+
+    Ok(())
+}
+
+// name.001, name.002, ... if the file is split
+fn format_part_name(name: &str, index: u64, parts_num: u64) -> String {
+    if parts_num > 1 {
+        format!("{}.{:03}", name, index + 1)
+    } else {
+        name.to_string()
+    }
+}
@@ -6,6 +6,7 @@
 */
 
 pub mod file;
+pub mod get;
 pub mod url;
 
-use super::{tasks, transfer, Progress};
+use super::{download, limit, retry, tasks, transfer, Progress};
@@ -6,13 +6,24 @@
 */
 
 use super::{tasks, transfer::multi_parts_uploader_from_url, Progress};
-use anyhow::Result;
+use crate::state::AppState;
+use anyhow::{anyhow, Result};
+use onedrive_api::resource::DriveItem;
 use std::sync::Arc;
 
-pub async fn handler(task: tasks::Model, progress: Arc<Progress>) -> Result<()> {
-    let filename = multi_parts_uploader_from_url(&task, progress.clone()).await?;
+pub async fn handler(
+    task: tasks::Model,
+    progress: Arc<Progress>,
+    state: AppState,
+) -> Result<Option<DriveItem>> {
+    let drive_item = multi_parts_uploader_from_url(&task, progress.clone(), state).await?;
 
-    progress.update_filename(task.id, &filename).await?;
+    let filename = drive_item
+        .name
+        .as_ref()
+        .ok_or_else(|| anyhow!("drive item name not found"))?;
 
-    Ok(())
+    progress.update_filename(task.id, filename).await?;
+
+    Ok(Some(drive_item))
 }
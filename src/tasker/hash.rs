@@ -0,0 +1,175 @@
+/*
+:project: telegram-onedrive
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+// according to https://learn.microsoft.com/en-us/onedrive/developer/code-snippets/quickxorhash
+const WIDTH_IN_BITS: usize = 160;
+const SHIFT: usize = 11;
+const CELLS_NUM: usize = WIDTH_IN_BITS.div_ceil(64);
+const LAST_CELL_BITS: usize = WIDTH_IN_BITS - (CELLS_NUM - 1) * 64;
+
+// quickXorHash computed part by part while uploading,
+// provided by both personal and business drives
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct QuickXorHash {
+    data: [u64; CELLS_NUM],
+    shift_so_far: usize,
+    length_so_far: u64,
+}
+
+impl QuickXorHash {
+    pub const fn new() -> Self {
+        Self {
+            data: [0; CELLS_NUM],
+            shift_so_far: 0,
+            length_so_far: 0,
+        }
+    }
+
+    pub const fn length(&self) -> u64 {
+        self.length_so_far
+    }
+
+    pub fn update(&mut self, bytes: &[u8]) {
+        let mut cell_index = self.shift_so_far / 64;
+        let mut cell_offset = self.shift_so_far % 64;
+
+        // bytes 160 apart are xored into the same position
+        for i in 0..bytes.len().min(WIDTH_IN_BITS) {
+            let is_last_cell = cell_index == CELLS_NUM - 1;
+            let cell_bits = if is_last_cell { LAST_CELL_BITS } else { 64 };
+
+            let xored_byte = bytes
+                .iter()
+                .skip(i)
+                .step_by(WIDTH_IN_BITS)
+                .fold(0, |xored_byte, byte| xored_byte ^ byte);
+
+            self.data[cell_index] ^= u64::from(xored_byte) << cell_offset;
+
+            // the byte crosses the boundary of the cell
+            if cell_offset > cell_bits - 8 {
+                let next_cell_index = if is_last_cell { 0 } else { cell_index + 1 };
+
+                self.data[next_cell_index] ^= u64::from(xored_byte) >> (cell_bits - cell_offset);
+            }
+
+            cell_offset += SHIFT;
+
+            if cell_offset >= cell_bits {
+                cell_index = if is_last_cell { 0 } else { cell_index + 1 };
+                cell_offset -= cell_bits;
+            }
+        }
+
+        self.shift_so_far =
+            (self.shift_so_far + SHIFT * (bytes.len() % WIDTH_IN_BITS)) % WIDTH_IN_BITS;
+        self.length_so_far += bytes.len() as u64;
+    }
+
+    pub fn finalize(&self) -> String {
+        let mut digest = self
+            .data
+            .iter()
+            .flat_map(|cell| cell.to_le_bytes())
+            .take(WIDTH_IN_BITS / 8)
+            .collect::<Vec<u8>>();
+
+        // the length is xored into the last 8 bytes
+        let offset = digest.len() - 8;
+        for (i, byte) in self.length_so_far.to_le_bytes().iter().enumerate() {
+            digest[offset + i] ^= byte;
+        }
+
+        STANDARD.encode(digest)
+    }
+
+    // saved with the task so that the hash can go on after resuming
+    pub fn to_state(&self) -> String {
+        format!(
+            "{}:{}:{}",
+            self.data
+                .iter()
+                .map(|cell| format!("{:016x}", cell))
+                .collect::<String>(),
+            self.shift_so_far,
+            self.length_so_far
+        )
+    }
+
+    pub fn from_state(state: &str) -> Option<Self> {
+        let mut parts = state.split(':');
+
+        let data_hex = parts.next()?;
+        let shift_so_far = parts.next()?.parse().ok()?;
+        let length_so_far = parts.next()?.parse().ok()?;
+
+        if data_hex.len() != CELLS_NUM * 16 || shift_so_far >= WIDTH_IN_BITS {
+            return None;
+        }
+
+        let mut data = [0; CELLS_NUM];
+        for (i, cell) in data.iter_mut().enumerate() {
+            *cell = u64::from_str_radix(data_hex.get(i * 16..(i + 1) * 16)?, 16).ok()?;
+        }
+
+        Some(Self {
+            data,
+            shift_so_far,
+            length_so_far,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::QuickXorHash;
+
+    #[test]
+    fn test_quick_xor_hash_by_parts() {
+        let bytes = (0..1000_u32)
+            .map(|i| (i * 31 % 251) as u8)
+            .collect::<Vec<u8>>();
+
+        let mut hash = QuickXorHash::new();
+        hash.update(&bytes);
+
+        let mut hash_by_parts = QuickXorHash::new();
+        for part in bytes.chunks(97) {
+            // resume from the saved state for each part
+            hash_by_parts = QuickXorHash::from_state(&hash_by_parts.to_state()).unwrap();
+            hash_by_parts.update(part);
+        }
+
+        assert_eq!(hash, hash_by_parts);
+        assert_eq!(hash.finalize(), hash_by_parts.finalize());
+        assert_eq!(
+            QuickXorHash::new().finalize(),
+            "AAAAAAAAAAAAAAAAAAAAAAAAAAA="
+        );
+    }
+
+    // expected values are computed by the C# reference implementation of Microsoft
+    #[test]
+    fn test_quick_xor_hash_known_answers() {
+        let hash = |bytes: &[u8]| {
+            let mut hash = QuickXorHash::new();
+            hash.update(bytes);
+            hash.finalize()
+        };
+
+        assert_eq!(hash(b"J"), "SgAAAAAAAAAAAAAAAQAAAAAAAAA=");
+        assert_eq!(hash(b"Hello, World!"), "SCgDG9jwBhaA4ApvnQMbyBACAAA=");
+
+        // longer than the width, so bytes are folded into the same cells
+        let bytes = (0..1000_u32)
+            .map(|i| (i * 31 % 251) as u8)
+            .collect::<Vec<u8>>();
+        assert_eq!(hash(&bytes), "egLp3hU8LkfrSX0d1jXwNKIbDmw=");
+    }
+}
@@ -0,0 +1,309 @@
+/*
+:project: telegram-onedrive
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use crate::{
+    env::ENV,
+    settings::{ChatBandwidthLimit, SettingsSession},
+};
+use anyhow::Result;
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::sync::Mutex;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Download,
+    Upload,
+}
+
+impl Display for Direction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Download => write!(f, "download"),
+            Self::Upload => write!(f, "upload"),
+        }
+    }
+}
+
+// bytes per second, 0 means unlimited
+#[derive(Clone, Copy, Default)]
+pub struct Limits {
+    pub download: u64,
+    pub upload: u64,
+}
+
+impl Limits {
+    pub const fn get(&self, direction: Direction) -> u64 {
+        match direction {
+            Direction::Download => self.download,
+            Direction::Upload => self.upload,
+        }
+    }
+
+    fn set(&mut self, direction: Direction, rate: u64) {
+        match direction {
+            Direction::Download => self.download = rate,
+            Direction::Upload => self.upload = rate,
+        }
+    }
+
+    pub const fn is_unlimited(&self) -> bool {
+        self.download == 0 && self.upload == 0
+    }
+}
+
+struct TokenBucket {
+    // negative if more bytes were taken than refilled, the taker waits until it's paid back
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new() -> Self {
+        Self {
+            tokens: 0.,
+            last_refill: Instant::now(),
+        }
+    }
+
+    // returns how long to wait before the bytes can be transferred
+    fn take(&mut self, rate: u64, length: usize) -> Duration {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.last_refill = now;
+
+        let rate = rate as f64;
+
+        // allow a burst of at most one second
+        self.tokens = (self.tokens + elapsed * rate).min(rate);
+        self.tokens -= length as f64;
+
+        if self.tokens >= 0. {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / rate)
+        }
+    }
+}
+
+struct Budget {
+    limits: Limits,
+    download: TokenBucket,
+    upload: TokenBucket,
+}
+
+impl Budget {
+    fn new(limits: Limits) -> Self {
+        Self {
+            limits,
+            download: TokenBucket::new(),
+            upload: TokenBucket::new(),
+        }
+    }
+
+    fn take(&mut self, direction: Direction, length: usize) -> Duration {
+        let rate = self.limits.get(direction);
+
+        if rate == 0 {
+            return Duration::ZERO;
+        }
+
+        match direction {
+            Direction::Download => self.download.take(rate, length),
+            Direction::Upload => self.upload.take(rate, length),
+        }
+    }
+}
+
+// shared by all transfers, a transfer is limited by both the global budget and the budget of its chat
+#[derive(Clone)]
+pub struct BandwidthLimiter {
+    global: Arc<Mutex<Budget>>,
+    // chat id -> budget
+    chats: Arc<Mutex<HashMap<i64, Budget>>>,
+}
+
+impl BandwidthLimiter {
+    // limits are the ones set before restart
+    pub fn new(global_limits: Limits, chat_bandwidth_limits: Vec<ChatBandwidthLimit>) -> Self {
+        let chats = chat_bandwidth_limits
+            .into_iter()
+            .map(|chat_bandwidth_limit| {
+                let limits = Limits {
+                    download: chat_bandwidth_limit.download as u64,
+                    upload: chat_bandwidth_limit.upload as u64,
+                };
+
+                (chat_bandwidth_limit.chat_id, Budget::new(limits))
+            })
+            .collect();
+
+        Self {
+            global: Arc::new(Mutex::new(Budget::new(global_limits))),
+            chats: Arc::new(Mutex::new(chats)),
+        }
+    }
+
+    // the ones set by /limit go first, then env
+    pub async fn load_global_limits(settings: &SettingsSession) -> Result<Limits> {
+        let env = ENV.get().unwrap();
+
+        Ok(Limits {
+            download: settings
+                .get_download_limit()
+                .await?
+                .unwrap_or_else(|| env.download_limit.saturating_mul(1024)),
+            upload: settings
+                .get_upload_limit()
+                .await?
+                .unwrap_or_else(|| env.upload_limit.saturating_mul(1024)),
+        })
+    }
+
+    pub async fn get_global_limits(&self) -> Limits {
+        self.global.lock().await.limits
+    }
+
+    pub async fn set_global_limit(&self, direction: Direction, rate: u64) {
+        self.global.lock().await.limits.set(direction, rate);
+    }
+
+    pub async fn get_chat_limits(&self, chat_id: i64) -> Limits {
+        self.chats
+            .lock()
+            .await
+            .get(&chat_id)
+            .map(|budget| budget.limits)
+            .unwrap_or_default()
+    }
+
+    pub async fn set_chat_limit(&self, chat_id: i64, direction: Direction, rate: u64) {
+        let mut chats = self.chats.lock().await;
+
+        let budget = chats
+            .entry(chat_id)
+            .or_insert_with(|| Budget::new(Limits::default()));
+        budget.limits.set(direction, rate);
+
+        if budget.limits.is_unlimited() {
+            chats.remove(&chat_id);
+        }
+    }
+
+    // the stricter one of the global and the chat limits
+    pub async fn get_effective_limits(&self, chat_id: i64) -> Limits {
+        let global_limits = self.get_global_limits().await;
+        let chat_limits = self.get_chat_limits(chat_id).await;
+
+        let stricter = |global_rate: u64, chat_rate: u64| match (global_rate, chat_rate) {
+            (0, rate) | (rate, 0) => rate,
+            (global_rate, chat_rate) => global_rate.min(chat_rate),
+        };
+
+        Limits {
+            download: stricter(global_limits.download, chat_limits.download),
+            upload: stricter(global_limits.upload, chat_limits.upload),
+        }
+    }
+
+    pub fn for_chat(&self, chat_id: i64) -> ChatLimiter {
+        ChatLimiter {
+            bandwidth_limiter: self.clone(),
+            chat_id,
+        }
+    }
+
+    // wait until the bytes can be transferred
+    pub async fn acquire(&self, chat_id: i64, direction: Direction, length: usize) {
+        let global_delay = self.global.lock().await.take(direction, length);
+
+        let chat_delay = self
+            .chats
+            .lock()
+            .await
+            .get_mut(&chat_id)
+            .map_or(Duration::ZERO, |budget| budget.take(direction, length));
+
+        let delay = global_delay.max(chat_delay);
+
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+// the limiter used by the transfers of a task
+#[derive(Clone)]
+pub struct ChatLimiter {
+    bandwidth_limiter: BandwidthLimiter,
+    chat_id: i64,
+}
+
+impl ChatLimiter {
+    pub async fn acquire(&self, direction: Direction, length: usize) {
+        self.bandwidth_limiter
+            .acquire(self.chat_id, direction, length)
+            .await;
+    }
+}
+
+pub fn format_rate(rate: u64) -> String {
+    if rate == 0 {
+        "unlimited".to_string()
+    } else {
+        format!("{}KB/s", rate / 1024)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Budget, Direction, Limits, TokenBucket};
+    use std::time::Duration;
+
+    #[test]
+    fn test_token_bucket_refill() {
+        let mut bucket = TokenBucket::new();
+
+        // an empty bucket waits for the bytes to be refilled
+        let delay = bucket.take(1000, 500);
+        assert!(delay > Duration::from_millis(450) && delay <= Duration::from_millis(500));
+
+        // paid back after the delay
+        bucket.last_refill -= Duration::from_millis(500);
+        assert_eq!(bucket.take(1000, 0), Duration::ZERO);
+
+        bucket.last_refill -= Duration::from_millis(200);
+        assert_eq!(bucket.take(1000, 100), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_token_bucket_burst() {
+        let mut bucket = TokenBucket::new();
+
+        // an idle bucket is filled up to one second of bytes only
+        bucket.last_refill -= Duration::from_secs(10);
+        assert_eq!(bucket.take(1000, 1000), Duration::ZERO);
+
+        let delay = bucket.take(1000, 1000);
+        assert!(delay > Duration::from_millis(950) && delay <= Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_budget_unlimited() {
+        let mut budget = Budget::new(Limits {
+            download: 0,
+            upload: 1000,
+        });
+
+        assert_eq!(budget.take(Direction::Download, 1_000_000), Duration::ZERO);
+        assert!(budget.take(Direction::Upload, 1000) > Duration::ZERO);
+    }
+}
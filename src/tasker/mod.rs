@@ -5,29 +5,54 @@
 :license: MIT, see LICENSE for more details.
 */
 
+mod download;
 mod handlers;
+mod hash;
+mod limit;
+mod part;
 mod progress;
+mod progress_messages;
+mod quota_watcher;
+mod resume;
+mod retry;
+mod schedule;
 mod session;
 mod tasks;
 mod transfer;
+mod uploaded_media;
 
 use crate::{
-    client::utils::chat_from_hex,
+    client::{onedrive::manage::ShareLinkOptions, utils::chat_from_hex},
     env::ENV,
-    error::{ErrorExt, ResultExt, ResultUnwrapExt},
+    error::{ErrorExt, ResultExt, ResultUnwrapExt, TaskSkipError},
+    history::{Outcome, Record},
     message::TelegramMessage,
     state::AppState,
+    utils::{get_current_timestamp, get_http_client},
 };
 use anyhow::{Context, Result};
+use chrono::Utc;
 use grammers_client::InputMessage;
+pub use limit::{format_rate, BandwidthLimiter, Direction};
+use onedrive_api::{resource::DriveItem, UploadSession};
 use path_slash::PathBufExt;
 use progress::Progress;
+use quota_watcher::QuotaWatcher;
+pub use resume::{resume_paused_task, retry_failed_task};
+pub use schedule::{Schedule, Scheduler};
 pub use session::{BatchAborter, TaskAborter, TaskSession};
-use std::{path::Path, sync::Arc, time::Duration};
-pub use tasks::{CmdType, InsertTask};
+use std::{
+    path::Path,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+pub use tasks::{CmdType, InsertTask, TaskStatus};
 use tokio::sync::Semaphore;
 use tokio_util::sync::CancellationToken;
 
+// appended to the indicator when a task fails, and removed when it's retried
+const FAILED_SUFFIX: &str = "\n\nFailed.";
+
 pub struct Tasker {
     state: AppState,
     progress: Arc<Progress>,
@@ -47,11 +72,16 @@ impl Tasker {
     pub async fn run(&self) {
         tracing::info!("tasker started");
 
+        resume::resume_tasks(&self.state).await.trace();
+
         let progress_clone = self.progress.clone();
         tokio::spawn(async move {
             progress_clone.run().await;
         });
 
+        let quota_watcher = QuotaWatcher::new(self.state.clone());
+        tokio::spawn(quota_watcher.run());
+
         let handler_num = ENV.get().unwrap().task_handler_num;
 
         let semaphore = Arc::new(Semaphore::new(handler_num as usize));
@@ -64,8 +94,15 @@ impl Tasker {
     }
 
     async fn handle_tasks(&self, semaphore: Arc<Semaphore>) -> Result<()> {
+        // only fetch a task when a handler is free, so that tasks queued later can still be bumped ahead
+        let permit = semaphore
+            .acquire_owned()
+            .await
+            .context("failed to acquire semaphore for task handler")?;
+
         let mut aborters = self.state.task_session.task_aborters.lock().await;
-        let task = self.session().fetch_task().await?;
+        let in_schedule = self.state.scheduler.is_open().await;
+        let task = self.session().fetch_task(in_schedule).await?;
 
         if let Some(task) = task {
             let chat = chat_from_hex(&task.chat_bot_hex)?;
@@ -87,7 +124,6 @@ impl Tasker {
                 return Ok(());
             };
 
-            let semaphore_clone = semaphore.clone();
             let state_clone = self.state.clone();
             let progress_clone = self.progress.clone();
 
@@ -99,21 +135,19 @@ impl Tasker {
                 &task.filename,
             );
             let cancellation_token = aborter.token.clone();
+            let pause_token = aborter.pause_token.clone();
             aborters.insert((chat.id, task.message_indicator_id), aborter);
             drop(aborters);
 
             tokio::spawn(async move {
-                let _permit = semaphore_clone
-                    .acquire()
-                    .await
-                    .context("failed to acquire semaphore for task handler")
-                    .unwrap_or_trace();
+                let _permit = permit;
 
                 if let Err(e) = handler_dispatch(
                     task,
                     message.clone(),
                     progress_clone,
                     cancellation_token,
+                    pause_token,
                     state_clone,
                 )
                 .await
@@ -132,6 +166,7 @@ async fn handler_dispatch(
     message: TelegramMessage,
     progress: Arc<Progress>,
     cancellation_token: CancellationToken,
+    pause_token: CancellationToken,
     state: AppState,
 ) -> Result<()> {
     let session = &state.task_session;
@@ -142,12 +177,19 @@ async fn handler_dispatch(
         .set_task_status(task.id, tasks::TaskStatus::Started)
         .await?;
 
+    let started_at = Instant::now();
+
     let fut = async {
         match task.cmd_type {
             CmdType::Url => {
                 tracing::info!("handle url task");
 
-                handlers::url::handler(task.clone(), progress).await
+                handlers::url::handler(task.clone(), progress, state.clone()).await
+            }
+            CmdType::Get => {
+                tracing::info!("handle get task");
+
+                handlers::get::handler(task.clone(), progress, state.clone()).await
             }
             CmdType::File | CmdType::Link => {
                 tracing::info!("handle file or link task");
@@ -164,13 +206,26 @@ async fn handler_dispatch(
     };
 
     let mut aborted = false;
+    let mut paused = false;
+
+    // a pause that arrived while the task was being fetched is applied before anything is transferred
+    let result = if pause_token.is_cancelled() {
+        paused = true;
 
-    let result = tokio::select! {
-        result = fut => result,
-        () = cancellation_token.cancelled() => {
-            aborted = true;
+        Ok(None)
+    } else {
+        tokio::select! {
+            result = fut => result,
+            () = cancellation_token.cancelled() => {
+                aborted = true;
 
-            Ok(())
+                Ok(None)
+            }
+            () = pause_token.cancelled() => {
+                paused = true;
+
+                Ok(None)
+            }
         }
     };
 
@@ -187,12 +242,37 @@ async fn handler_dispatch(
     let batch_is_processing = batch_aborter.is_some_and(|batch_aborter| batch_aborter.processing);
     drop(batch_aborters);
 
-    if aborted {
+    if paused {
+        // the upload session and the length are kept, so that it continues from there when resumed
+        session
+            .set_task_status(task.id, tasks::TaskStatus::Paused)
+            .await?;
+
+        return Ok(());
+    }
+
+    // the file handler returns none if it's aborted while downloading
+    if aborted || matches!(result, Ok(None)) {
+        record_history(&task, Outcome::Cancelled, None, None, started_at, &state)
+            .await
+            .trace();
+
         return Ok(());
     }
 
     match result {
-        Ok(()) => {
+        Ok(drive_item) => {
+            record_history(
+                &task,
+                Outcome::Completed,
+                drive_item.as_ref(),
+                None,
+                started_at,
+                &state,
+            )
+            .await
+            .trace();
+
             session
                 .set_task_status(task.id, tasks::TaskStatus::Completed)
                 .await?;
@@ -202,9 +282,25 @@ async fn handler_dispatch(
                     let chat_bot = chat_from_hex(&task.chat_bot_hex)?;
                     let chat_user = chat_from_hex(&task.chat_user_hex)?;
 
-                    telegram_bot
-                        .delete_messages(chat_bot, &[task.message_indicator_id])
-                        .await?;
+                    if session
+                        .is_last_indicator_task(chat_id, task.message_indicator_id)
+                        .await?
+                    {
+                        telegram_bot
+                            .delete_messages(chat_bot, &[task.message_indicator_id])
+                            .await?;
+                    }
+
+                    // items of an album other than the first are deleted once they are done
+                    if let Some(message_origin_id) =
+                        task.message_origin_id.filter(|message_origin_id| {
+                            task.cmd_type == CmdType::File && *message_origin_id != task.message_id
+                        })
+                    {
+                        telegram_user
+                            .delete_messages(chat_user, &[message_origin_id])
+                            .await?;
+                    }
 
                     if state
                         .task_session
@@ -221,11 +317,38 @@ async fn handler_dispatch(
                             .await?;
                     }
                 } else {
-                    handle_completed_task(task.clone(), state.clone()).await?;
+                    handle_completed_task(task.clone(), drive_item.as_ref(), state.clone()).await?;
                 }
             }
         }
+        Err(e) if e.downcast_ref::<TaskSkipError>().is_some() => {
+            record_history(&task, Outcome::Skipped, None, None, started_at, &state)
+                .await
+                .trace();
+
+            session
+                .set_task_status(task.id, tasks::TaskStatus::Completed)
+                .await?;
+
+            handle_skipped_task(task.clone(), state.clone()).await?;
+        }
         Err(e) => {
+            record_history(
+                &task,
+                Outcome::Failed,
+                None,
+                Some(format!("{:#}", e)),
+                started_at,
+                &state,
+            )
+            .await
+            .trace();
+
+            // keep the failed task with its error, so that it can be retried by /retry
+            session
+                .set_last_error(task.id, Some(format!("{:#}", e)))
+                .await?;
+
             e.send(message.clone()).await.unwrap_both().trace();
 
             session
@@ -233,6 +356,8 @@ async fn handler_dispatch(
                 .await?;
 
             handle_failed_task(task.clone(), state.clone()).await?;
+
+            return Ok(());
         }
     }
 
@@ -241,7 +366,83 @@ async fn handler_dispatch(
     Ok(())
 }
 
-async fn handle_completed_task(task: tasks::Model, state: AppState) -> Result<()> {
+async fn record_history(
+    task: &tasks::Model,
+    outcome: Outcome,
+    drive_item: Option<&DriveItem>,
+    error: Option<String>,
+    started_at: Instant,
+    state: &AppState,
+) -> Result<()> {
+    // lengths are updated while transferring, the task may be deleted already if it's aborted
+    let latest_task = state.task_session.get_task(task.id).await.ok();
+    let current_length = latest_task
+        .as_ref()
+        .map_or(task.current_length, |latest_task| {
+            latest_task.current_length
+        });
+    let total_length = latest_task
+        .as_ref()
+        .and_then(|latest_task| latest_task.total_length)
+        .or(task.total_length);
+
+    // the file may be renamed by onedrive
+    let filename = drive_item
+        .and_then(|drive_item| drive_item.name.clone())
+        .unwrap_or_else(|| task.filename.clone());
+    let path = Path::new(&task.root_path)
+        .join(&filename)
+        .to_slash_lossy()
+        .to_string();
+
+    let origin_chat_id = match &task.chat_origin_hex {
+        Some(chat_origin_hex) => Some(chat_from_hex(chat_origin_hex)?.id),
+        None => None,
+    };
+
+    let duration = started_at.elapsed();
+    // a resumed task only transfers the rest of the file
+    let transferred_length = (current_length - task.current_length).max(0);
+    let average_speed = (transferred_length as f64 / duration.as_secs_f64().max(0.001)) as i64;
+
+    let account = match &task.account {
+        Some(account) => Some(account.clone()),
+        None => state.onedrive.get_current_username().await?,
+    };
+
+    state
+        .history
+        .insert_record(Record {
+            id: 0,
+            cmd_type: task.cmd_type.clone(),
+            filename,
+            path,
+            item_id: drive_item
+                .and_then(|drive_item| drive_item.id.as_ref().map(|id| id.as_str().to_string())),
+            url: task.url.clone(),
+            chat_id: task.chat_id,
+            message_id: task.message_id,
+            origin_chat_id,
+            origin_message_id: task.message_origin_id,
+            account,
+            size: total_length.unwrap_or(current_length),
+            duration: duration.as_millis() as i64,
+            average_speed,
+            outcome,
+            error,
+            finished_at: get_current_timestamp(),
+        })
+        .await
+}
+
+async fn handle_completed_task(
+    task: tasks::Model,
+    drive_item: Option<&DriveItem>,
+    state: AppState,
+) -> Result<()> {
+    // total length is only known after the upload if the source didn't tell the size
+    let task = state.task_session.get_task(task.id).await?;
+
     let chat_bot = chat_from_hex(&task.chat_bot_hex)?;
 
     let file_path_raw = Path::new(&task.root_path).join(task.filename);
@@ -253,17 +454,105 @@ async fn handle_completed_task(task: tasks::Model, state: AppState) -> Result<()
         .get_message(chat_bot, task.message_indicator_id)
         .await?;
 
-    let response = format!(
-        "{}\n\nDone.\nFile uploaded to {}\nSize {:.2}MB.",
+    let action = if task.cmd_type == CmdType::Get {
+        "sent from"
+    } else {
+        "uploaded to"
+    };
+
+    let mut response = format!(
+        "{}\n\nDone.\nFile {} {}\nSize {:.2}MB.",
         message_indicator.text(),
+        action,
         file_path,
-        task.total_length as f64 / 1024.0 / 1024.0
+        task.total_length.unwrap_or(task.current_length) as f64 / 1024.0 / 1024.0
+    );
+
+    if task.verified {
+        response.push_str("\nVerified by quickXorHash.");
+    }
+
+    // only uploaded files are shared
+    let item_id = drive_item
+        .filter(|_| task.cmd_type != CmdType::Get)
+        .and_then(|drive_item| drive_item.id.as_ref());
+
+    if let Some(item_id) = item_id {
+        // the file is uploaded anyway, so a failed link is only reported
+        match create_share_link(&task, item_id.as_str(), &state).await {
+            Ok(Some(share_link)) => {
+                response.push_str(&format!("\n<a href=\"{}\">Share link</a>", share_link));
+            }
+            Ok(None) => {}
+            Err(e) => response.push_str(&format!("\nFailed to create share link: {:#}", e)),
+        }
+    }
+
+    message_indicator
+        .edit(task.message_indicator_id, InputMessage::html(&response))
+        .await
+        .context(response)?;
+
+    Ok(())
+}
+
+// none if the chat doesn't want share links
+async fn create_share_link(
+    task: &tasks::Model,
+    item_id: &str,
+    state: &AppState,
+) -> Result<Option<String>> {
+    let Some(chat_share_link) = state.settings.get_chat_share_link(task.chat_id).await? else {
+        return Ok(None);
+    };
+
+    let options = ShareLinkOptions {
+        link_type: chat_share_link.link_type,
+        scope: chat_share_link.scope,
+        expiration: chat_share_link
+            .expiration_hours
+            .map(|expiration_hours| Utc::now() + chrono::Duration::hours(expiration_hours)),
+        password: chat_share_link.password,
+    };
+
+    let share_link = state
+        .onedrive
+        .create_share_link_from_id(item_id, task.account.as_deref(), &options)
+        .await?;
+
+    Ok(Some(share_link))
+}
+
+async fn handle_skipped_task(task: tasks::Model, state: AppState) -> Result<()> {
+    // the upload session can't be completed anymore
+    UploadSession::from_upload_url(&task.upload_url)
+        .delete(&get_http_client()?)
+        .await
+        .ok();
+
+    let chat_bot = chat_from_hex(&task.chat_bot_hex)?;
+
+    let file_path_raw = Path::new(&task.root_path).join(&task.filename);
+    let file_path = file_path_raw.to_slash_lossy();
+
+    let telegram_bot = &state.telegram_bot;
+
+    let message_indicator = telegram_bot
+        .get_message(chat_bot, task.message_indicator_id)
+        .await?;
+
+    let response = format!(
+        "{}\n\nSkipped.\nFile already exists at {}",
+        message_indicator.text(),
+        file_path
     );
     message_indicator
         .edit(task.message_indicator_id, InputMessage::html(&response))
         .await
         .context(response)?;
 
+    tracing::info!("skipped existing file: {}", file_path);
+
     Ok(())
 }
 
@@ -276,9 +565,9 @@ async fn handle_failed_task(task: tasks::Model, state: AppState) -> Result<()> {
         .get_message(chat_bot, task.message_indicator_id)
         .await?;
 
-    let response = format!("{}\n\nFailed.", message_indicator.text());
+    let response = format!("{}{}", message_indicator.text(), FAILED_SUFFIX);
     message_indicator
-        .edit(task.message_id, InputMessage::html(&response))
+        .edit(task.message_indicator_id, InputMessage::html(&response))
         .await
         .context(response)?;
 
@@ -0,0 +1,147 @@
+/*
+:project: telegram-onedrive
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use super::download::Downloader;
+use crate::env::ENV;
+use anyhow::Result;
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use tokio::{sync::mpsc, task::JoinHandle};
+
+// onedrive requires the size of each part to be a multiple of 320 KiB, except the last one
+pub const PART_UNIT: usize = 320 * 1024;
+// the fixed part size used before
+const MIN_PART_SIZE: usize = PART_UNIT * 10;
+// onedrive accepts at most 60 MiB in one request
+const MAX_PART_SIZE: usize = PART_UNIT * 192;
+// parts are resized so that uploading one takes about this long
+const TARGET_PART_DURATION: Duration = Duration::from_secs(8);
+
+// picks the size of the next part by the throughput of the last uploaded part,
+// shared between the downloading and the uploading side
+#[derive(Clone)]
+pub struct PartSizer {
+    part_size: Arc<AtomicUsize>,
+}
+
+impl PartSizer {
+    pub fn new() -> Self {
+        Self {
+            part_size: Arc::new(AtomicUsize::new(MIN_PART_SIZE)),
+        }
+    }
+
+    pub fn part_size(&self) -> usize {
+        self.part_size.load(Ordering::Acquire)
+    }
+
+    pub fn update(&self, length: usize, elapsed: Duration) {
+        let part_size = self.part_size();
+
+        let throughput = length as f64 / elapsed.as_secs_f64().max(f64::EPSILON);
+        let target = (throughput * TARGET_PART_DURATION.as_secs_f64()) as usize;
+
+        // a single slow or fast part shouldn't swing the size too much
+        let target = target.clamp(part_size / 2, part_size * 2);
+        let new_part_size = (target / PART_UNIT * PART_UNIT).clamp(MIN_PART_SIZE, MAX_PART_SIZE);
+
+        if new_part_size != part_size {
+            tracing::debug!("part size changed from {} to {}", part_size, new_part_size);
+
+            self.part_size.store(new_part_size, Ordering::Release);
+        }
+    }
+}
+
+// downloads parts in another task, so that the next parts are downloaded while the current one is uploaded
+pub struct PartPipeline {
+    receiver: mpsc::Receiver<Result<Vec<u8>>>,
+    handle: JoinHandle<()>,
+}
+
+impl PartPipeline {
+    pub fn new(mut downloader: Downloader, part_sizer: PartSizer) -> Self {
+        let prefetch_num = ENV.get().unwrap().part_prefetch_num.max(1);
+
+        let (sender, receiver) = mpsc::channel(prefetch_num);
+
+        let handle = tokio::spawn(async move {
+            loop {
+                match downloader.next_part(part_sizer.part_size()).await {
+                    Ok(Some(part)) => {
+                        if sender.send(Ok(part)).await.is_err() {
+                            break;
+                        }
+                    }
+                    Ok(None) => break,
+                    Err(e) => {
+                        sender.send(Err(e)).await.ok();
+
+                        break;
+                    }
+                }
+            }
+        });
+
+        Self { receiver, handle }
+    }
+
+    // none if the source has ended
+    pub async fn next_part(&mut self) -> Result<Option<Vec<u8>>> {
+        self.receiver.recv().await.transpose()
+    }
+}
+
+impl Drop for PartPipeline {
+    fn drop(&mut self) {
+        // stop downloading if the task is aborted or failed
+        self.handle.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PartSizer, MAX_PART_SIZE, MIN_PART_SIZE, PART_UNIT};
+    use std::time::Duration;
+
+    #[test]
+    fn test_part_size_aligned() {
+        let part_sizer = PartSizer::new();
+        assert_eq!(part_sizer.part_size(), MIN_PART_SIZE);
+
+        // 500 KiB/s for 8 seconds is 12.5 times of 320 KiB, rounded down
+        part_sizer.update(500 * 1024, Duration::from_secs(1));
+        assert_eq!(part_sizer.part_size() % PART_UNIT, 0);
+        assert_eq!(part_sizer.part_size(), PART_UNIT * 12);
+
+        // changes at most twice or half at a time
+        part_sizer.update(100 * 1024 * 1024, Duration::from_secs(1));
+        assert_eq!(part_sizer.part_size(), PART_UNIT * 24);
+    }
+
+    #[test]
+    fn test_part_size_clamped() {
+        let part_sizer = PartSizer::new();
+
+        // never below 3.2 MiB however slow it is
+        part_sizer.update(1024, Duration::from_secs(60));
+        assert_eq!(part_sizer.part_size(), MIN_PART_SIZE);
+        assert_eq!(MIN_PART_SIZE, 3_276_800);
+
+        // never above 60 MiB however fast it is
+        for _ in 0..10 {
+            part_sizer.update(MAX_PART_SIZE, Duration::from_millis(1));
+        }
+        assert_eq!(part_sizer.part_size(), MAX_PART_SIZE);
+        assert_eq!(MAX_PART_SIZE, 60 * 1024 * 1024);
+    }
+}
@@ -5,7 +5,12 @@
 :license: MIT, see LICENSE for more details.
 */
 
-use super::{TaskSession, session::ChatHex, tasks};
+use super::{
+    TaskSession,
+    limit::format_rate,
+    session::ChatHex,
+    tasks::{self, TaskStatus},
+};
 use crate::{
     client::utils::chat_from_hex,
     error::{ErrorExt, ResultExt, ResultUnwrapExt},
@@ -13,15 +18,30 @@ use crate::{
 };
 use anyhow::{Context, Result, anyhow};
 use grammers_client::InputMessage;
-use std::{collections::HashMap, time::Duration};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
+    time::{Duration, Instant},
+};
 
 pub struct Progress {
     state: AppState,
+    // task id -> (current length, instant), used to calculate speed of tasks with unknown total length
+    speed_samples: Mutex<HashMap<i64, (u64, Instant)>>,
+    // task id -> bytes of the part being uploaded that have been sent
+    in_flight_lengths: Mutex<HashMap<i64, Arc<AtomicU64>>>,
 }
 
 impl Progress {
-    pub const fn new(state: AppState) -> Self {
-        Self { state }
+    pub fn new(state: AppState) -> Self {
+        Self {
+            state,
+            speed_samples: Mutex::new(HashMap::new()),
+            in_flight_lengths: Mutex::new(HashMap::new()),
+        }
     }
 
     fn session(&self) -> &TaskSession {
@@ -29,13 +49,63 @@ impl Progress {
     }
 
     pub async fn set_current_length(&self, id: i64, current_length: u64) -> Result<()> {
-        self.session().set_current_length(id, current_length).await
+        self.session()
+            .set_current_length(id, current_length)
+            .await?;
+
+        // the part in flight is included in current length now
+        if let Some(in_flight_length) = self.lock_in_flight_lengths()?.get(&id) {
+            in_flight_length.store(0, Ordering::Release);
+        }
+
+        Ok(())
+    }
+
+    pub fn get_in_flight_length(&self, id: i64) -> Result<Arc<AtomicU64>> {
+        Ok(self
+            .lock_in_flight_lengths()?
+            .entry(id)
+            .or_insert_with(|| Arc::new(AtomicU64::new(0)))
+            .clone())
+    }
+
+    fn lock_in_flight_lengths(&self) -> Result<MutexGuard<'_, HashMap<i64, Arc<AtomicU64>>>> {
+        self.in_flight_lengths
+            .lock()
+            .map_err(|_| anyhow!("failed to lock in flight lengths"))
+    }
+
+    pub async fn set_total_length(&self, id: i64, total_length: u64) -> Result<()> {
+        self.session().set_total_length(id, total_length).await
+    }
+
+    pub async fn set_quick_xor_hash_state(
+        &self,
+        id: i64,
+        quick_xor_hash_state: Option<String>,
+    ) -> Result<()> {
+        self.session()
+            .set_quick_xor_hash_state(id, quick_xor_hash_state)
+            .await
+    }
+
+    pub async fn set_verified(&self, id: i64) -> Result<()> {
+        self.session().set_verified(id, true).await
     }
 
     pub async fn run(&self) {
         tracing::info!("progress started");
 
-        let mut chat_progress_message_id = HashMap::new();
+        // reuse progress messages sent before restart
+        let mut chat_progress_message_id = self
+            .session()
+            .get_progress_message_ids()
+            .await
+            .unwrap_or_else(|e| {
+                e.trace();
+
+                HashMap::new()
+            });
         let mut last_progress_response = String::new();
 
         loop {
@@ -57,6 +127,19 @@ impl Progress {
     ) -> Result<()> {
         let chat_tasks = self.session().get_chats_current_tasks().await?;
 
+        let current_task_ids = chat_tasks
+            .values()
+            .flatten()
+            .map(|task| task.id)
+            .collect::<HashSet<_>>();
+        self.speed_samples
+            .lock()
+            .map_err(|_| anyhow!("failed to lock speed samples"))?
+            .retain(|id, _| current_task_ids.contains(id));
+        // the uploader holds the other reference while the task is running
+        self.lock_in_flight_lengths()?
+            .retain(|_, in_flight_length| Arc::strong_count(in_flight_length) > 1);
+
         for (
             ChatHex {
                 chat_bot_hex,
@@ -71,7 +154,11 @@ impl Progress {
 
             let telegram_bot = &self.state.telegram_bot;
 
-            if !current_tasks.is_empty() {
+            // a chat with only paused tasks has nothing in progress
+            if current_tasks
+                .iter()
+                .any(|task| task.status == TaskStatus::Started)
+            {
                 let result = self
                     .sync_chat_progress(
                         &chat_bot_hex,
@@ -122,6 +209,10 @@ impl Progress {
                     }
                 }
 
+                self.session()
+                    .delete_progress_message_id(chat_bot_hex)
+                    .await?;
+
                 tracing::debug!("chat without tasks to be removed: {}", chat.id);
 
                 chat_to_be_removed.push(chat_bot_hex.clone());
@@ -151,14 +242,42 @@ impl Progress {
         let mut response = "Progress:\n".to_string();
 
         for task_progress in current_tasks {
+            let in_flight_length = self
+                .lock_in_flight_lengths()?
+                .get(&task_progress.id)
+                .map_or(0, |in_flight_length| {
+                    in_flight_length.load(Ordering::Acquire)
+                });
+            let transferred_length = task_progress.current_length as u64 + in_flight_length;
+
+            let current_length = transferred_length as f64 / 1024. / 1024.;
+
+            let length = match task_progress.total_length {
+                Some(total_length) => {
+                    format!(
+                        "{:.2}/{:.2}MB",
+                        current_length,
+                        total_length as f64 / 1024. / 1024.
+                    )
+                }
+                None if task_progress.status == TaskStatus::Paused => {
+                    format!("{:.2}MB", current_length)
+                }
+                None => {
+                    let speed = self.get_speed(task_progress.id, transferred_length)?;
+
+                    format!("{:.2}MB, {:.2}MB/s", current_length, speed / 1024. / 1024.)
+                }
+            };
+
             response += &format!(
-                "\n<a href=\"https://t.me/c/{}/{}\">{}</a>: {:.2}/{:.2}MB",
-                chat.id,
-                task_progress.message_id,
-                task_progress.filename,
-                task_progress.current_length as f64 / 1024. / 1024.,
-                task_progress.total_length as f64 / 1024. / 1024.
+                "\n<a href=\"https://t.me/c/{}/{}\">{}</a>: {}",
+                chat.id, task_progress.message_id, task_progress.filename, length
             );
+
+            if task_progress.status == TaskStatus::Paused {
+                response += ", paused";
+            }
         }
 
         let pending_tasks_number = self
@@ -170,6 +289,20 @@ impl Progress {
             response += &format!("\n\n{} more tasks pending...", pending_tasks_number);
         }
 
+        let limits = self
+            .state
+            .bandwidth_limiter
+            .get_effective_limits(chat.id)
+            .await;
+
+        if !limits.is_unlimited() {
+            response += &format!(
+                "\n\nLimit: download {}, upload {}",
+                format_rate(limits.download),
+                format_rate(limits.upload)
+            );
+        }
+
         let progress_message_id = chat_progress_message_id
             .get_mut(chat_bot_hex)
             .ok_or_else(|| anyhow!("chat_bot_hex not in chat_progress_message_id"))?;
@@ -207,6 +340,10 @@ impl Progress {
                         .context(response.clone())?;
 
                     *progress_message_id = message.id();
+
+                    self.session()
+                        .set_progress_message_id(chat_bot_hex, message.id())
+                        .await?;
                 }
             }
         } else {
@@ -216,6 +353,10 @@ impl Progress {
                 .context(response.clone())?;
 
             *progress_message_id = Some(message.id());
+
+            self.session()
+                .set_progress_message_id(chat_bot_hex, message.id())
+                .await?;
         }
 
         *last_progress_response = response;
@@ -223,6 +364,33 @@ impl Progress {
         Ok(())
     }
 
+    // bytes per second since the last sample
+    fn get_speed(&self, id: i64, current_length: u64) -> Result<f64> {
+        let mut speed_samples = self
+            .speed_samples
+            .lock()
+            .map_err(|_| anyhow!("failed to lock speed samples"))?;
+
+        let now = Instant::now();
+
+        let speed = match speed_samples.get(&id) {
+            Some((last_length, last_instant)) => {
+                let elapsed = now.duration_since(*last_instant).as_secs_f64();
+
+                if elapsed > 0. {
+                    current_length.saturating_sub(*last_length) as f64 / elapsed
+                } else {
+                    0.
+                }
+            }
+            None => 0.,
+        };
+
+        speed_samples.insert(id, (current_length, now));
+
+        Ok(speed)
+    }
+
     pub async fn update_filename(&self, id: i64, filename: &str) -> Result<()> {
         self.session().update_filename(id, filename).await
     }
@@ -0,0 +1,25 @@
+/*
+:project: telegram-onedrive
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use sea_orm::{
+    entity::prelude::DeriveEntityModel, ActiveModelBehavior, DerivePrimaryKey, DeriveRelation,
+    EntityTrait, EnumIter, PrimaryKeyTrait,
+};
+
+// progress message of each chat, kept so that it can be reused after restart
+#[derive(Clone, Debug, DeriveEntityModel)]
+#[sea_orm(table_name = "progress_messages")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub chat_bot_hex: String,
+    pub message_id: i32,
+}
+
+#[derive(Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
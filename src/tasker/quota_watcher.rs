@@ -0,0 +1,118 @@
+/*
+:project: telegram-onedrive
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use crate::{client::utils::chat_from_hex, env::ENV, error::ResultExt, state::AppState};
+use anyhow::Result;
+use std::{collections::HashSet, time::Duration};
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+// warns the chats subscribed by /quota alert when a drive is running out of space
+pub struct QuotaWatcher {
+    state: AppState,
+    // accounts that have been warned, warned again only after they recover
+    low_space_alerted: HashSet<String>,
+    queue_alerted: HashSet<String>,
+}
+
+impl QuotaWatcher {
+    pub fn new(state: AppState) -> Self {
+        Self {
+            state,
+            low_space_alerted: HashSet::new(),
+            queue_alerted: HashSet::new(),
+        }
+    }
+
+    pub async fn run(mut self) {
+        loop {
+            self.check().await.trace();
+
+            tokio::time::sleep(CHECK_INTERVAL).await;
+        }
+    }
+
+    async fn check(&mut self) -> Result<()> {
+        let chats = self.state.settings.get_quota_alert_chats().await?;
+
+        if chats.is_empty() {
+            return Ok(());
+        }
+
+        let onedrive = &self.state.onedrive;
+
+        let threshold = ENV.get().unwrap().quota_alert_threshold * 1024 * 1024;
+        let current_username = onedrive.get_current_username().await?;
+        let queued_lengths = self.state.task_session.get_queued_lengths().await?;
+
+        let mut alerts = Vec::new();
+
+        for username in onedrive.get_usernames().await? {
+            let quota = match onedrive.get_quota(Some(&username)).await {
+                Ok(Some(quota)) => quota,
+                Ok(None) => continue,
+                Err(e) => {
+                    tracing::warn!("failed to get quota of {}: {:#}", username, e);
+
+                    continue;
+                }
+            };
+
+            if quota.remaining < threshold {
+                if self.low_space_alerted.insert(username.clone()) {
+                    alerts.push(format!(
+                        "{} has only {:.2}GB left.",
+                        username,
+                        quota.remaining as f64 / 1024.0 / 1024.0 / 1024.0
+                    ));
+                }
+            } else {
+                self.low_space_alerted.remove(&username);
+            }
+
+            // tasks without an account go to the current one
+            let mut queued_length = queued_lengths
+                .get(&Some(username.clone()))
+                .copied()
+                .unwrap_or_default();
+            if current_username.as_ref() == Some(&username) {
+                queued_length += queued_lengths.get(&None).copied().unwrap_or_default();
+            }
+
+            if queued_length > quota.remaining {
+                if self.queue_alerted.insert(username.clone()) {
+                    alerts.push(format!(
+                        "Queued tasks of {} need {:.2}GB but only {:.2}GB is left.",
+                        username,
+                        queued_length as f64 / 1024.0 / 1024.0 / 1024.0,
+                        quota.remaining as f64 / 1024.0 / 1024.0 / 1024.0
+                    ));
+                }
+            } else {
+                self.queue_alerted.remove(&username);
+            }
+        }
+
+        if alerts.is_empty() {
+            return Ok(());
+        }
+
+        let response = format!("OneDrive is running out of space.\n\n{}", alerts.join("\n"));
+
+        for chat_bot_hex in chats {
+            let chat = chat_from_hex(&chat_bot_hex)?;
+
+            self.state
+                .telegram_bot
+                .send_message(chat, response.as_str())
+                .await
+                .trace();
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,321 @@
+/*
+:project: telegram-onedrive
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use super::{
+    handlers::get::{self, ZIP_FILE_PREFIX},
+    hash::QuickXorHash,
+    retry::RetryPolicy,
+    tasks::{self, CmdType, TaskStatus},
+    transfer::TAIL_FILE_PREFIX,
+    FAILED_SUFFIX,
+};
+use crate::{
+    client::{onedrive::item::get_quick_xor_hash, utils::chat_from_hex},
+    env::SESSION_DIR,
+    error::{ErrorExt, ResultExt, ResultUnwrapExt},
+    state::AppState,
+    utils::get_http_client,
+};
+use anyhow::{anyhow, Context, Result};
+use grammers_client::InputMessage;
+use onedrive_api::UploadSession;
+use reqwest::StatusCode;
+use tokio::fs;
+
+// temporary files of transfers in the session dir, named by these prefixes
+const TEMP_FILE_PREFIXES: [&str; 2] = [TAIL_FILE_PREFIX, ZIP_FILE_PREFIX];
+
+// tasks interrupted by the last shutdown are put back to the queue,
+// and continue from where onedrive stopped receiving
+pub async fn resume_tasks(state: &AppState) -> Result<()> {
+    let session = &state.task_session;
+
+    // nothing is transferring yet, so temporary files are left by the last run
+    remove_temp_files().await.trace();
+
+    // failed tasks are kept for /retry
+    session.delete_completed_tasks().await?;
+
+    let tasks = session.get_unfinished_tasks().await?;
+
+    if !tasks.is_empty() {
+        tracing::info!("resume {} unfinished tasks", tasks.len());
+    }
+
+    for task in tasks {
+        if let Err(e) = resume_task(&task, state).await {
+            let chat_bot = chat_from_hex(&task.chat_bot_hex)?;
+
+            e.context(format!("failed to resume task {}", task.filename))
+                .send_chat(&state.telegram_bot, chat_bot)
+                .await
+                .unwrap_both()
+                .trace();
+
+            session.delete_task(task.id).await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn remove_temp_files() -> Result<()> {
+    let mut entries = fs::read_dir(SESSION_DIR)
+        .await
+        .context("failed to read session dir")?;
+
+    while let Some(entry) = entries
+        .next_entry()
+        .await
+        .context("failed to visit session dir entry")?
+    {
+        let filename = entry.file_name();
+
+        if TEMP_FILE_PREFIXES
+            .iter()
+            .any(|prefix| filename.to_string_lossy().starts_with(prefix))
+        {
+            tracing::debug!("remove temporary file {}", filename.to_string_lossy());
+
+            fs::remove_file(entry.path())
+                .await
+                .context("failed to remove temporary file")?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn resume_task(task: &tasks::Model, state: &AppState) -> Result<()> {
+    let session = &state.task_session;
+
+    let chat_bot = chat_from_hex(&task.chat_bot_hex)?;
+
+    // the indicator was deleted while the bot was offline, so the task was cancelled
+    if state
+        .telegram_bot
+        .get_message(chat_bot, task.message_indicator_id)
+        .await
+        .is_err()
+    {
+        tracing::info!("task {} was cancelled before resuming", task.filename);
+
+        UploadSession::from_upload_url(&task.upload_url)
+            .delete(&get_http_client()?)
+            .await
+            .ok();
+
+        session.delete_task(task.id).await?;
+
+        return Ok(());
+    }
+
+    let current_length = sync_upload_session(task, state).await?;
+
+    session.set_current_length(task.id, current_length).await?;
+    session
+        .set_task_status(task.id, TaskStatus::Waiting)
+        .await?;
+
+    tracing::info!("task {} resumed from {}", task.filename, current_length);
+
+    Ok(())
+}
+
+// put a failed task back to the queue, continuing from its upload session if it's still available
+pub async fn retry_failed_task(task: &tasks::Model, state: &AppState) -> Result<()> {
+    let session = &state.task_session;
+
+    let current_length = sync_upload_session(task, state).await?;
+
+    // remove the failed mark before the task may be completed again
+    let chat_bot = chat_from_hex(&task.chat_bot_hex)?;
+
+    let message_indicator = state
+        .telegram_bot
+        .get_message(chat_bot, task.message_indicator_id)
+        .await?;
+
+    if let Some(response) = message_indicator.text().strip_suffix(FAILED_SUFFIX) {
+        message_indicator
+            .edit(task.message_indicator_id, InputMessage::html(response))
+            .await
+            .context(response.to_string())?;
+    }
+
+    // the message is auto deleted once the other tasks of it are done, the indicator stands for it then
+    if state
+        .telegram_bot
+        .get_message(chat_bot, task.message_id)
+        .await
+        .is_err()
+    {
+        session
+            .set_message_id(task.id, task.message_indicator_id)
+            .await?;
+    }
+
+    session.set_current_length(task.id, current_length).await?;
+    session.set_last_error(task.id, None).await?;
+    session
+        .set_task_status(task.id, TaskStatus::Waiting)
+        .await?;
+
+    tracing::info!("task {} retried from {}", task.filename, current_length);
+
+    Ok(())
+}
+
+// put a paused task back to the queue, continuing from the next range onedrive expects,
+// returns false if it's not paused anymore
+pub async fn resume_paused_task(task: &tasks::Model, state: &AppState) -> Result<bool> {
+    let session = &state.task_session;
+
+    let current_length = sync_upload_session(task, state).await?;
+
+    session.set_current_length(task.id, current_length).await?;
+
+    // the task may be cancelled or resumed by someone else meanwhile
+    let resumed = session
+        .switch_task_status(task.id, TaskStatus::Paused, TaskStatus::Waiting)
+        .await?;
+
+    if resumed {
+        tracing::info!("task {} resumed from {}", task.filename, current_length);
+    }
+
+    Ok(resumed)
+}
+
+// get the offset onedrive expects next, and recreate the upload session if it has expired,
+// the total length is returned if the file was uploaded before the upload session was gone
+pub async fn sync_upload_session(task: &tasks::Model, state: &AppState) -> Result<u64> {
+    // nothing is uploaded to onedrive by /get, it goes on from the last part sent to telegram
+    if task.cmd_type == CmdType::Get {
+        return Ok(get::get_resume_length(task));
+    }
+
+    let http_client = get_http_client()?;
+
+    let upload_session = UploadSession::from_upload_url(&task.upload_url);
+
+    let retry_policy = RetryPolicy::new();
+    let mut retries = 0;
+
+    loop {
+        match upload_session.get_meta(&http_client).await {
+            Ok(upload_session_meta) => {
+                let current_length = upload_session_meta
+                    .next_expected_ranges
+                    .first()
+                    .map_or(task.current_length as u64, |range| range.start);
+
+                return Ok(current_length);
+            }
+            // the upload session has expired, or it's gone because the file was completed
+            Err(e) if e.status_code() == Some(StatusCode::NOT_FOUND) => {
+                tracing::info!(
+                    "upload session of task {} is not available: {}",
+                    task.filename,
+                    e
+                );
+
+                break;
+            }
+            Err(e) => {
+                if retries < retry_policy.max_retries {
+                    retries += 1;
+
+                    retry_policy.wait(retries, None).await;
+
+                    continue;
+                }
+
+                return Err(e).context("failed to get upload session");
+            }
+        }
+    }
+
+    if is_uploaded(task, state).await? {
+        tracing::info!("task {} was uploaded before", task.filename);
+
+        return Ok(task.total_length.unwrap_or_default() as u64);
+    }
+
+    tracing::info!("recreate upload session of task {}", task.filename);
+
+    // the drive may have filled up while the task was waiting
+    if let (Some(total_length), Ok(Some(quota))) = (
+        task.total_length,
+        state.onedrive.get_quota(task.account.as_deref()).await,
+    ) {
+        if quota.remaining < total_length as u64 {
+            return Err(anyhow!(
+                "OneDrive account is full, {:.2}MB left but {:.2}MB is needed",
+                quota.remaining as f64 / 1024.0 / 1024.0,
+                total_length as f64 / 1024.0 / 1024.0
+            ));
+        }
+    }
+
+    let (upload_session, upload_session_meta) = state
+        .onedrive
+        .multipart_upload_session_builder(
+            &task.root_path,
+            &task.filename,
+            task.conflict_policy,
+            task.account.as_deref(),
+        )
+        .await?;
+
+    state
+        .task_session
+        .set_upload_url(task.id, upload_session.upload_url())
+        .await?;
+
+    let current_length = upload_session_meta
+        .next_expected_ranges
+        .first()
+        .map_or(0, |range| range.start);
+
+    Ok(current_length)
+}
+
+// the upload had started and the file at the target path matches it,
+// checked by the hash too if it covers the whole file
+async fn is_uploaded(task: &tasks::Model, state: &AppState) -> Result<bool> {
+    let Some(total_length) = task.total_length else {
+        return Ok(false);
+    };
+
+    if task.current_length == 0 {
+        return Ok(false);
+    }
+
+    let Some(drive_item) = state
+        .onedrive
+        .get_item_from_path(&task.root_path, &task.filename, task.account.as_deref())
+        .await?
+    else {
+        return Ok(false);
+    };
+
+    if drive_item.file.is_none() || drive_item.size != Some(total_length) {
+        return Ok(false);
+    }
+
+    let quick_xor_hash = task
+        .quick_xor_hash_state
+        .as_deref()
+        .and_then(QuickXorHash::from_state)
+        .filter(|quick_xor_hash| quick_xor_hash.length() == total_length as u64)
+        .map(|quick_xor_hash| quick_xor_hash.finalize());
+
+    Ok(quick_xor_hash.is_none_or(|quick_xor_hash| {
+        get_quick_xor_hash(&drive_item).is_none_or(|expected| expected == quick_xor_hash)
+    }))
+}
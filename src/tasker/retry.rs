@@ -0,0 +1,70 @@
+/*
+:project: telegram-onedrive
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use crate::env::ENV;
+use rand::Rng;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use std::time::Duration;
+
+// exponential backoff with jitter, used by every transfer of a task
+#[derive(Clone, Copy)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    base_delay: Duration,
+    max_delay: Duration,
+}
+
+impl RetryPolicy {
+    pub fn new() -> Self {
+        let env = ENV.get().unwrap();
+
+        Self {
+            max_retries: env.max_retries,
+            base_delay: Duration::from_secs(env.retry_base_delay),
+            max_delay: Duration::from_secs(env.retry_max_delay),
+        }
+    }
+
+    // retries starts from 1
+    pub fn delay(&self, retries: u32, retry_after: Option<Duration>) -> Duration {
+        // the server knows better when it will be available again
+        if let Some(retry_after) = retry_after {
+            return retry_after;
+        }
+
+        let delay = self
+            .base_delay
+            .saturating_mul(2_u32.saturating_pow(retries.saturating_sub(1)))
+            .min(self.max_delay);
+
+        // pick a random delay between half and the whole of it,
+        // so that parallel workers don't retry at the same time
+        let millis = delay.as_millis() as u64;
+
+        Duration::from_millis(rand::thread_rng().gen_range(millis / 2..=millis))
+    }
+
+    pub async fn wait(&self, retries: u32, retry_after: Option<Duration>) {
+        let delay = self.delay(retries, retry_after);
+
+        tracing::debug!("retry {} after {:?}", retries, delay);
+
+        tokio::time::sleep(delay).await;
+    }
+}
+
+// graph responds with Retry-After in seconds when throttled (429) or unavailable (503)
+pub fn get_retry_after(headers: &HeaderMap) -> Option<Duration> {
+    headers
+        .get(RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .map(Duration::from_secs)
+}
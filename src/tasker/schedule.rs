@@ -0,0 +1,350 @@
+/*
+:project: telegram-onedrive
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use crate::{env::ENV, error::ResultExt, settings::SettingsSession};
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Datelike, Days, Local, NaiveTime, Timelike, Weekday};
+use std::{fmt::Display, sync::Arc};
+use tokio::sync::Mutex;
+
+const WEEKDAYS: [&str; 7] = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+const MINUTES_PER_DAY: u32 = 24 * 60;
+
+#[derive(Clone, Copy)]
+struct Window {
+    // indexed by days from monday
+    weekdays: [bool; 7],
+    // minutes from midnight, a window that ends before it starts runs over midnight
+    start: u32,
+    end: u32,
+}
+
+impl Window {
+    // like "mon-fri 22:00-06:00", weekdays are optional
+    fn parse(rule: &str) -> Result<Self> {
+        let (weekdays, time_range) = match rule.split_once(' ') {
+            Some((weekdays, time_range)) => (parse_weekdays(weekdays)?, time_range.trim()),
+            None => ([true; 7], rule),
+        };
+
+        let (start, end) = time_range
+            .split_once('-')
+            .ok_or_else(|| anyhow!("time range should be like 22:00-06:00: {}", time_range))?;
+
+        let start = parse_time(start)?;
+        let end = parse_time(end)?;
+
+        if start == end || start == MINUTES_PER_DAY {
+            return Err(anyhow!("time range is empty: {}", time_range));
+        }
+
+        Ok(Self {
+            weekdays,
+            start,
+            end,
+        })
+    }
+
+    fn is_open(&self, weekday: Weekday, minute: u32) -> bool {
+        let today = weekday.num_days_from_monday() as usize;
+        let yesterday = weekday.pred().num_days_from_monday() as usize;
+
+        if self.start < self.end {
+            self.weekdays[today] && (self.start..self.end).contains(&minute)
+        } else {
+            // the part after midnight belongs to the window started yesterday
+            (self.weekdays[today] && minute >= self.start)
+                || (self.weekdays[yesterday] && minute < self.end)
+        }
+    }
+}
+
+// like "mon-fri", "sat,sun" or "daily"
+fn parse_weekdays(weekdays: &str) -> Result<[bool; 7]> {
+    let mut parsed_weekdays = [false; 7];
+
+    for part in weekdays.split(',') {
+        if part == "daily" {
+            parsed_weekdays = [true; 7];
+        } else if let Some((from, to)) = part.split_once('-') {
+            let from = parse_weekday(from)?;
+            let to = parse_weekday(to)?;
+
+            // mon-fri, or fri-mon over the weekend
+            let mut day = from;
+            loop {
+                parsed_weekdays[day] = true;
+
+                if day == to {
+                    break;
+                }
+
+                day = (day + 1) % 7;
+            }
+        } else {
+            parsed_weekdays[parse_weekday(part)?] = true;
+        }
+    }
+
+    Ok(parsed_weekdays)
+}
+
+fn parse_weekday(weekday: &str) -> Result<usize> {
+    WEEKDAYS
+        .iter()
+        .position(|day| day.eq_ignore_ascii_case(weekday))
+        .ok_or_else(|| {
+            anyhow!(
+                "weekday should be one of {}: {}",
+                WEEKDAYS.join(", "),
+                weekday
+            )
+        })
+}
+
+// minutes from midnight, 24:00 is allowed as the end of a day
+fn parse_time(time: &str) -> Result<u32> {
+    let time = time.trim();
+
+    if time == "24:00" {
+        return Ok(MINUTES_PER_DAY);
+    }
+
+    let time = NaiveTime::parse_from_str(time, "%H:%M")
+        .context(format!("time should be like 22:00: {}", time))?;
+
+    Ok(time.hour() * 60 + time.minute())
+}
+
+// windows in which waiting tasks are started, always open if there is no window
+#[derive(Clone, Default)]
+pub struct Schedule {
+    windows: Vec<Window>,
+    rules: String,
+}
+
+impl Schedule {
+    // rules are separated by semicolons, like "mon-fri 22:00-06:00; sat,sun 00:00-24:00"
+    pub fn parse(rules: &str) -> Result<Self> {
+        let rules = rules
+            .split(';')
+            .map(str::trim)
+            .filter(|rule| !rule.is_empty())
+            .collect::<Vec<_>>();
+
+        let windows = rules
+            .iter()
+            .map(|rule| Window::parse(rule))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            windows,
+            rules: rules.join("; "),
+        })
+    }
+
+    // empty if it's always open
+    pub fn rules(&self) -> &str {
+        &self.rules
+    }
+
+    pub fn is_open(&self, now: DateTime<Local>) -> bool {
+        if self.windows.is_empty() {
+            return true;
+        }
+
+        let minute = now.hour() * 60 + now.minute();
+
+        self.windows
+            .iter()
+            .any(|window| window.is_open(now.weekday(), minute))
+    }
+
+    // none if it's open now or it never opens
+    pub fn next_open(&self, now: DateTime<Local>) -> Option<DateTime<Local>> {
+        if self.is_open(now) {
+            return None;
+        }
+
+        let today = now.date_naive();
+
+        (0..=7)
+            .filter_map(|days| today.checked_add_days(Days::new(days)))
+            .flat_map(|date| {
+                self.windows
+                    .iter()
+                    .filter(move |window| {
+                        window.weekdays[date.weekday().num_days_from_monday() as usize]
+                    })
+                    .filter_map(move |window| {
+                        date.and_hms_opt(window.start / 60, window.start % 60, 0)
+                    })
+            })
+            .filter_map(|datetime| datetime.and_local_timezone(Local).earliest())
+            .filter(|datetime| *datetime > now)
+            .min()
+    }
+}
+
+impl Display for Schedule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.windows.is_empty() {
+            write!(f, "always")
+        } else {
+            write!(f, "{}", self.rules)
+        }
+    }
+}
+
+// consulted by the tasker before fetching a waiting task
+#[derive(Clone)]
+pub struct Scheduler {
+    schedule: Arc<Mutex<Schedule>>,
+}
+
+impl Scheduler {
+    pub fn new(schedule: Schedule) -> Self {
+        Self {
+            schedule: Arc::new(Mutex::new(schedule)),
+        }
+    }
+
+    // from env
+    pub fn default_schedule() -> Schedule {
+        Schedule::parse(&ENV.get().unwrap().schedule)
+            .context("invalid schedule in env")
+            .unwrap_or_trace()
+    }
+
+    // set by /schedule before restart, otherwise from env
+    pub async fn load_schedule(settings: &SettingsSession) -> Result<Schedule> {
+        match settings.get_schedule_rules().await? {
+            Some(rules) => Schedule::parse(&rules).context("invalid schedule in settings"),
+            None => Ok(Self::default_schedule()),
+        }
+    }
+
+    pub async fn get_schedule(&self) -> Schedule {
+        self.schedule.lock().await.clone()
+    }
+
+    pub async fn set_schedule(&self, schedule: Schedule) {
+        *self.schedule.lock().await = schedule;
+    }
+
+    pub async fn is_open(&self) -> bool {
+        self.schedule.lock().await.is_open(Local::now())
+    }
+
+    pub async fn next_open(&self) -> Option<DateTime<Local>> {
+        self.schedule.lock().await.next_open(Local::now())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Schedule, Window};
+    use chrono::{DateTime, Local, TimeZone, Weekday};
+
+    // 2024-01-01 is a monday
+    fn build_datetime(day: u32, hour: u32, minute: u32) -> DateTime<Local> {
+        Local
+            .with_ymd_and_hms(2024, 1, day, hour, minute, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_window_parse() {
+        let window = Window::parse("22:00-06:00").unwrap();
+        assert_eq!(window.weekdays, [true; 7]);
+        assert_eq!(window.start, 22 * 60);
+        assert_eq!(window.end, 6 * 60);
+
+        let window = Window::parse("mon-fri 09:30-24:00").unwrap();
+        assert_eq!(
+            window.weekdays,
+            [true, true, true, true, true, false, false]
+        );
+        assert_eq!(window.start, 9 * 60 + 30);
+        assert_eq!(window.end, 24 * 60);
+
+        // over the weekend
+        let window = Window::parse("fri-mon 10:00-12:00").unwrap();
+        assert_eq!(
+            window.weekdays,
+            [true, false, false, false, true, true, true]
+        );
+
+        let window = Window::parse("sat,sun 00:00-24:00").unwrap();
+        assert_eq!(
+            window.weekdays,
+            [false, false, false, false, false, true, true]
+        );
+
+        assert!(Window::parse("10:00-10:00").is_err());
+        assert!(Window::parse("24:00-06:00").is_err());
+        assert!(Window::parse("10:00").is_err());
+        assert!(Window::parse("25:00-26:00").is_err());
+        assert!(Window::parse("funday 10:00-11:00").is_err());
+    }
+
+    #[test]
+    fn test_window_is_open_past_midnight() {
+        let window = Window::parse("fri 22:00-06:00").unwrap();
+
+        assert!(!window.is_open(Weekday::Fri, 21 * 60 + 59));
+        assert!(window.is_open(Weekday::Fri, 22 * 60));
+        // the part after midnight belongs to friday
+        assert!(window.is_open(Weekday::Sat, 5 * 60 + 59));
+        assert!(!window.is_open(Weekday::Sat, 6 * 60));
+        assert!(!window.is_open(Weekday::Sat, 23 * 60));
+        assert!(!window.is_open(Weekday::Fri, 3 * 60));
+    }
+
+    #[test]
+    fn test_schedule_is_open() {
+        assert!(Schedule::parse("")
+            .unwrap()
+            .is_open(build_datetime(1, 12, 0)));
+
+        let schedule = Schedule::parse("mon-fri 22:00-06:00; sat,sun 10:00-12:00").unwrap();
+
+        // monday
+        assert!(!schedule.is_open(build_datetime(1, 12, 0)));
+        assert!(schedule.is_open(build_datetime(1, 23, 0)));
+        // tuesday morning, from monday night
+        assert!(schedule.is_open(build_datetime(2, 5, 0)));
+        // saturday morning, from friday night
+        assert!(schedule.is_open(build_datetime(6, 5, 0)));
+        assert!(schedule.is_open(build_datetime(6, 11, 0)));
+        assert!(!schedule.is_open(build_datetime(6, 23, 0)));
+        // monday morning, sunday has no night window
+        assert!(!schedule.is_open(build_datetime(8, 5, 0)));
+    }
+
+    #[test]
+    fn test_schedule_next_open() {
+        let schedule = Schedule::parse("mon-fri 22:00-06:00").unwrap();
+
+        assert_eq!(schedule.next_open(build_datetime(1, 23, 0)), None);
+        assert_eq!(
+            schedule.next_open(build_datetime(1, 12, 0)),
+            Some(build_datetime(1, 22, 0))
+        );
+        // saturday noon waits for monday night
+        assert_eq!(
+            schedule.next_open(build_datetime(6, 12, 0)),
+            Some(build_datetime(8, 22, 0))
+        );
+
+        let schedule = Schedule::parse("sun 00:00-01:00").unwrap();
+        assert_eq!(
+            schedule.next_open(build_datetime(7, 2, 0)),
+            Some(build_datetime(14, 0, 0))
+        );
+    }
+}
@@ -5,95 +5,421 @@
 :license: MIT, see LICENSE for more details.
 */
 
-use super::tasks::{self, InsertTask, TaskStatus};
-use anyhow::{Context, Ok, Result};
+use super::{
+    progress_messages,
+    tasks::{self, CmdType, InsertTask, TaskStatus},
+    uploaded_media,
+};
+use crate::{client::onedrive::item::get_quick_xor_hash, message::TelegramMessage};
+use anyhow::{anyhow, Context, Ok, Result};
+use onedrive_api::resource::DriveItem;
 use sea_orm::{
-    sea_query::Expr, ActiveValue, ColumnTrait, Condition, ConnectionTrait, DatabaseConnection,
-    EntityName, EntityTrait, PaginatorTrait, QueryFilter, Schema, Set,
+    sea_query::{Expr, OnConflict},
+    ActiveValue, ColumnTrait, Condition, ConnectionTrait, DatabaseConnection, EntityName,
+    EntityTrait, IdenStatic, Iterable, PaginatorTrait, QueryFilter, QueryOrder, Schema, Set,
+    Statement, TransactionTrait,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Instant,
 };
-use std::{collections::HashMap, path::Path, sync::Arc};
-use tokio::{fs, sync::Mutex};
+use tokio::sync::Mutex;
 use tokio_util::sync::CancellationToken;
 
+// bump it whenever columns of table tasks change,
+// the table will be rebuilt and unfinished tasks will be kept
+const SCHEMA_VERSION: i32 = 10;
+
 // (chat id, message indicator id) -> aborter
 pub type TaskAborters = Arc<Mutex<HashMap<(i64, i32), TaskAborter>>>;
 pub type BatchAborters = Arc<Mutex<HashMap<(i64, i32), BatchAborter>>>;
+// (chat id, grouped id) -> items of an album received so far
+pub type AlbumMessages = Arc<Mutex<HashMap<(i64, i64), Vec<TelegramMessage>>>>;
 
 pub struct TaskSession {
     connection: DatabaseConnection,
     pub task_aborters: TaskAborters,
     pub batch_aborters: BatchAborters,
+    pub album_messages: AlbumMessages,
+    // chat id -> when a task of the chat was fetched last time
+    last_fetched_at: Mutex<HashMap<i64, Instant>>,
+    // set by /pause all, no task is fetched until /resume all
+    queue_paused: AtomicBool,
 }
 
 impl TaskSession {
     pub async fn new(session_path: &str) -> Result<Self> {
-        if Path::new(session_path).exists() {
-            fs::remove_file(session_path)
-                .await
-                .context("failed to remove old task session")?;
-        }
+        Self::from_url(&format!("sqlite://{}?mode=rwc", session_path)).await
+    }
 
-        let connection = Self::connect_db(session_path).await?;
+    // tests use an in-memory database
+    async fn from_url(url: &str) -> Result<Self> {
+        let connection = Self::connect_db(url).await?;
         let task_aborters = Arc::new(Mutex::new(HashMap::new()));
         let batch_aborters = Arc::new(Mutex::new(HashMap::new()));
+        let album_messages = Arc::new(Mutex::new(HashMap::new()));
 
         Ok(Self {
             connection,
             task_aborters,
             batch_aborters,
+            album_messages,
+            last_fetched_at: Mutex::new(HashMap::new()),
+            queue_paused: AtomicBool::new(false),
         })
     }
 
-    async fn connect_db(path: &str) -> Result<DatabaseConnection> {
-        let connection = sea_orm::Database::connect(format!("sqlite://{}?mode=rwc", path))
+    async fn connect_db(url: &str) -> Result<DatabaseConnection> {
+        let connection = sea_orm::Database::connect(url)
             .await
             .context("failed to connect to task session")?;
 
-        Self::create_table_if_not_exists(&connection).await?;
+        Self::migrate_tasks_table(&connection).await?;
+        Self::migrate_uploaded_media_table(&connection).await?;
+        Self::create_table_if_not_exists(&connection, tasks::Entity).await?;
+        Self::create_table_if_not_exists(&connection, progress_messages::Entity).await?;
+        Self::create_table_if_not_exists(&connection, uploaded_media::Entity).await?;
 
         Ok(connection)
     }
 
-    async fn create_table_if_not_exists(connection: &DatabaseConnection) -> Result<()> {
-        if !Self::is_table_exists(connection).await {
+    async fn create_table_if_not_exists<E>(connection: &DatabaseConnection, entity: E) -> Result<()>
+    where
+        E: EntityTrait + EntityName,
+    {
+        if !Self::is_table_exists::<E>(connection).await {
             let backend = connection.get_database_backend();
 
-            let table_create_statement =
-                Schema::new(backend).create_table_from_entity(tasks::Entity);
+            let table_create_statement = Schema::new(backend).create_table_from_entity(entity);
 
             connection
                 .execute(backend.build(&table_create_statement))
                 .await
-                .context(format!(
-                    "failed to create table {}",
-                    tasks::Entity.table_name()
-                ))?;
+                .context(format!("failed to create table {}", entity.table_name()))?;
         }
 
         Ok(())
     }
 
-    async fn is_table_exists(connection: &DatabaseConnection) -> bool {
-        let result = tasks::Entity::find().all(connection).await;
+    async fn is_table_exists<E>(connection: &DatabaseConnection) -> bool
+    where
+        E: EntityTrait,
+    {
+        let result = E::find().all(connection).await;
 
         result.is_ok()
     }
 
-    pub async fn fetch_task(&self) -> Result<Option<tasks::Model>> {
-        let task = tasks::Entity::find()
-            .filter(tasks::Column::Status.eq(TaskStatus::Waiting))
-            .one(&self.connection)
+    async fn migrate_tasks_table(connection: &DatabaseConnection) -> Result<()> {
+        let backend = connection.get_database_backend();
+
+        let version = connection
+            .query_one(Statement::from_string(backend, "PRAGMA user_version"))
+            .await
+            .context("failed to query task session version")?
+            .map(|row| row.try_get::<i32>("", "user_version"))
+            .transpose()
+            .context("failed to get task session version")?
+            .unwrap_or_default();
+
+        if version >= SCHEMA_VERSION {
+            return Ok(());
+        }
+
+        let table_name = tasks::Entity.table_name();
+
+        // a failed step leaves the old table and version untouched, it's retried on next start
+        let txn = connection
+            .begin()
+            .await
+            .context("failed to begin migration of table tasks")?;
+
+        let old_columns = txn
+            .query_all(Statement::from_string(
+                backend,
+                format!("PRAGMA table_info({})", table_name),
+            ))
+            .await
+            .context("failed to query columns of table tasks")?
+            .into_iter()
+            .map(|row| row.try_get::<String>("", "name"))
+            .collect::<Result<Vec<String>, _>>()
+            .context("failed to get column name of table tasks")?;
+
+        if !old_columns.is_empty() {
+            tracing::info!(
+                "migrate table {} from version {} to {}",
+                table_name,
+                version,
+                SCHEMA_VERSION
+            );
+
+            // new columns must be nullable or have default values
+            let columns = tasks::Column::iter()
+                .map(|column| column.as_str().to_string())
+                .filter(|column| old_columns.contains(column))
+                .collect::<Vec<String>>()
+                .join(", ");
+
+            let table_create_statement =
+                Schema::new(backend).create_table_from_entity(tasks::Entity);
+
+            txn.execute_unprepared(&format!(
+                "ALTER TABLE {} RENAME TO {}_old",
+                table_name, table_name
+            ))
+            .await
+            .context("failed to rename old table tasks")?;
+
+            txn.execute(backend.build(&table_create_statement))
+                .await
+                .context(format!("failed to create table {}", table_name))?;
+
+            txn.execute_unprepared(&format!(
+                "INSERT INTO {} ({}) SELECT {} FROM {}_old",
+                table_name, columns, columns, table_name
+            ))
+            .await
+            .context("failed to copy tasks from old table")?;
+
+            txn.execute_unprepared(&format!("DROP TABLE {}_old", table_name))
+                .await
+                .context("failed to drop old table tasks")?;
+        }
+
+        txn.execute_unprepared(&format!("PRAGMA user_version = {}", SCHEMA_VERSION))
+            .await
+            .context("failed to set task session version")?;
+
+        txn.commit()
+            .await
+            .context("failed to commit migration of table tasks")?;
+
+        Ok(())
+    }
+
+    // the index is kept, columns added later are nullable
+    async fn migrate_uploaded_media_table(connection: &DatabaseConnection) -> Result<()> {
+        let backend = connection.get_database_backend();
+
+        let table_name = uploaded_media::Entity.table_name();
+
+        let old_columns = connection
+            .query_all(Statement::from_string(
+                backend,
+                format!("PRAGMA table_info({})", table_name),
+            ))
             .await
-            .context("failed to get a task")?;
+            .context("failed to query columns of table uploaded_media")?
+            .into_iter()
+            .map(|row| row.try_get::<String>("", "name"))
+            .collect::<Result<Vec<String>, _>>()
+            .context("failed to get column name of table uploaded_media")?;
+
+        // the table will be created later
+        if old_columns.is_empty() {
+            return Ok(());
+        }
+
+        let account_column = uploaded_media::Column::Account.as_str();
+
+        if !old_columns.iter().any(|column| column == account_column) {
+            tracing::info!("add column {} to table {}", account_column, table_name);
+
+            connection
+                .execute_unprepared(&format!(
+                    "ALTER TABLE {} ADD COLUMN {} TEXT",
+                    table_name, account_column
+                ))
+                .await
+                .context("failed to add column account to table uploaded_media")?;
+        }
+
+        Ok(())
+    }
+
+    // tasks with the highest priority are fetched first,
+    // among them the chat with the fewest running tasks and served least recently goes first,
+    // so that a busy chat can't starve the others,
+    // only tasks ignoring the schedule are fetched outside the schedule windows,
+    // and tasks sharing an indicator, like items of an album, run one by one in order
+    pub async fn fetch_task(&self, in_schedule: bool) -> Result<Option<tasks::Model>> {
+        if self.is_queue_paused() {
+            return Ok(None);
+        }
+
+        let running_tasks = tasks::Entity::find()
+            .filter(
+                Condition::any()
+                    .add(tasks::Column::Status.eq(TaskStatus::Fetched))
+                    .add(tasks::Column::Status.eq(TaskStatus::Started)),
+            )
+            .all(&self.connection)
+            .await
+            .context("failed to get running tasks")?;
+
+        let mut running_nums: HashMap<i64, usize> = HashMap::new();
+        let mut running_indicators = HashSet::new();
+        for task in running_tasks {
+            *running_nums.entry(task.chat_id).or_default() += 1;
+            running_indicators.insert((task.chat_id, task.message_indicator_id));
+        }
+
+        let mut select =
+            tasks::Entity::find().filter(tasks::Column::Status.eq(TaskStatus::Waiting));
+
+        if !in_schedule {
+            select = select.filter(tasks::Column::IgnoreSchedule.eq(true));
+        }
+
+        let waiting_tasks = select
+            .order_by_desc(tasks::Column::Priority)
+            .order_by_asc(tasks::Column::Id)
+            .all(&self.connection)
+            .await
+            .context("failed to get waiting tasks")?
+            .into_iter()
+            .filter(|task| !running_indicators.contains(&(task.chat_id, task.message_indicator_id)))
+            .collect::<Vec<_>>();
+
+        let Some(top_priority) = waiting_tasks.first().map(|task| task.priority) else {
+            return Ok(None);
+        };
+
+        let mut last_fetched_at = self.last_fetched_at.lock().await;
+
+        // a chat that has never been served goes before the others
+        let task = waiting_tasks
+            .into_iter()
+            .take_while(|task| task.priority == top_priority)
+            .min_by_key(|task| {
+                (
+                    running_nums.get(&task.chat_id).copied().unwrap_or_default(),
+                    last_fetched_at.get(&task.chat_id).copied(),
+                    task.id,
+                )
+            });
 
         if let Some(task) = &task {
             self.set_task_status(task.id, tasks::TaskStatus::Fetched)
                 .await?;
+
+            last_fetched_at.insert(task.chat_id, Instant::now());
         }
 
         Ok(task)
     }
 
+    pub fn is_queue_paused(&self) -> bool {
+        self.queue_paused.load(Ordering::Acquire)
+    }
+
+    pub fn set_queue_paused(&self, paused: bool) {
+        self.queue_paused.store(paused, Ordering::Release);
+    }
+
+    // waiting and paused tasks of a chat, in the order they will be fetched
+    pub async fn get_queued_tasks(&self, chat_id: i64) -> Result<Vec<tasks::Model>> {
+        tasks::Entity::find()
+            .filter(tasks::Column::ChatId.eq(chat_id))
+            .filter(Self::queued_condition())
+            .order_by_desc(tasks::Column::Priority)
+            .order_by_asc(tasks::Column::Id)
+            .all(&self.connection)
+            .await
+            .context("failed to get queued tasks")
+    }
+
+    // bytes left to upload of queued tasks by account, none for the current account
+    pub async fn get_queued_lengths(&self) -> Result<HashMap<Option<String>, u64>> {
+        // /get tasks take no space on onedrive
+        let tasks = tasks::Entity::find()
+            .filter(Self::queued_condition())
+            .filter(tasks::Column::CmdType.ne(CmdType::Get))
+            .all(&self.connection)
+            .await
+            .context("failed to get queued tasks")?;
+
+        let mut queued_lengths = HashMap::new();
+
+        for task in tasks {
+            if let Some(total_length) = task.total_length {
+                *queued_lengths.entry(task.account).or_default() +=
+                    (total_length - task.current_length).max(0) as u64;
+            }
+        }
+
+        Ok(queued_lengths)
+    }
+
+    // move the task ahead of all queued tasks, returns false if it has been fetched
+    pub async fn bump_task(&self, id: i64) -> Result<bool> {
+        let top_priority = tasks::Entity::find()
+            .filter(Self::queued_condition())
+            .order_by_desc(tasks::Column::Priority)
+            .one(&self.connection)
+            .await
+            .context("failed to get the top priority")?
+            .map(|task| task.priority)
+            .unwrap_or_default();
+
+        let bumped = tasks::Entity::update_many()
+            .filter(tasks::Column::Id.eq(id))
+            .filter(Self::queued_condition())
+            .col_expr(tasks::Column::Priority, Expr::value(top_priority + 1))
+            .exec(&self.connection)
+            .await
+            .context("failed to bump task")?
+            .rows_affected
+            > 0;
+
+        Ok(bumped)
+    }
+
+    // only changes the task in the from status, returns false if its status has changed
+    pub async fn switch_task_status(
+        &self,
+        id: i64,
+        from: TaskStatus,
+        to: TaskStatus,
+    ) -> Result<bool> {
+        let switched = tasks::Entity::update_many()
+            .filter(tasks::Column::Id.eq(id))
+            .filter(tasks::Column::Status.eq(from))
+            .col_expr(tasks::Column::Status, Expr::value(to))
+            .exec(&self.connection)
+            .await
+            .context("failed to switch task status")?
+            .rows_affected
+            > 0;
+
+        Ok(switched)
+    }
+
+    // returns false if it has been fetched
+    pub async fn delete_queued_task(&self, id: i64) -> Result<bool> {
+        let deleted = tasks::Entity::delete_many()
+            .filter(tasks::Column::Id.eq(id))
+            .filter(Self::queued_condition())
+            .exec(&self.connection)
+            .await
+            .context("failed to delete queued task")?
+            .rows_affected
+            > 0;
+
+        Ok(deleted)
+    }
+
+    fn queued_condition() -> Condition {
+        Condition::any()
+            .add(tasks::Column::Status.eq(TaskStatus::Waiting))
+            .add(tasks::Column::Status.eq(TaskStatus::Paused))
+    }
+
     pub async fn insert_task(
         &self,
         InsertTask {
@@ -112,6 +438,11 @@ impl TaskSession {
             message_indicator_id,
             message_origin_id,
             auto_delete,
+            media_key,
+            conflict_policy,
+            account,
+            ignore_schedule,
+            zip,
         }: InsertTask,
     ) -> Result<i64> {
         let insert_item = tasks::ActiveModel {
@@ -122,7 +453,7 @@ impl TaskSession {
             url: Set(url),
             upload_url: Set(upload_url.to_string()),
             current_length: Set(current_length as i64),
-            total_length: Set(total_length as i64),
+            total_length: Set(total_length.map(|total_length| total_length as i64)),
             chat_id: Set(chat_id),
             chat_bot_hex: Set(chat_bot_hex.to_string()),
             chat_user_hex: Set(chat_user_hex.to_string()),
@@ -132,6 +463,15 @@ impl TaskSession {
             message_origin_id: Set(message_origin_id),
             status: Set(TaskStatus::Waiting),
             auto_delete: Set(auto_delete),
+            last_error: Set(None),
+            media_key: Set(media_key),
+            conflict_policy: Set(conflict_policy),
+            quick_xor_hash_state: Set(None),
+            verified: Set(false),
+            priority: Set(0),
+            ignore_schedule: Set(ignore_schedule),
+            account: Set(account),
+            zip: Set(zip),
         };
 
         let id = tasks::Entity::insert(insert_item)
@@ -154,6 +494,17 @@ impl TaskSession {
         Ok(())
     }
 
+    pub async fn set_last_error(&self, id: i64, last_error: Option<String>) -> Result<()> {
+        tasks::Entity::update_many()
+            .filter(tasks::Column::Id.eq(id))
+            .col_expr(tasks::Column::LastError, Expr::value(last_error))
+            .exec(&self.connection)
+            .await
+            .context("failed to update last error")?;
+
+        Ok(())
+    }
+
     pub async fn set_current_length(&self, id: i64, current_length: u64) -> Result<()> {
         tasks::Entity::update_many()
             .filter(tasks::Column::Id.eq(id))
@@ -168,11 +519,177 @@ impl TaskSession {
         Ok(())
     }
 
+    pub async fn set_total_length(&self, id: i64, total_length: u64) -> Result<()> {
+        tasks::Entity::update_many()
+            .filter(tasks::Column::Id.eq(id))
+            .col_expr(tasks::Column::TotalLength, Expr::value(total_length as i64))
+            .exec(&self.connection)
+            .await
+            .context("failed to update total length")?;
+
+        Ok(())
+    }
+
+    pub async fn set_quick_xor_hash_state(
+        &self,
+        id: i64,
+        quick_xor_hash_state: Option<String>,
+    ) -> Result<()> {
+        tasks::Entity::update_many()
+            .filter(tasks::Column::Id.eq(id))
+            .col_expr(
+                tasks::Column::QuickXorHashState,
+                Expr::value(quick_xor_hash_state),
+            )
+            .exec(&self.connection)
+            .await
+            .context("failed to update quick xor hash state")?;
+
+        Ok(())
+    }
+
+    pub async fn set_verified(&self, id: i64, verified: bool) -> Result<()> {
+        tasks::Entity::update_many()
+            .filter(tasks::Column::Id.eq(id))
+            .col_expr(tasks::Column::Verified, Expr::value(verified))
+            .exec(&self.connection)
+            .await
+            .context("failed to update verified")?;
+
+        Ok(())
+    }
+
+    pub async fn set_upload_url(&self, id: i64, upload_url: &str) -> Result<()> {
+        tasks::Entity::update_many()
+            .filter(tasks::Column::Id.eq(id))
+            .col_expr(tasks::Column::UploadUrl, Expr::value(upload_url))
+            .exec(&self.connection)
+            .await
+            .context("failed to update upload url")?;
+
+        Ok(())
+    }
+
+    pub async fn set_message_id(&self, id: i64, message_id: i32) -> Result<()> {
+        tasks::Entity::update_many()
+            .filter(tasks::Column::Id.eq(id))
+            .col_expr(tasks::Column::MessageId, Expr::value(message_id))
+            .exec(&self.connection)
+            .await
+            .context("failed to update message id")?;
+
+        Ok(())
+    }
+
+    pub async fn get_task(&self, id: i64) -> Result<tasks::Model> {
+        tasks::Entity::find_by_id(id)
+            .one(&self.connection)
+            .await
+            .context("failed to get task")?
+            .ok_or_else(|| anyhow!("task not found"))
+    }
+
+    pub async fn get_unfinished_tasks(&self) -> Result<Vec<tasks::Model>> {
+        tasks::Entity::find()
+            .filter(
+                Condition::any()
+                    .add(tasks::Column::Status.eq(TaskStatus::Fetched))
+                    .add(tasks::Column::Status.eq(TaskStatus::Started)),
+            )
+            .all(&self.connection)
+            .await
+            .context("failed to get unfinished tasks")
+    }
+
+    pub async fn delete_completed_tasks(&self) -> Result<()> {
+        tasks::Entity::delete_many()
+            .filter(tasks::Column::Status.eq(TaskStatus::Completed))
+            .exec(&self.connection)
+            .await
+            .context("failed to delete completed tasks")?;
+
+        Ok(())
+    }
+
+    pub async fn get_failed_tasks(&self, chat_id: i64) -> Result<Vec<tasks::Model>> {
+        tasks::Entity::find()
+            .filter(tasks::Column::ChatId.eq(chat_id))
+            .filter(tasks::Column::Status.eq(TaskStatus::Failed))
+            .all(&self.connection)
+            .await
+            .context("failed to get failed tasks")
+    }
+
+    pub async fn get_failed_task_from_message_indicator_id(
+        &self,
+        chat_id: i64,
+        message_indicator_id: i32,
+    ) -> Result<Option<tasks::Model>> {
+        tasks::Entity::find()
+            .filter(tasks::Column::ChatId.eq(chat_id))
+            .filter(tasks::Column::MessageIndicatorId.eq(message_indicator_id))
+            .filter(tasks::Column::Status.eq(TaskStatus::Failed))
+            .one(&self.connection)
+            .await
+            .context("failed to get failed task from message indicator id")
+    }
+
+    pub async fn get_task_from_message_indicator_id(
+        &self,
+        chat_id: i64,
+        message_indicator_id: i32,
+    ) -> Result<Option<tasks::Model>> {
+        tasks::Entity::find()
+            .filter(tasks::Column::ChatId.eq(chat_id))
+            .filter(tasks::Column::MessageIndicatorId.eq(message_indicator_id))
+            .one(&self.connection)
+            .await
+            .context("failed to get task from message indicator id")
+    }
+
+    // all chats if chat id is none
+    pub async fn get_paused_tasks(&self, chat_id: Option<i64>) -> Result<Vec<tasks::Model>> {
+        let mut select = tasks::Entity::find().filter(tasks::Column::Status.eq(TaskStatus::Paused));
+
+        if let Some(chat_id) = chat_id {
+            select = select.filter(tasks::Column::ChatId.eq(chat_id));
+        }
+
+        select
+            .all(&self.connection)
+            .await
+            .context("failed to get paused tasks")
+    }
+
+    // all chats if chat id is none, returns the number of paused tasks
+    pub async fn pause_waiting_tasks(&self, chat_id: Option<i64>) -> Result<u64> {
+        let mut update = tasks::Entity::update_many()
+            .filter(tasks::Column::Status.eq(TaskStatus::Waiting))
+            .col_expr(tasks::Column::Status, Expr::value(TaskStatus::Paused));
+
+        if let Some(chat_id) = chat_id {
+            update = update.filter(tasks::Column::ChatId.eq(chat_id));
+        }
+
+        let paused_num = update
+            .exec(&self.connection)
+            .await
+            .context("failed to pause waiting tasks")?
+            .rows_affected;
+
+        Ok(paused_num)
+    }
+
     pub async fn get_chats_current_tasks(&self) -> Result<HashMap<ChatHex, Vec<tasks::Model>>> {
         let mut chats = HashMap::new();
 
+        // paused tasks are shown along with the started ones
         let tasks = tasks::Entity::find()
-            .filter(tasks::Column::Status.eq(TaskStatus::Started))
+            .filter(
+                Condition::any()
+                    .add(tasks::Column::Status.eq(TaskStatus::Started))
+                    .add(tasks::Column::Status.eq(TaskStatus::Paused)),
+            )
             .all(&self.connection)
             .await
             .context("failed to get chat current tasks")?;
@@ -293,6 +810,22 @@ impl TaskSession {
         Ok(())
     }
 
+    // items of an album share the indicator, it's kept until the last of them is done
+    pub async fn is_last_indicator_task(
+        &self,
+        chat_id: i64,
+        message_indicator_id: i32,
+    ) -> Result<bool> {
+        let count = tasks::Entity::find()
+            .filter(tasks::Column::ChatId.eq(chat_id))
+            .filter(tasks::Column::MessageIndicatorId.eq(message_indicator_id))
+            .count(&self.connection)
+            .await
+            .context("failed to count with message indicator id")?;
+
+        Ok(count <= 1)
+    }
+
     pub async fn is_last_task(&self, chat_id: i64, message_indicator_id: i32) -> Result<bool> {
         // check if the task is the last task in batch or /links
         let task = tasks::Entity::find()
@@ -303,8 +836,10 @@ impl TaskSession {
             .context("failed to get task with message indicator id")?;
 
         if let Some(task) = task {
+            // failed tasks are kept for /retry, they don't hold the message back
             let count = tasks::Entity::find()
                 .filter(tasks::Column::MessageId.eq(task.message_id))
+                .filter(tasks::Column::Status.ne(TaskStatus::Failed))
                 .count(&self.connection)
                 .await
                 .context("failed to count with message id")?;
@@ -329,7 +864,118 @@ impl TaskSession {
             .await
             .context("failed to get message indicator ids")?;
 
-        Ok(tasks.iter().map(|task| task.message_indicator_id).collect())
+        // items of an album share the indicator
+        let mut message_indicator_ids = tasks
+            .iter()
+            .map(|task| task.message_indicator_id)
+            .collect::<Vec<i32>>();
+        message_indicator_ids.sort_unstable();
+        message_indicator_ids.dedup();
+
+        Ok(message_indicator_ids)
+    }
+
+    pub async fn get_progress_message_ids(&self) -> Result<HashMap<String, Option<i32>>> {
+        let progress_messages = progress_messages::Entity::find()
+            .all(&self.connection)
+            .await
+            .context("failed to get progress message ids")?;
+
+        Ok(progress_messages
+            .into_iter()
+            .map(|progress_message| {
+                (
+                    progress_message.chat_bot_hex,
+                    Some(progress_message.message_id),
+                )
+            })
+            .collect())
+    }
+
+    pub async fn set_progress_message_id(&self, chat_bot_hex: &str, message_id: i32) -> Result<()> {
+        let insert_item = progress_messages::ActiveModel {
+            chat_bot_hex: Set(chat_bot_hex.to_string()),
+            message_id: Set(message_id),
+        };
+
+        progress_messages::Entity::insert(insert_item)
+            .on_conflict(
+                OnConflict::column(progress_messages::Column::ChatBotHex)
+                    .update_column(progress_messages::Column::MessageId)
+                    .to_owned(),
+            )
+            .exec(&self.connection)
+            .await
+            .context("failed to set progress message id")?;
+
+        Ok(())
+    }
+
+    pub async fn get_uploaded_media(
+        &self,
+        media_key: &str,
+    ) -> Result<Option<uploaded_media::Model>> {
+        uploaded_media::Entity::find_by_id(media_key.to_string())
+            .one(&self.connection)
+            .await
+            .context("failed to get uploaded media")
+    }
+
+    pub async fn set_uploaded_media(
+        &self,
+        media_key: &str,
+        drive_item: &DriveItem,
+        account: Option<&str>,
+    ) -> Result<()> {
+        let item_id = drive_item
+            .id
+            .as_ref()
+            .ok_or_else(|| anyhow!("drive item id not found"))?;
+
+        let insert_item = uploaded_media::ActiveModel {
+            media_key: Set(media_key.to_string()),
+            item_id: Set(item_id.as_str().to_string()),
+            size: Set(drive_item.size.unwrap_or_default()),
+            quick_xor_hash: Set(get_quick_xor_hash(drive_item)),
+            web_url: Set(drive_item.web_url.clone()),
+            account: Set(account.map(ToString::to_string)),
+        };
+
+        uploaded_media::Entity::insert(insert_item)
+            .on_conflict(
+                OnConflict::column(uploaded_media::Column::MediaKey)
+                    .update_columns([
+                        uploaded_media::Column::ItemId,
+                        uploaded_media::Column::Size,
+                        uploaded_media::Column::QuickXorHash,
+                        uploaded_media::Column::WebUrl,
+                        uploaded_media::Column::Account,
+                    ])
+                    .to_owned(),
+            )
+            .exec(&self.connection)
+            .await
+            .context("failed to set uploaded media")?;
+
+        Ok(())
+    }
+
+    pub async fn delete_uploaded_media(&self, media_key: &str) -> Result<()> {
+        uploaded_media::Entity::delete_by_id(media_key.to_string())
+            .exec(&self.connection)
+            .await
+            .context("failed to delete uploaded media")?;
+
+        Ok(())
+    }
+
+    pub async fn delete_progress_message_id(&self, chat_bot_hex: &str) -> Result<()> {
+        progress_messages::Entity::delete_by_id(chat_bot_hex.to_string())
+            .exec(&self.connection)
+            .await
+            .context("failed to delete progress message id")?;
+
+        Ok(())
     }
 }
 
@@ -345,6 +991,8 @@ pub struct TaskAborter {
     pub message_id: i32,
     filename: String,
     pub token: CancellationToken,
+    // stops the task but keeps it, so that it can be resumed
+    pub pause_token: CancellationToken,
 }
 
 impl TaskAborter {
@@ -355,6 +1003,7 @@ impl TaskAborter {
             message_id,
             filename: filename.to_string(),
             token: CancellationToken::new(),
+            pause_token: CancellationToken::new(),
         }
     }
 
@@ -363,6 +1012,12 @@ impl TaskAborter {
 
         self.token.cancel();
     }
+
+    pub fn pause(&self) {
+        tracing::info!("task {} paused", self.filename);
+
+        self.pause_token.cancel();
+    }
 }
 
 pub struct BatchAborter {
@@ -385,3 +1040,156 @@ impl BatchAborter {
         self.token.cancel();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::TaskSession;
+    use crate::{
+        settings::ConflictPolicy,
+        tasker::{CmdType, InsertTask, TaskStatus},
+    };
+
+    fn build_task(chat_id: i64, message_indicator_id: i32) -> InsertTask {
+        InsertTask {
+            cmd_type: CmdType::Url,
+            filename: format!("{}_{}.bin", chat_id, message_indicator_id),
+            root_path: "/".to_string(),
+            url: None,
+            upload_url: String::new(),
+            current_length: 0,
+            total_length: None,
+            chat_id,
+            chat_bot_hex: String::new(),
+            chat_user_hex: String::new(),
+            chat_origin_hex: None,
+            message_id: message_indicator_id,
+            message_indicator_id,
+            message_origin_id: None,
+            auto_delete: false,
+            media_key: None,
+            conflict_policy: ConflictPolicy::default(),
+            account: None,
+            ignore_schedule: false,
+            zip: false,
+        }
+    }
+
+    async fn fetch_chat_ids(task_session: &TaskSession, num: usize) -> Vec<i64> {
+        let mut chat_ids = Vec::new();
+
+        for _ in 0..num {
+            let task = task_session.fetch_task(true).await.unwrap().unwrap();
+
+            chat_ids.push(task.chat_id);
+        }
+
+        chat_ids
+    }
+
+    #[tokio::test]
+    async fn test_fetch_task_alternates_chats() {
+        let task_session = TaskSession::from_url("sqlite::memory:").await.unwrap();
+
+        // the busy chat queued all of its tasks first
+        for message_indicator_id in 1..=3 {
+            task_session
+                .insert_task(build_task(1, message_indicator_id))
+                .await
+                .unwrap();
+        }
+        for message_indicator_id in 1..=2 {
+            task_session
+                .insert_task(build_task(2, message_indicator_id))
+                .await
+                .unwrap();
+        }
+
+        assert_eq!(fetch_chat_ids(&task_session, 5).await, vec![1, 2, 1, 2, 1]);
+        assert!(task_session.fetch_task(true).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_fetch_task_priority_and_indicator() {
+        let task_session = TaskSession::from_url("sqlite::memory:").await.unwrap();
+
+        // items of an album share the indicator
+        task_session.insert_task(build_task(1, 1)).await.unwrap();
+        task_session.insert_task(build_task(1, 1)).await.unwrap();
+        task_session.insert_task(build_task(2, 1)).await.unwrap();
+        let bumped_id = task_session.insert_task(build_task(2, 2)).await.unwrap();
+
+        assert!(task_session.bump_task(bumped_id).await.unwrap());
+
+        let task = task_session.fetch_task(true).await.unwrap().unwrap();
+        assert_eq!(task.id, bumped_id);
+
+        // chat 2 has a running task, so chat 1 goes next
+        let task = task_session.fetch_task(true).await.unwrap().unwrap();
+        assert_eq!((task.chat_id, task.message_indicator_id), (1, 1));
+        let album_item_id = task.id;
+
+        // the other item of the album waits for the running one
+        let task = task_session.fetch_task(true).await.unwrap().unwrap();
+        assert_eq!((task.chat_id, task.message_indicator_id), (2, 1));
+        assert!(task_session.fetch_task(true).await.unwrap().is_none());
+
+        task_session
+            .set_task_status(album_item_id, TaskStatus::Completed)
+            .await
+            .unwrap();
+
+        let task = task_session.fetch_task(true).await.unwrap().unwrap();
+        assert_eq!((task.chat_id, task.message_indicator_id), (1, 1));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_task_outside_schedule() {
+        let task_session = TaskSession::from_url("sqlite::memory:").await.unwrap();
+
+        task_session.insert_task(build_task(1, 1)).await.unwrap();
+        let now_id = task_session
+            .insert_task(InsertTask {
+                ignore_schedule: true,
+                ..build_task(1, 2)
+            })
+            .await
+            .unwrap();
+
+        let task = task_session.fetch_task(false).await.unwrap().unwrap();
+        assert_eq!(task.id, now_id);
+        assert!(task_session.fetch_task(false).await.unwrap().is_none());
+
+        task_session.set_queue_paused(true);
+        assert!(task_session.fetch_task(true).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_is_last_task_without_failed() {
+        let task_session = TaskSession::from_url("sqlite::memory:").await.unwrap();
+
+        // two links of the same message
+        let failed_id = task_session
+            .insert_task(InsertTask {
+                message_id: 1,
+                ..build_task(1, 2)
+            })
+            .await
+            .unwrap();
+        task_session
+            .insert_task(InsertTask {
+                message_id: 1,
+                ..build_task(1, 3)
+            })
+            .await
+            .unwrap();
+
+        assert!(!task_session.is_last_task(1, 3).await.unwrap());
+
+        task_session
+            .set_task_status(failed_id, TaskStatus::Failed)
+            .await
+            .unwrap();
+
+        assert!(task_session.is_last_task(1, 3).await.unwrap());
+    }
+}
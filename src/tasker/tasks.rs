@@ -5,6 +5,7 @@
 :license: MIT, see LICENSE for more details.
 */
 
+use crate::settings::ConflictPolicy;
 use sea_orm::{
     entity::prelude::DeriveEntityModel,
     sea_query::{ArrayType, ValueType, ValueTypeErr},
@@ -26,7 +27,8 @@ pub struct Model {
     // onedrive upload url
     pub upload_url: String,
     pub current_length: i64,
-    pub total_length: i64,
+    // none if the size of the source is unknown, like a chunked response for /url
+    pub total_length: Option<i64>,
     pub chat_id: i64,
     // chat hex used by bot
     pub chat_bot_hex: String,
@@ -39,10 +41,33 @@ pub struct Model {
     // message id of the indicator(sent from the bot)
     pub message_indicator_id: i32,
     // message id of the origin message in the origin chat
-    // for link
+    // for link, and for items of an album whose message id is the first item
     pub message_origin_id: Option<i32>,
     pub status: TaskStatus,
     pub auto_delete: bool,
+    // error of the last attempt, kept with failed tasks for /retry
+    pub last_error: Option<String>,
+    // key of the telegram media in the uploaded media index, for file and link
+    pub media_key: Option<String>,
+    // kept so that resumed or retried tasks handle an existing file the same way
+    #[sea_orm(default_value = "rename")]
+    pub conflict_policy: ConflictPolicy,
+    // quickXorHash of the uploaded bytes so far, none if the task was resumed from an unknown state
+    pub quick_xor_hash_state: Option<String>,
+    // whether the uploaded file matches the hash computed while uploading
+    #[sea_orm(default_value = false)]
+    pub verified: bool,
+    // tasks with higher priority are fetched first, tasks with the same priority are fetched in order
+    #[sea_orm(default_value = 0)]
+    pub priority: i32,
+    // started even outside the schedule windows
+    #[sea_orm(default_value = false)]
+    pub ignore_schedule: bool,
+    // onedrive username the file is uploaded to, none for the current account
+    pub account: Option<String>,
+    // for /get, send the file as a split zip
+    #[sea_orm(default_value = false)]
+    pub zip: bool,
 }
 
 #[derive(Clone, Debug, EnumIter, DeriveRelation)]
@@ -55,6 +80,8 @@ pub enum CmdType {
     File,
     Link,
     Url,
+    // from onedrive to telegram
+    Get,
 }
 
 impl ValueType for CmdType {
@@ -64,6 +91,7 @@ impl ValueType for CmdType {
                 "file" => Ok(Self::File),
                 "link" => Ok(Self::Link),
                 "url" => Ok(Self::Url),
+                "get" => Ok(Self::Get),
                 _ => Err(ValueTypeErr),
             },
             _ => Err(ValueTypeErr),
@@ -86,7 +114,7 @@ impl ValueType for CmdType {
 impl From<CmdType> for Value {
     fn from(value: CmdType) -> Self {
         match value {
-            CmdType::File | CmdType::Link | CmdType::Url => {
+            CmdType::File | CmdType::Link | CmdType::Url | CmdType::Get => {
                 Self::String(Some(Box::new(value.to_string())))
             }
         }
@@ -101,8 +129,9 @@ impl TryGetable for CmdType {
             "file" => Ok(Self::File),
             "link" => Ok(Self::Link),
             "url" => Ok(Self::Url),
+            "get" => Ok(Self::Get),
             _ => Err(TryGetError::DbErr(DbErr::Type(format!(
-                "cmd type value should be one of file, photo, link and url: {}",
+                "cmd type value should be one of file, photo, link, url and get: {}",
                 value
             )))),
         }
@@ -115,6 +144,7 @@ impl Display for CmdType {
             Self::File => write!(f, "file"),
             Self::Link => write!(f, "link"),
             Self::Url => write!(f, "url"),
+            Self::Get => write!(f, "get"),
         }
     }
 }
@@ -131,6 +161,8 @@ pub enum TaskStatus {
     Started,
     Completed,
     Failed,
+    // waiting task held back by /queue pause
+    Paused,
 }
 
 impl ValueType for TaskStatus {
@@ -142,6 +174,7 @@ impl ValueType for TaskStatus {
                 "started" => Ok(Self::Started),
                 "completed" => Ok(Self::Completed),
                 "failed" => Ok(Self::Failed),
+                "paused" => Ok(Self::Paused),
                 _ => Err(ValueTypeErr),
             },
             _ => Err(ValueTypeErr),
@@ -168,7 +201,8 @@ impl From<TaskStatus> for Value {
             | TaskStatus::Fetched
             | TaskStatus::Started
             | TaskStatus::Completed
-            | TaskStatus::Failed => Self::String(Some(Box::new(value.to_string()))),
+            | TaskStatus::Failed
+            | TaskStatus::Paused => Self::String(Some(Box::new(value.to_string()))),
         }
     }
 }
@@ -183,8 +217,9 @@ impl TryGetable for TaskStatus {
             "started" => Ok(Self::Started),
             "completed" => Ok(Self::Completed),
             "failed" => Ok(Self::Failed),
+            "paused" => Ok(Self::Paused),
             _ => Err(TryGetError::DbErr(DbErr::Type(format!(
-                "task status value should be one of waiting, started, completed and failed: {}",
+                "task status value should be one of waiting, fetched, started, completed, failed and paused: {}",
                 value
             )))),
         }
@@ -199,6 +234,7 @@ impl Display for TaskStatus {
             Self::Started => write!(f, "started"),
             Self::Completed => write!(f, "completed"),
             Self::Failed => write!(f, "failed"),
+            Self::Paused => write!(f, "paused"),
         }
     }
 }
@@ -210,7 +246,7 @@ pub struct InsertTask {
     pub url: Option<String>,
     pub upload_url: String,
     pub current_length: u64,
-    pub total_length: u64,
+    pub total_length: Option<u64>,
     pub chat_id: i64,
     pub chat_bot_hex: String,
     pub chat_user_hex: String,
@@ -219,4 +255,9 @@ pub struct InsertTask {
     pub message_indicator_id: i32,
     pub message_origin_id: Option<i32>,
     pub auto_delete: bool,
+    pub media_key: Option<String>,
+    pub conflict_policy: ConflictPolicy,
+    pub account: Option<String>,
+    pub ignore_schedule: bool,
+    pub zip: bool,
 }
@@ -5,135 +5,162 @@
 :license: MIT, see LICENSE for more details.
 */
 
-use super::{tasks, Progress};
+use super::{
+    download::{Downloader, TgDownloader, UrlDownloader},
+    hash::QuickXorHash,
+    limit::{ChatLimiter, Direction},
+    part::{PartPipeline, PartSizer},
+    retry::{get_retry_after, RetryPolicy},
+    tasks, Progress,
+};
 use crate::{
-    client::utils::chat_from_hex, error::TaskAbortError, state::AppState, utils::get_http_client,
+    client::{onedrive::item::get_quick_xor_hash, utils::chat_from_hex},
+    env::SESSION_DIR,
+    error::TaskSkipError,
+    settings::ConflictPolicy,
+    state::AppState,
+    utils::get_http_client,
 };
 use anyhow::{anyhow, Context, Error, Result};
-use grammers_client::client::files::MAX_CHUNK_SIZE;
-use onedrive_api::{resource::DriveItem, UploadSession};
-use std::{collections::VecDeque, ops::Range, sync::Arc, time::Duration};
+use futures::{stream, StreamExt};
+use onedrive_api::resource::DriveItem;
+use reqwest::{header, Body, StatusCode};
+use std::{
+    collections::VecDeque,
+    fmt::Display,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+use tokio::{
+    fs::File,
+    io::{AsyncReadExt, AsyncWriteExt},
+};
 use tokio_util::sync::CancellationToken;
 
-const MAX_RETRIES: i32 = 5;
+// the request body of a part is sent in pieces of this size
+const BODY_CHUNK_SIZE: usize = 64 * 1024;
+// bytes of a source of unknown length held in memory, the rest is spilled to disk
+const TAIL_MEMORY_LIMIT: usize = 64 * 1024 * 1024;
+// spill files are named like tail_{id}.part in the session dir
+pub const TAIL_FILE_PREFIX: &str = "tail_";
 
 pub async fn multi_parts_uploader_from_url(
-    tasks::Model {
+    task: &tasks::Model,
+    progress: Arc<Progress>,
+    state: AppState,
+) -> Result<DriveItem> {
+    let tasks::Model {
         id,
         url,
-        upload_url,
         current_length,
         total_length,
+        chat_id,
+        quick_xor_hash_state,
         ..
-    }: &tasks::Model,
-    progress: Arc<Progress>,
-) -> Result<String> {
-    const PART_SIZE: usize = 3276800;
+    } = task;
 
     let http_client = get_http_client()?;
 
     let url = url.clone().ok_or_else(|| anyhow!("url is none"))?;
 
-    let upload_session = UploadSession::from_upload_url(upload_url);
-
-    let mut current_length = current_length.to_owned() as u64;
-    let total_length = total_length.to_owned() as u64;
-
-    progress
-        .set_current_length(id.to_owned(), current_length)
-        .await?;
-
-    let mut response = http_client
-        .get(url)
-        .send()
-        .await
-        .context("failed to send request for /url")?;
-
-    let upload_response = loop {
-        let mut buffer = Vec::with_capacity(PART_SIZE);
-
-        while let Some(chunk) = response.chunk().await.context("failed to get chunk")? {
-            buffer.extend_from_slice(&chunk);
+    let current_length = current_length.to_owned() as u64;
+    let total_length = total_length.map(|total_length| total_length as u64);
 
-            if buffer.len() >= PART_SIZE {
-                break;
-            }
-        }
-
-        tracing::debug!("downloaded chunk from url");
+    if current_length > 0 && total_length.is_some_and(|total_length| current_length >= total_length)
+    {
+        return get_uploaded_item(task, &state).await;
+    }
 
-        let upload_response = upload_file(
-            &upload_session,
-            &buffer,
-            current_length,
-            total_length,
-            &http_client,
-        )
-        .await?;
+    let retry_policy = RetryPolicy::new();
 
-        tracing::debug!("uploaded chunk from url");
+    let downloader = UrlDownloader::new(
+        &http_client,
+        &url,
+        current_length,
+        total_length,
+        retry_policy,
+        state.bandwidth_limiter.for_chat(chat_id.to_owned()),
+    )
+    .await?;
 
-        current_length += buffer.len() as u64;
-        progress
-            .set_current_length(id.to_owned(), current_length)
-            .await?;
+    let uploader = PartUploader::new(task, &http_client, retry_policy, &progress, &state)?;
 
-        if current_length >= total_length {
-            break upload_response;
-        }
-    };
-
-    let filename = upload_response
-        .ok_or_else(|| anyhow!("failed to get drive item after upload"))?
-        .name
-        .ok_or_else(|| anyhow!("drive item name not found"))?;
+    let (upload_response, total_length, quick_xor_hash) = upload_parts(
+        Downloader::Url(downloader),
+        uploader,
+        id.to_owned(),
+        current_length,
+        total_length,
+        quick_xor_hash_state.as_deref(),
+        progress.clone(),
+    )
+    .await?;
+
+    let drive_item =
+        upload_response.ok_or_else(|| anyhow!("failed to get drive item after upload"))?;
+
+    verify_drive_item(
+        id.to_owned(),
+        task.account.as_deref(),
+        &drive_item,
+        quick_xor_hash,
+        &progress,
+        &state,
+    )
+    .await?;
 
     tracing::info!(
         "uploaded file from url: {} size: {}",
-        filename,
+        drive_item.name.as_deref().unwrap_or_default(),
         total_length
     );
 
-    Ok(filename)
+    Ok(drive_item)
 }
 
 pub async fn multi_parts_uploader_from_tg_file(
-    tasks::Model {
+    task: &tasks::Model,
+    progress: Arc<Progress>,
+    cancellation_token: CancellationToken,
+    state: AppState,
+) -> Result<DriveItem> {
+    let tasks::Model {
         id,
         cmd_type,
-        upload_url,
         current_length,
         total_length,
+        chat_id,
         chat_user_hex,
         chat_origin_hex,
         message_id,
         message_origin_id,
+        quick_xor_hash_state,
         ..
-    }: &tasks::Model,
-    progress: Arc<Progress>,
-    cancellation_token: CancellationToken,
-    state: AppState,
-) -> Result<String> {
-    const WORKER_COUNT: i32 = 4;
+    } = task;
 
     let http_client = get_http_client()?;
 
-    let upload_session = UploadSession::from_upload_url(upload_url);
-
-    let mut current_length = current_length.to_owned() as u64;
-    let total_length = total_length.to_owned() as u64;
-
-    progress
-        .set_current_length(id.to_owned(), current_length)
-        .await?;
+    let current_length = current_length.to_owned() as u64;
+    let total_length =
+        total_length.ok_or_else(|| anyhow!("total length of tg file is none"))? as u64;
 
-    let mut upload_response = None;
+    if current_length > 0 && current_length >= total_length {
+        return get_uploaded_item(task, &state).await;
+    }
 
     let telegram_user = &state.telegram_user;
     let chat = chat_from_hex(chat_user_hex)?;
 
     let message = match cmd_type {
-        tasks::CmdType::File => telegram_user.get_message(chat, *message_id).await?,
+        // items of an album keep their own message as the origin
+        tasks::CmdType::File => {
+            telegram_user
+                .get_message(chat, message_origin_id.unwrap_or(*message_id))
+                .await?
+        }
         tasks::CmdType::Link => {
             let chat = chat_from_hex(
                 chat_origin_hex
@@ -147,174 +174,523 @@ pub async fn multi_parts_uploader_from_tg_file(
 
             telegram_user.get_message(chat, *message_origin_id).await?
         }
-        tasks::CmdType::Url => return Err(anyhow!("invalid cmd type")),
+        tasks::CmdType::Url | tasks::CmdType::Get => return Err(anyhow!("invalid cmd type")),
     };
 
-    let media = Arc::new(
-        message
-            .media()
-            .ok_or_else(|| anyhow!("message does not contain any media"))?,
+    let media = message
+        .media()
+        .ok_or_else(|| anyhow!("message does not contain any media"))?;
+
+    let retry_policy = RetryPolicy::new();
+
+    let downloader = TgDownloader::new(
+        telegram_user,
+        media,
+        current_length,
+        total_length,
+        retry_policy,
+        state.bandwidth_limiter.for_chat(chat_id.to_owned()),
+        cancellation_token,
     );
 
-    let mut work_handles = VecDeque::new();
+    let uploader = PartUploader::new(task, &http_client, retry_policy, &progress, &state)?;
 
-    let total_chunks_num = if total_length > MAX_CHUNK_SIZE as u64 {
-        (total_length as f32 / MAX_CHUNK_SIZE as f32).ceil() as i32
-    } else {
-        1
-    };
-    let mut current_chunk_num = 0;
+    let (upload_response, _, quick_xor_hash) = upload_parts(
+        Downloader::Telegram(downloader),
+        uploader,
+        id.to_owned(),
+        current_length,
+        Some(total_length),
+        quick_xor_hash_state.as_deref(),
+        progress.clone(),
+    )
+    .await?;
+
+    let drive_item =
+        upload_response.ok_or_else(|| anyhow!("failed to get drive item after upload"))?;
+
+    verify_drive_item(
+        id.to_owned(),
+        task.account.as_deref(),
+        &drive_item,
+        quick_xor_hash,
+        &progress,
+        &state,
+    )
+    .await?;
 
-    while current_chunk_num < total_chunks_num {
-        let telegram_user_clone = telegram_user.clone();
-        let media_clone = media.clone();
+    tracing::info!(
+        "uploaded file from telegram: {} size: {}",
+        drive_item.name.as_deref().unwrap_or_default(),
+        total_length
+    );
 
-        let cancellation_token_clone = cancellation_token.clone();
+    Ok(drive_item)
+}
 
-        // create a worker
-        work_handles.push_back(tokio::spawn(async move {
-            let mut download = telegram_user_clone
-                .iter_download(media_clone.as_ref())
-                .skip_chunks(current_chunk_num);
+// the file was uploaded before the upload session was gone, found when resuming
+async fn get_uploaded_item(task: &tasks::Model, state: &AppState) -> Result<DriveItem> {
+    tracing::info!("file was uploaded before: {}", task.filename);
 
-            let fut = async {
-                let mut retries = 0;
+    state
+        .onedrive
+        .get_item_from_path(&task.root_path, &task.filename, task.account.as_deref())
+        .await?
+        .ok_or_else(|| anyhow!("failed to get drive item uploaded before"))
+}
 
-                loop {
-                    match download.next().await {
-                        Ok(chunk) => break Ok(chunk),
-                        Err(e) => {
-                            if retries <= MAX_RETRIES {
-                                tokio::time::sleep(Duration::from_secs(2)).await;
+// download parts in the background and upload them in order,
+// returns the drive item responded to the last part, the uploaded length and the quickXorHash of the uploaded bytes
+async fn upload_parts(
+    downloader: Downloader,
+    uploader: PartUploader,
+    id: i64,
+    mut current_length: u64,
+    total_length: Option<u64>,
+    quick_xor_hash_state: Option<&str>,
+    progress: Arc<Progress>,
+) -> Result<(Option<DriveItem>, u64, Option<String>)> {
+    progress.set_current_length(id, current_length).await?;
+
+    // the hash can only go on from where it stopped, otherwise the file can't be verified
+    let mut quick_xor_hash = match quick_xor_hash_state.and_then(QuickXorHash::from_state) {
+        Some(quick_xor_hash) if quick_xor_hash.length() == current_length => Some(quick_xor_hash),
+        _ if current_length == 0 => Some(QuickXorHash::new()),
+        _ => None,
+    };
 
-                                retries += 1;
+    let part_sizer = PartSizer::new();
+    let mut pipeline = PartPipeline::new(downloader, part_sizer.clone());
 
-                                continue;
-                            }
+    // onedrive requires the total length in every part, so a source of unknown length is held until it ends
+    let (mut part_source, total_length) = match total_length {
+        Some(total_length) => (PartSource::Pipeline(pipeline), total_length),
+        None => {
+            let mut tail_buffer = TailBuffer::new(id);
 
-                            break Err(e);
-                        }
-                    }
-                }
-            };
+            // show the downloaded bytes and speed while nothing can be uploaded
+            let in_flight_length = progress.get_in_flight_length(id)?;
 
-            tokio::select! {
-                result = fut => result.context("failed to get next chunk from tg file downloader"),
-                () = cancellation_token_clone.cancelled() => Err(TaskAbortError.into())
+            while let Some(buffer) = pipeline.next_part().await? {
+                in_flight_length.fetch_add(buffer.len() as u64, Ordering::AcqRel);
+
+                tail_buffer.push(buffer).await?;
             }
-        }));
 
-        current_chunk_num += 1;
+            let total_length = current_length + tail_buffer.length();
 
-        // once reached the max worker number, or the last chunk, wait for the workers to finish
-        // onedrive needs the chunk to be uploaded sequentially in order
-        if current_chunk_num % WORKER_COUNT == 0 || current_chunk_num == total_chunks_num {
-            let mut chunk = Vec::new();
+            // the upload is shown against the total from now on
+            progress.set_total_length(id, total_length).await?;
+            progress.set_current_length(id, current_length).await?;
 
-            while let Some(handle) = work_handles.pop_front() {
-                let mut chunk_part = handle
-                    .await
-                    .context("failed to join handle")??
-                    .ok_or_else(|| anyhow!("failed to get chunk from tg file downloader"))?;
+            (PartSource::Tail(tail_buffer), total_length)
+        }
+    };
 
-                chunk.append(&mut chunk_part);
-            }
+    let upload_response = loop {
+        let buffer = part_source
+            .next_part()
+            .await?
+            .ok_or_else(|| anyhow!("source ended before the whole file was downloaded"))?;
+
+        let start = Instant::now();
+
+        let upload_response = uploader
+            .upload_file(&buffer, current_length, total_length)
+            .await?;
+
+        part_sizer.update(buffer.len(), start.elapsed());
 
-            tracing::debug!("downloaded chunk from telegram");
+        tracing::debug!("uploaded part");
 
-            upload_response = upload_file(
-                &upload_session,
-                &chunk,
-                current_length,
-                total_length,
-                &http_client,
-            )
+        if let Some(quick_xor_hash) = &mut quick_xor_hash {
+            quick_xor_hash.update(&buffer);
+        }
+
+        progress
+            .set_quick_xor_hash_state(id, quick_xor_hash.as_ref().map(QuickXorHash::to_state))
             .await?;
 
-            tracing::debug!("uploaded chunk from telegram");
+        current_length += buffer.len() as u64;
+        progress.set_current_length(id, current_length).await?;
+
+        if current_length >= total_length {
+            break upload_response;
+        }
+    };
+
+    Ok((
+        upload_response,
+        current_length,
+        quick_xor_hash.map(|quick_xor_hash| quick_xor_hash.finalize()),
+    ))
+}
+
+// where the parts to upload come from
+enum PartSource {
+    Pipeline(PartPipeline),
+    Tail(TailBuffer),
+}
 
-            current_length += chunk.len() as u64;
-            progress
-                .set_current_length(id.to_owned(), current_length)
-                .await?;
+impl PartSource {
+    async fn next_part(&mut self) -> Result<Option<Vec<u8>>> {
+        match self {
+            Self::Pipeline(pipeline) => pipeline.next_part().await,
+            Self::Tail(tail_buffer) => tail_buffer.next_part().await,
         }
     }
+}
 
-    let filename = upload_response
-        .ok_or_else(|| anyhow!("failed to get drive item after upload"))?
-        .name
-        .ok_or_else(|| anyhow!("drive item name not found"))?;
+// holds the parts of a source of unknown length until it ends, in memory up to a limit and then on disk
+struct TailBuffer {
+    memory_parts: VecDeque<Vec<u8>>,
+    memory_length: usize,
+    spill_path: String,
+    spill_writer: Option<File>,
+    spill_reader: Option<File>,
+    // lengths of the parts in the spill file, in order
+    spilled_part_lengths: VecDeque<usize>,
+    length: u64,
+}
 
-    tracing::info!(
-        "uploaded file from telegram: {} size: {}",
-        filename,
-        total_length
-    );
+impl TailBuffer {
+    fn new(id: i64) -> Self {
+        Self {
+            memory_parts: VecDeque::new(),
+            memory_length: 0,
+            spill_path: format!("{}/{}{}.part", SESSION_DIR, TAIL_FILE_PREFIX, id),
+            spill_writer: None,
+            spill_reader: None,
+            spilled_part_lengths: VecDeque::new(),
+            length: 0,
+        }
+    }
 
-    Ok(filename)
+    const fn length(&self) -> u64 {
+        self.length
+    }
+
+    async fn push(&mut self, part: Vec<u8>) -> Result<()> {
+        self.length += part.len() as u64;
+
+        if self.spilled_part_lengths.is_empty()
+            && self.memory_length + part.len() <= TAIL_MEMORY_LIMIT
+        {
+            self.memory_length += part.len();
+            self.memory_parts.push_back(part);
+
+            return Ok(());
+        }
+
+        if self.spill_writer.is_none() {
+            tracing::debug!("spill parts of unknown length to {}", self.spill_path);
+
+            self.spill_writer = Some(
+                File::create(&self.spill_path)
+                    .await
+                    .context("failed to create tail file")?,
+            );
+        }
+
+        if let Some(spill_writer) = &mut self.spill_writer {
+            spill_writer
+                .write_all(&part)
+                .await
+                .context("failed to write tail file")?;
+        }
+
+        self.spilled_part_lengths.push_back(part.len());
+
+        Ok(())
+    }
+
+    // parts in memory go first, then the spilled ones
+    async fn next_part(&mut self) -> Result<Option<Vec<u8>>> {
+        if let Some(part) = self.memory_parts.pop_front() {
+            return Ok(Some(part));
+        }
+
+        let Some(part_length) = self.spilled_part_lengths.pop_front() else {
+            return Ok(None);
+        };
+
+        if let Some(mut spill_writer) = self.spill_writer.take() {
+            spill_writer
+                .flush()
+                .await
+                .context("failed to flush tail file")?;
+
+            self.spill_reader = Some(
+                File::open(&self.spill_path)
+                    .await
+                    .context("failed to open tail file")?,
+            );
+        }
+
+        let mut part = vec![0; part_length];
+
+        if let Some(spill_reader) = &mut self.spill_reader {
+            spill_reader
+                .read_exact(&mut part)
+                .await
+                .context("failed to read tail file")?;
+        }
+
+        Ok(Some(part))
+    }
 }
 
-async fn upload_file(
-    upload_session: &UploadSession,
-    buffer: &[u8],
-    current_length: u64,
-    total_length: u64,
-    http_client: &reqwest::Client,
-) -> Result<Option<DriveItem>> {
-    let mut upload_response = None;
-
-    let mut tries = 0;
-
-    loop {
-        tries += 1;
-
-        let result = upload_session
-            .upload_part(
-                buffer.to_owned(),
-                Range {
-                    start: current_length,
-                    end: current_length + buffer.len() as u64,
-                },
-                total_length,
-                http_client,
-            )
-            .await;
-
-        match result {
-            Ok(response) => {
-                upload_response = response;
-
-                break;
-            }
-            Err(e) => {
-                if let Some(status_code) = e.status_code() {
-                    // normal
-                    // 408: Request Timeout
-                    // 500: Internal Server Error
-                    // 502: Bad Gateway
-                    // 503: Service Unavailable
-                    // 504: Gateway Timeout
-                    // 416: Requested Range Not Satisfiable, probably because the fragment has already been received
-                    //
-                    // probably has some problem
-                    // 409: Conflict, probably caused by rename, too many files with the same name uploaded at once
-                    // 404: Not Found, probably because the item has already been uploaded
-
-                    if status_code.as_u16() == 416 {
-                        break;
-                    }
+impl Drop for TailBuffer {
+    fn drop(&mut self) {
+        if self.spill_writer.is_some() || self.spill_reader.is_some() {
+            std::fs::remove_file(&self.spill_path).ok();
+        }
+    }
+}
+
+// compare the hash computed while uploading with the one onedrive computed from the stored bytes
+async fn verify_drive_item(
+    id: i64,
+    account: Option<&str>,
+    drive_item: &DriveItem,
+    quick_xor_hash: Option<String>,
+    progress: &Progress,
+    state: &AppState,
+) -> Result<()> {
+    let Some(quick_xor_hash) = quick_xor_hash else {
+        tracing::info!("file is not verified because the hash was lost when resuming");
+
+        return Ok(());
+    };
+
+    let item_id = drive_item
+        .id
+        .as_ref()
+        .ok_or_else(|| anyhow!("drive item id not found"))?
+        .as_str();
+
+    // hashes may be missing in the response of the last part
+    let expected_quick_xor_hash = match get_quick_xor_hash(drive_item) {
+        Some(expected_quick_xor_hash) => Some(expected_quick_xor_hash),
+        None => state
+            .onedrive
+            .get_item_from_id(item_id, account)
+            .await?
+            .as_ref()
+            .and_then(get_quick_xor_hash),
+    };
+
+    let Some(expected_quick_xor_hash) = expected_quick_xor_hash else {
+        tracing::info!("file is not verified because the drive doesn't provide quickXorHash");
+
+        return Ok(());
+    };
+
+    // the file is kept, the task fails with the mismatch so that /retry uploads it again
+    if expected_quick_xor_hash != quick_xor_hash {
+        return Err(anyhow!(
+            "uploaded file is broken, quickXorHash is {} but {} is expected",
+            expected_quick_xor_hash,
+            quick_xor_hash
+        ));
+    }
+
+    tracing::debug!("file verified with quickXorHash {}", quick_xor_hash);
+
+    progress.set_verified(id).await
+}
+
+// uploads parts of a task to its upload session
+struct PartUploader {
+    upload_url: String,
+    http_client: reqwest::Client,
+    retry_policy: RetryPolicy,
+    limiter: ChatLimiter,
+    conflict_policy: ConflictPolicy,
+    // bytes of the part being uploaded that have been sent
+    in_flight_length: Arc<AtomicU64>,
+}
+
+impl PartUploader {
+    fn new(
+        task: &tasks::Model,
+        http_client: &reqwest::Client,
+        retry_policy: RetryPolicy,
+        progress: &Progress,
+        state: &AppState,
+    ) -> Result<Self> {
+        Ok(Self {
+            upload_url: task.upload_url.clone(),
+            http_client: http_client.clone(),
+            retry_policy,
+            limiter: state.bandwidth_limiter.for_chat(task.chat_id),
+            conflict_policy: task.conflict_policy,
+            in_flight_length: progress.get_in_flight_length(task.id)?,
+        })
+    }
+
+    async fn upload_file(
+        &self,
+        buffer: &[u8],
+        current_length: u64,
+        total_length: u64,
+    ) -> Result<Option<DriveItem>> {
+        let mut upload_response = None;
+
+        let mut retries = 0;
+
+        loop {
+            let result = self.upload_part(buffer, current_length, total_length).await;
+
+            match result {
+                Ok(response) => {
+                    upload_response = response;
+
+                    break;
                 }
+                Err(e) => {
+                    if let Some(status_code) = e.status_code {
+                        // normal
+                        // 408: Request Timeout
+                        // 500: Internal Server Error
+                        // 502: Bad Gateway
+                        // 503: Service Unavailable
+                        // 504: Gateway Timeout
+                        // 416: Requested Range Not Satisfiable, probably because the fragment has already been received
+                        // 429: Too Many Requests, retry after the time in Retry-After
+                        //
+                        // probably has some problem
+                        // 409: Conflict, probably caused by rename, too many files with the same name uploaded at once
+                        // 404: Not Found, probably because the item has already been uploaded
+
+                        if status_code == StatusCode::RANGE_NOT_SATISFIABLE {
+                            break;
+                        }
+
+                        // the file was created by others after the task was inserted
+                        if status_code == StatusCode::CONFLICT
+                            && self.conflict_policy == ConflictPolicy::Skip
+                        {
+                            return Err(TaskSkipError.into());
+                        }
+                    }
+
+                    if retries < self.retry_policy.max_retries {
+                        retries += 1;
+
+                        self.retry_policy.wait(retries, e.retry_after).await;
 
-                if tries < MAX_RETRIES {
-                    tokio::time::sleep(Duration::from_secs(2)).await;
+                        continue;
+                    }
 
-                    continue;
+                    return Err(Error::from(e)).context("failed to upload part");
                 }
+            }
+        }
+
+        Ok(upload_response)
+    }
+
+    async fn upload_part(
+        &self,
+        buffer: &[u8],
+        current_length: u64,
+        total_length: u64,
+    ) -> Result<Option<DriveItem>, UploadPartError> {
+        let content_range = format!(
+            "bytes {}-{}/{}",
+            current_length,
+            current_length + buffer.len() as u64 - 1,
+            total_length
+        );
+
+        // count the bytes taken by the request, so that the progress moves within a large part
+        self.in_flight_length.store(0, Ordering::Release);
 
-                return Err(Error::from(e)).context("failed to upload part");
+        let limiter = self.limiter.clone();
+        let in_flight_length = self.in_flight_length.clone();
+
+        let body_chunks = buffer
+            .chunks(BODY_CHUNK_SIZE)
+            .map(<[u8]>::to_vec)
+            .collect::<Vec<Vec<u8>>>();
+        let body = Body::wrap_stream(stream::iter(body_chunks).then(move |chunk| {
+            let limiter = limiter.clone();
+            let in_flight_length = in_flight_length.clone();
+
+            async move {
+                limiter.acquire(Direction::Upload, chunk.len()).await;
+
+                in_flight_length.fetch_add(chunk.len() as u64, Ordering::AcqRel);
+
+                Ok::<Vec<u8>, std::io::Error>(chunk)
             }
+        }));
+
+        let response = self
+            .http_client
+            .put(&self.upload_url)
+            .header(header::CONTENT_RANGE, content_range)
+            .header(header::CONTENT_LENGTH, buffer.len())
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| UploadPartError::new(e.status(), e.into()))?;
+
+        let status_code = response.status();
+        let retry_after = get_retry_after(response.headers());
+
+        // more parts are expected
+        if status_code == StatusCode::ACCEPTED {
+            return Ok(None);
+        }
+
+        let content = response
+            .bytes()
+            .await
+            .map_err(|e| UploadPartError::new(Some(status_code), e.into()))?;
+
+        if !status_code.is_success() {
+            let mut error = UploadPartError::new(
+                Some(status_code),
+                anyhow!("{}", String::from_utf8_lossy(&content)),
+            );
+            error.retry_after = retry_after;
+
+            return Err(error);
+        }
+
+        let drive_item = serde_json::from_slice::<DriveItem>(&content)
+            .map_err(|e| UploadPartError::new(Some(status_code), e.into()))?;
+
+        Ok(Some(drive_item))
+    }
+}
+
+#[derive(Debug)]
+struct UploadPartError {
+    status_code: Option<StatusCode>,
+    retry_after: Option<Duration>,
+    source: Error,
+}
+
+impl UploadPartError {
+    const fn new(status_code: Option<StatusCode>, source: Error) -> Self {
+        Self {
+            status_code,
+            retry_after: None,
+            source,
         }
     }
+}
+
+impl std::error::Error for UploadPartError {}
 
-    Ok(upload_response)
+impl Display for UploadPartError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.status_code {
+            Some(status_code) => write!(f, "{}: {:#}", status_code, self.source),
+            None => write!(f, "{:#}", self.source),
+        }
+    }
 }
@@ -0,0 +1,32 @@
+/*
+:project: telegram-onedrive
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use sea_orm::{
+    entity::prelude::DeriveEntityModel, ActiveModelBehavior, DerivePrimaryKey, DeriveRelation,
+    EntityTrait, EnumIter, PrimaryKeyTrait,
+};
+
+// media uploaded before, used to skip duplicates no matter which chat they come from
+#[derive(Clone, Debug, DeriveEntityModel)]
+#[sea_orm(table_name = "uploaded_media")]
+pub struct Model {
+    // document:{id} or photo:{id}, telegram keeps the id when the media is forwarded
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub media_key: String,
+    // onedrive item id
+    pub item_id: String,
+    pub size: i64,
+    pub quick_xor_hash: Option<String>,
+    pub web_url: Option<String>,
+    // onedrive account the item was uploaded to, none for media indexed before it was recorded
+    pub account: Option<String>,
+}
+
+#[derive(Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}